@@ -0,0 +1,106 @@
+/*
+ * Copyright © 2019 Romeo Calota
+ *
+ * This program is free software; you can redistribute it and/or
+ * modify it under the terms of the GNU Lesser General Public
+ * License as published by the Free Software Foundation; either
+ * version 2 of the licence, or (at your option) any later version.
+ *
+ * This software is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+ * Lesser General Public License for more details.
+ *
+ * You should have received a copy of the GNU Lesser General Public
+ * License along with this program; if not, see <http://www.gnu.org/licenses/>.
+ *
+ * Author: Romeo Calota
+ */
+
+use std::process;
+
+use virtualinput::{Key, VirtualInput, VirtualInputBuilder};
+
+use crate::dbus::DBusCall;
+use crate::macros::MacroStep;
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Action
+{
+    /* Press and release a key chord on the virtual input device */
+    Keys(Vec<Key>),
+    /* Type text on the virtual input device, following its keyboard layout */
+    Text(String),
+    /* Run a shell command, without waiting for it to finish */
+    Command(String),
+    /* Call a method on the session bus, without waiting for the reply; handled by the backend, which keeps the connection */
+    DBus(DBusCall),
+    /* Switch to the named binding profile, handled by the backend */
+    Profile(String),
+    /* Play steps one after the other, run over time by the backend */
+    Macro(Vec<MacroStep>),
+}
+
+impl Action {
+    /* "keys", "text", "command", "dbus", "profile" or "macro", as written in the configuration */
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Action::Keys(_) => "keys",
+            Action::Text(_) => "text",
+            Action::Command(_) => "command",
+            Action::DBus(_) => "dbus",
+            Action::Profile(_) => "profile",
+            Action::Macro(_) => "macro",
+        }
+    }
+
+    /* Whether running it types text, and so needs the keymap */
+    pub fn types_text(&self) -> bool {
+        match self {
+            Action::Text(_) => true,
+            Action::Macro(steps) => steps.iter().any(|step| matches!(step, MacroStep::Text(_))),
+            _ => false,
+        }
+    }
+
+    /* Fails if the virtual device described lacks a key, button or axis the action needs */
+    pub fn check_virtual_device(&self, device: &VirtualInputBuilder) -> Result<(), String> {
+        match self {
+            Action::Keys(keys) => device.check_keys(keys),
+            Action::Text(_) => device.check_typing(),
+            Action::Macro(steps) => steps.iter().try_for_each(|step| step.check_virtual_device(device)),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn execute(&self, virtual_input: Option<&mut VirtualInput>) -> Result<(), String> {
+        match self {
+            Action::Keys(keys) => {
+                match virtual_input {
+                    Some(virtual_input) => virtual_input.press(keys, true),
+                    None => Err(String::from("No virtual input device available")),
+                }
+            },
+            Action::Text(text) => {
+                match virtual_input {
+                    Some(virtual_input) => virtual_input.type_text(text),
+                    None => Err(String::from("No virtual input device available")),
+                }
+            },
+            Action::Command(command) => {
+                process::Command::new("sh")
+                    .arg("-c")
+                    .arg(command)
+                    .spawn()
+                    .map(|mut child| {
+                        /* Reap the child once it exits so it doesn't linger as a zombie */
+                        std::thread::spawn(move || { let _ = child.wait(); });
+                    })
+                    .map_err(|e| e.to_string())
+            },
+            Action::DBus(_) => Err(String::from("Cannot make a D-Bus call outside of the backend")),
+            Action::Profile(name) => Err(format!("Cannot switch to profile \"{}\" outside of the backend", name)),
+            Action::Macro(_) => Err(String::from("Cannot play a macro outside of the backend")),
+        }
+    }
+}
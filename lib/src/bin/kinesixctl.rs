@@ -0,0 +1,152 @@
+/*
+ * Copyright © 2019 Romeo Calota
+ *
+ * This program is free software; you can redistribute it and/or
+ * modify it under the terms of the GNU Lesser General Public
+ * License as published by the Free Software Foundation; either
+ * version 2 of the licence, or (at your option) any later version.
+ *
+ * This software is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+ * Lesser General Public License for more details.
+ *
+ * You should have received a copy of the GNU Lesser General Public
+ * License along with this program; if not, see <http://www.gnu.org/licenses/>.
+ *
+ * Author: Romeo Calota
+ */
+
+use std::env;
+use std::io::{self, BufRead, BufReader, Write};
+use std::os::unix::net::UnixStream;
+use std::path::PathBuf;
+use std::process;
+use std::thread;
+use std::time::Duration;
+
+use kinesix::device::DeviceKind;
+use kinesix::event_stream;
+use kinesix::ipc;
+use kinesix::KinesixBackend;
+
+const USAGE: &str = "Usage: kinesixctl [--socket PATH] COMMAND
+
+Commands:
+  devices                  list gesture capable devices, as JSON lines
+  watch [KINDS]            print events as JSON lines, optionally only some kinds, e.g.
+                           watch gesture-end,device-added,device-removed,action-result
+  bind GESTURE ACTION      bind a gesture in the default profile until Kinesix exits,
+                           e.g. bind swipe-up:3 keys LeftMeta+PageUp
+  unbind GESTURE
+  profile [NAME]           print the active profile, or switch to another
+  pause | resume           stop and start handling gestures
+  reload                   apply the configuration file again
+
+All commands talk to the running Kinesix; devices and watch work without it too.";
+
+fn fail(message: &str) -> ! {
+    eprintln!("kinesixctl: {}", message);
+    process::exit(1)
+}
+
+/* Prints the replies, returns false if the daemon reported an error */
+fn run_remote(stream: UnixStream, request: &str) -> bool {
+    let mut writer = stream.try_clone().unwrap_or_else(|e| fail(&e.to_string()));
+    if let Err(e) = writeln!(writer, "{}", request) {
+        fail(&e.to_string());
+    }
+
+    let stdout = io::stdout();
+    let mut succeeded = true;
+    for line in BufReader::new(stream).lines() {
+        let line = line.unwrap_or_else(|e| fail(&e.to_string()));
+        if line.starts_with("{\"error\":") {
+            eprintln!("kinesixctl: {}", line);
+            succeeded = false;
+            continue;
+        }
+
+        /* A watch ends when whoever reads the output goes away */
+        if writeln!(stdout.lock(), "{}", line).is_err() { break; }
+    }
+
+    succeeded
+}
+
+fn new_backend() -> KinesixBackend {
+    KinesixBackend::new(|_, _| {}, |_, _| {})
+}
+
+fn standalone_devices() {
+    let mut backend = new_backend();
+    for device in backend.get_valid_device_list() {
+        println!("{}", ipc::device_json(&device, false));
+    }
+}
+
+/* Recognizes gestures on the first touchpad without running any actions */
+fn standalone_watch(filter: &str) {
+    let kinds = event_stream::parse_kinds(filter).unwrap_or_else(|e| fail(&e));
+    let mut backend = new_backend();
+    let touchpad = backend.get_valid_device_list().into_iter().find(|d| d.kind == DeviceKind::Touchpad)
+        .unwrap_or_else(|| fail("No touchpad found"));
+    backend.set_active_device(&touchpad);
+
+    backend.add_event_listener(move |event| {
+        if event_stream::matches(&kinds, event) && writeln!(io::stdout(), "{}", event.to_json()).is_err() {
+            process::exit(0);
+        }
+    });
+
+    loop {
+        backend.dispatch_pending_events();
+        thread::sleep(Duration::from_millis(10));
+    }
+}
+
+fn main() {
+    let mut args: Vec<String> = env::args().skip(1).collect();
+
+    let mut socket_path = ipc::default_socket_path();
+    if args.first().map(|a| a == "--socket").unwrap_or(false) {
+        if args.len() < 2 { fail("--socket needs a path"); }
+        socket_path = Some(PathBuf::from(args.remove(1)));
+        args.remove(0);
+    }
+
+    let command = match args.first() {
+        Some(command) if command != "--help" && command != "-h" => command.as_str(),
+        _ => {
+            println!("{}", USAGE);
+            return;
+        }
+    };
+
+    let expected_arguments = match command {
+        "devices" | "pause" | "resume" | "reload" => 0..=0,
+        "profile" | "watch" => 0..=1,
+        "unbind" => 1..=1,
+        "bind" => 2..=usize::MAX,
+        _ => fail(&format!("Unknown command \"{}\"\n\n{}", command, USAGE)),
+    };
+    if !expected_arguments.contains(&(args.len() - 1)) {
+        fail(&format!("Wrong number of arguments for {}\n\n{}", command, USAGE));
+    }
+
+    let connection = match &socket_path {
+        Some(path) => UnixStream::connect(path)
+            .map_err(|e| format!("Kinesix is not running, or not listening on {}: {}", path.display(), e)),
+        None => Err(String::from("XDG_RUNTIME_DIR is not set, pass --socket with the path Kinesix listens on")),
+    };
+    match connection {
+        Ok(stream) => {
+            if !run_remote(stream, &args.join(" ")) {
+                process::exit(1);
+            }
+        },
+        Err(_) if command == "devices" => standalone_devices(),
+        Err(_) if command == "watch" => standalone_watch(args.get(1).map(|a| a.as_str()).unwrap_or("")),
+        Err(e) => fail(&e),
+    }
+}
@@ -0,0 +1,832 @@
+/*
+ * Copyright © 2019 Romeo Calota
+ *
+ * This program is free software; you can redistribute it and/or
+ * modify it under the terms of the GNU Lesser General Public
+ * License as published by the Free Software Foundation; either
+ * version 2 of the licence, or (at your option) any later version.
+ *
+ * This software is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+ * Lesser General Public License for more details.
+ *
+ * You should have received a copy of the GNU Lesser General Public
+ * License along with this program; if not, see <http://www.gnu.org/licenses/>.
+ *
+ * Author: Romeo Calota
+ */
+
+/*
+ * Configuration file format, one setting per line:
+ *
+ *   [options]
+ *   swipe-cancel-return-ratio = 0.25
+ *   sequence-policy = wait                  # or "immediate"
+ *   use-device-rotation = true
+ *   stroke-button = right                   # hold to draw mouse strokes, or middle, side, extra
+ *   touchscreen-stroke-fingers = 3          # draw strokes on a touchscreen with this many fingers, the
+ *                                           # desktop sees them too; "none" (default) disables them
+ *   template-threshold = 0.8                # minimum score for a drawn shape to match a template
+ *   raw-touchpad = true                     # also read the touchpad through evdev, needed for taps
+ *   grab-touchpad = false                   # take the touchpad from the desktop while bound gestures
+ *                                           # or taps of three or more fingers are performed, implies raw-touchpad;
+ *                                           # such touches are kept whole, so e.g. with only swipe-up:3
+ *                                           # bound the desktop no longer sees any three-finger gesture;
+ *                                           # needed for quick two-finger swipes (swipe-left:2, swipe-right:2)
+ *   keyboard-layout = de(nodeadkeys)        # layout text actions are typed with, the system default if unset
+ *   keymap-file = /path/to/keymap.xkb       # or a complete keymap, as written by xkbcomp
+ *   unicode-input = ctrl-shift-u            # for characters the layout lacks, or "none"
+ *   virtual-device-name = Kinesix           # the device actions are performed with, "dummy" by default
+ *   virtual-device-id = 046d:c52b           # vendor and product, hexadecimal; 0000:0000 by default
+ *   virtual-device-bus = usb                # or pci, bluetooth, i8042; virtual by default
+ *   virtual-device-version = 0111
+ *   virtual-device-keys = LeftMeta PageUp PageDown   # only these, or "all" (default) or "none"
+ *   virtual-device-buttons = left right     # or "none", all five by default
+ *   virtual-device-pointer = false          # relative motion, and the wheel with
+ *   virtual-device-wheel = false            # virtual-device-wheel; both on by default
+ *                                           # every bound action has to be possible with what is enabled
+ *
+ *   [device /dev/input/event4]
+ *   invert-x = true
+ *   rotation = 90
+ *
+ *   [bindings]
+ *   swipe-up:3 = keys LeftMeta+PageUp
+ *   super+swipe-left:3 = keys LeftMeta+LeftArrow
+ *   swipe-down:4 = keys XF86AudioLowerVolume      # keys by name, kernel name (KEY_MUTE) or X keysym
+ *   swipe-up:4 = text Kind regards,               # typed with the keyboard layout
+ *   pinch-in:2 = command xdotool key ctrl+minus
+ *   swipe-right:4 = dbus org.mpris.MediaPlayer2.vlc /org/mpris/MediaPlayer2 org.mpris.MediaPlayer2.Player.Next
+ *   swipe-left:4 = dbus org.gnome.Shell /org/gnome/Shell org.gnome.Shell.Eval 'Main.overview.toggle()'
+ *                                          # on the session bus: destination, object path, interface.method
+ *                                          # and arguments in GVariant text format, e.g. (uint32 5, 'x')
+ *   swipe-up:3 then swipe-left:3 within 800ms = command gnome-terminal
+ *   pad-button:0 = keys LeftControl+Z      # also pad-ring-cw:N, pad-ring-ccw:N,
+ *                                          # pad-strip-up:N and pad-strip-down:N
+ *   tablet-mode-enter = profile touch      # also tablet-mode-exit, lid-close, lid-open
+ *   stroke:DR = keys LeftControl+W         # mouse stroke down then right, from U, D, L and R
+ *   tap:4 = keys LeftMeta                  # also double-tap:N, needs raw-touchpad
+ *   region-tap:corner = command pactl set-sink-mute @DEFAULT_SINK@ toggle
+ *                                          # also region-slide-up:NAME, -down, -left and -right,
+ *                                          # once per step of a slide; needs raw-touchpad, and
+ *                                          # grab-touchpad to keep the touch from the desktop
+ *   shape:circle = command xdg-open .      # stroke matching a template, built in are circle,
+ *                                          # checkmark, caret, triangle, rectangle and zigzag
+ *   swipe-down:3 = macro key-down LeftMeta; sleep 100ms; text terminal; key-up LeftMeta; keys Enter
+ *                                          # steps are separated by ';', which they cannot contain;
+ *                                          # also key-up, click, button-down/-up LEFT, move DX DY,
+ *                                          # scroll V H and command; held keys are released at the end
+ *
+ *   [proportional swipe-vertical:3]         # or swipe-horizontal:N, pinch:N
+ *   step = 40
+ *   acceleration = 0.5
+ *   increase = command pactl set-sink-volume @DEFAULT_SINK@ +2%
+ *   decrease = command pactl set-sink-volume @DEFAULT_SINK@ -2%
+ *
+ *   [pad-mode 1]                            # pad bindings only active in mode 1
+ *   pad-ring-cw:0 = keys LeftControl+Equals
+ *
+ *   [pad-mode 0 group 1]                    # on pads with several mode groups, only in mode 0
+ *   pad-ring-cw:1 = keys PageDown           # of group 1, e.g. the second ring and its buttons
+ *
+ *   [region corner]                         # area of the touchpad as fractions of its size,
+ *   area = 0.8 0.0 1.0 0.2                  # from the left, top, right and bottom
+ *
+ *   [template lightning]                    # recorded shape, overrides a built-in one of the same name
+ *   points = 0.0,-120.5 12.3,-98.0 ...
+ *
+ *   [app firefox]                           # gesture bindings for windows whose WM_CLASS matches, with
+ *   title = *YouTube*                       # '*' and '?', optionally their title too; needs X11, and
+ *   swipe-left:3 = keys LeftControl+PageUp  # global bindings apply to whatever isn't bound here; only
+ *                                           # single swipes and pinches, sequences, proportional and
+ *                                           # other triggers stay in [bindings]
+ *
+ * Binding sections can be suffixed with " @name" to place them in a named profile
+ * instead of the default one, e.g. "[bindings @touch]" or "[pad-mode 1 @touch]".
+ */
+
+use std::env;
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+
+use virtualinput::{Button, Key, UnicodeFallback, VirtualInputBuilder};
+
+use crate::action::Action;
+use crate::focused_window::AppPattern;
+use crate::gesture::{DirectionTransform, Gesture, Rotation};
+use crate::macros::MacroStep;
+use crate::profile::{AppBindings, Profile, DEFAULT_PROFILE};
+use crate::proportional::{ProportionalAction, ProportionalAxis};
+use crate::region::{Region, RegionTrigger};
+use crate::sequence::{AmbiguityPolicy, GestureSequence};
+use crate::stroke::Stroke;
+use crate::switches::SwitchTrigger;
+use crate::tap::Tap;
+use crate::template::{self, StrokeTemplate};
+use crate::tablet_pad::{PadBinding, PadTrigger};
+
+/* Name of the virtual input device unless configured otherwise */
+pub const VIRTUAL_DEVICE_NAME: &str = "dummy";
+
+#[derive(Debug, Clone)]
+pub struct Config
+{
+    pub swipe_cancel_return_ratio: Option<f64>,
+    pub sequence_policy: AmbiguityPolicy,
+    pub use_device_rotation: bool,
+    /* Mouse button that is held to draw strokes, None disables mouse strokes */
+    pub stroke_button: Option<Button>,
+    /* Fingers touchscreen strokes are drawn with, None disables them */
+    pub touchscreen_stroke_fingers: Option<u32>,
+    pub template_threshold: f64,
+    /* Read the touchpad through evdev as well, for what libinput doesn't report */
+    pub raw_touchpad: bool,
+    /* Keep touches with bound finger counts from the desktop, passing everything else through */
+    pub grab_touchpad: bool,
+    /* Layout and variant text is typed with, empty for the system default */
+    pub keyboard_layout: String,
+    pub keyboard_variant: String,
+    /* Complete keymap to use instead of the layout */
+    pub keymap_file: Option<String>,
+    pub unicode_input: UnicodeFallback,
+    /* Identity and capabilities of the device actions are performed with */
+    pub virtual_device: VirtualInputBuilder,
+
+    /* Direction transforms keyed by device path */
+    pub device_transforms: Vec<(String, DirectionTransform)>,
+
+    /* The default profile always comes first */
+    pub profiles: Vec<Profile>,
+
+    /* Recorded shapes, in addition to the built-in ones */
+    pub templates: Vec<StrokeTemplate>,
+
+    /* Touchpad regions, bound by name */
+    pub regions: Vec<Region>,
+}
+
+/* Binding sections carry the index of the profile they belong to */
+enum Section
+{
+    None,
+    Options,
+    Device(usize),
+    Bindings(usize),
+    App(usize, usize),
+    /* Profile, mode and mode group */
+    PadMode(usize, u32, Option<u32>),
+    Proportional(usize, usize),
+    Template(String),
+    Region(String),
+}
+
+impl Config {
+    pub fn new() -> Config {
+        Config {
+            swipe_cancel_return_ratio: None,
+            sequence_policy: AmbiguityPolicy::WaitForSequence,
+            use_device_rotation: false,
+            stroke_button: None,
+            touchscreen_stroke_fingers: None,
+            template_threshold: template::DEFAULT_THRESHOLD,
+            raw_touchpad: false,
+            grab_touchpad: false,
+            keyboard_layout: String::new(),
+            keyboard_variant: String::new(),
+            keymap_file: None,
+            unicode_input: UnicodeFallback::Disabled,
+            virtual_device: VirtualInputBuilder::new(VIRTUAL_DEVICE_NAME),
+            device_transforms: Vec::new(),
+            profiles: vec![Profile::new(DEFAULT_PROFILE)],
+            templates: Vec::new(),
+            regions: Vec::new(),
+        }
+    }
+
+    pub fn default_profile(&self) -> &Profile {
+        &self.profiles[0]
+    }
+
+    pub fn profile(&self, name: &str) -> Option<&Profile> {
+        self.profiles.iter().find(|p| p.name == name)
+    }
+
+    fn profile_index(&mut self, name: &str) -> usize {
+        match self.profiles.iter().position(|p| p.name == name) {
+            Some(index) => index,
+            None => {
+                self.profiles.push(Profile::new(name));
+                self.profiles.len() - 1
+            }
+        }
+    }
+
+    /* $XDG_CONFIG_HOME/kinesix/kinesix.conf, falling back to ~/.config */
+    pub fn default_path() -> Option<PathBuf> {
+        let config_dir = match env::var_os("XDG_CONFIG_HOME") {
+            Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+            _ => PathBuf::from(env::var_os("HOME")?).join(".config"),
+        };
+
+        Some(config_dir.join("kinesix").join("kinesix.conf"))
+    }
+
+    pub fn load(path: &PathBuf) -> Result<Config, String> {
+        let contents = fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        Config::parse(&contents).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    /* Appends a recorded shape to the file at path, creating it if needed */
+    pub fn save_template(path: &PathBuf, template: &StrokeTemplate) -> Result<(), String> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(|e| format!("{}: {}", dir.display(), e))?;
+        }
+
+        fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .and_then(|mut file| write!(file, "\n[template {}]\npoints = {}\n", template.name, template::format_points(&template.points)))
+            .map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    pub fn parse(contents: &str) -> Result<Config, String> {
+        let mut config = Config::new();
+        let mut section = Section::None;
+        let mut section_line = 0;
+
+        for (index, line) in contents.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') { continue; }
+
+            let result = if line.starts_with('[') && line.ends_with(']') {
+                if let Err(e) = config.finish_section(&section) {
+                    return Err(format!("line {}: {}", section_line, e));
+                }
+                section_line = index + 1;
+                config.parse_section(&line[1..line.len() - 1]).map(|s| section = s)
+            } else {
+                match line.find('=') {
+                    Some(separator) => {
+                        let key = line[..separator].trim();
+                        let value = line[separator + 1..].trim();
+                        config.parse_entry(&section, key, value)
+                    },
+                    None => Err(format!("Expected \"key = value\", found \"{}\"", line)),
+                }
+            };
+
+            if let Err(e) = result {
+                return Err(format!("line {}: {}", index + 1, e));
+            }
+        }
+
+        config.finish_section(&section).map_err(|e| format!("line {}: {}", section_line, e))?;
+        config.check_virtual_device()?;
+        Ok(config)
+    }
+
+    /* A device left without what a bound action needs would only fail once the gesture is made */
+    fn check_virtual_device(&self) -> Result<(), String> {
+        for profile in &self.profiles {
+            for action in profile.actions() {
+                action.check_virtual_device(&self.virtual_device)
+                    .map_err(|e| format!("A {} action in profile \"{}\" can't run: {}", action.kind(), profile.name, e))?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /* Checks for settings a section cannot do without */
+    fn finish_section(&self, section: &Section) -> Result<(), String> {
+        if let Section::Proportional(profile, index) = section {
+            /* Placeholders until set, a configured command can't be empty */
+            let action = &self.profiles[*profile].proportional_actions[*index].1;
+            let placeholder = Action::Command(String::new());
+            if action.increase == placeholder || action.decrease == placeholder {
+                return Err(String::from("Proportional actions need both \"increase\" and \"decrease\""));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn parse_section(&mut self, header: &str) -> Result<Section, String> {
+        let mut header = header.trim();
+        let mut profile_name = DEFAULT_PROFILE;
+        /* Only after whitespace, section arguments such as device paths may contain '@' */
+        if let Some(separator) = header.rfind(" @") {
+            profile_name = header[separator + 2..].trim();
+            header = header[..separator].trim();
+        }
+
+        let mut parts = header.splitn(2, char::is_whitespace);
+        let name = parts.next().unwrap_or("");
+        let argument = parts.next().map(|a| a.trim()).unwrap_or("");
+
+        if profile_name.is_empty() {
+            return Err(format!("Missing profile name in \"[{}]\"", header));
+        }
+        if profile_name != DEFAULT_PROFILE && (name == "options" || name == "device" || name == "template" || name == "region") {
+            return Err(format!("\"[{}]\" cannot be placed in a profile", name));
+        }
+        let profile = self.profile_index(profile_name);
+
+        match name {
+            "options" => Ok(Section::Options),
+            "bindings" => Ok(Section::Bindings(profile)),
+            "app" if !argument.is_empty() => {
+                let app_bindings = &mut self.profiles[profile].app_bindings;
+                app_bindings.push(AppBindings { pattern: AppPattern::new(argument), bindings: Vec::new() });
+                Ok(Section::App(profile, app_bindings.len() - 1))
+            },
+            "pad-mode" => {
+                let (mode, group) = match argument.split_once(" group ") {
+                    Some((mode, group)) => (mode.trim(), Some(group.trim())),
+                    None => (argument, None),
+                };
+                let mode = mode.parse::<u32>().map_err(|_| format!("Invalid pad mode \"{}\"", mode))?;
+                let group = match group {
+                    Some(group) => Some(group.parse::<u32>().map_err(|_| format!("Invalid pad mode group \"{}\"", group))?),
+                    None => None,
+                };
+                Ok(Section::PadMode(profile, mode, group))
+            },
+            "device" if !argument.is_empty() => {
+                self.device_transforms.push((argument.to_string(), DirectionTransform::identity()));
+                Ok(Section::Device(self.device_transforms.len() - 1))
+            },
+            "template" if !argument.is_empty() => Ok(Section::Template(argument.to_string())),
+            "region" if !argument.is_empty() => Ok(Section::Region(argument.to_string())),
+            "proportional" => {
+                let (axis, finger_count) = parse_proportional_trigger(argument)?;
+                let action = ProportionalAction::new(axis, Action::Command(String::new()), Action::Command(String::new()));
+                let proportional_actions = &mut self.profiles[profile].proportional_actions;
+                proportional_actions.push((finger_count, action));
+                Ok(Section::Proportional(profile, proportional_actions.len() - 1))
+            },
+            _ => Err(format!("Unknown section \"[{}]\"", header)),
+        }
+    }
+
+    fn parse_entry(&mut self, section: &Section, key: &str, value: &str) -> Result<(), String> {
+        match section {
+            Section::None => Err(String::from("Setting outside of a section")),
+            Section::Options => {
+                match key {
+                    "swipe-cancel-return-ratio" => {
+                        self.swipe_cancel_return_ratio = Some(parse_number(value)?);
+                    },
+                    "sequence-policy" => {
+                        self.sequence_policy = match value {
+                            "wait" => AmbiguityPolicy::WaitForSequence,
+                            "immediate" => AmbiguityPolicy::FireImmediately,
+                            _ => return Err(format!("Unknown sequence policy \"{}\"", value)),
+                        };
+                    },
+                    "use-device-rotation" => self.use_device_rotation = parse_bool(value)?,
+                    "raw-touchpad" => self.raw_touchpad = parse_bool(value)?,
+                    "grab-touchpad" => self.grab_touchpad = parse_bool(value)?,
+                    "keyboard-layout" => {
+                        let (layout, variant) = match value.find('(') {
+                            Some(start) if value.ends_with(')') => (&value[..start], &value[start + 1..value.len() - 1]),
+                            _ => (value, ""),
+                        };
+                        self.keyboard_layout = layout.trim().to_string();
+                        self.keyboard_variant = variant.trim().to_string();
+                    },
+                    "keymap-file" => self.keymap_file = Some(value.to_string()),
+                    "unicode-input" => self.unicode_input = value.parse()?,
+                    "virtual-device-name" => self.virtual_device.name = value.to_string(),
+                    "virtual-device-id" => {
+                        let (vendor_id, product_id) = value.split_once(':')
+                            .ok_or_else(|| format!("Expected VENDOR:PRODUCT, found \"{}\"", value))?;
+                        self.virtual_device.vendor_id = parse_hex_id(vendor_id)?;
+                        self.virtual_device.product_id = parse_hex_id(product_id)?;
+                    },
+                    "virtual-device-bus" => self.virtual_device.bus_type = value.parse()?,
+                    "virtual-device-version" => self.virtual_device.version = parse_hex_id(value)?,
+                    "virtual-device-keys" => {
+                        self.virtual_device.keys = match value {
+                            "all" => Key::all().collect(),
+                            "none" => Vec::new(),
+                            _ => value.split_whitespace().map(|k| k.parse::<Key>()).collect::<Result<Vec<Key>, String>>()?,
+                        };
+                    },
+                    "virtual-device-buttons" => {
+                        self.virtual_device.buttons = match value {
+                            "none" => Vec::new(),
+                            _ => value.split_whitespace().map(|b| b.parse::<Button>()).collect::<Result<Vec<Button>, String>>()?,
+                        };
+                    },
+                    "virtual-device-pointer" => self.virtual_device.pointer_motion = parse_bool(value)?,
+                    "virtual-device-wheel" => self.virtual_device.wheel = parse_bool(value)?,
+                    "template-threshold" => self.template_threshold = parse_number(value)?,
+                    "stroke-button" => {
+                        self.stroke_button = match value {
+                            "none" => None,
+                            _ => match value.parse()? {
+                                /* Holding it back would leave the mouse unable to click, drag or select */
+                                Button::Left => return Err(String::from("The left button can't be the stroke button")),
+                                button => Some(button),
+                            },
+                        };
+                    },
+                    "touchscreen-stroke-fingers" => {
+                        self.touchscreen_stroke_fingers = match value {
+                            "none" => None,
+                            _ => match value.parse::<u32>() {
+                                Ok(fingers) if fingers > 0 => Some(fingers),
+                                _ => return Err(format!("Invalid finger count \"{}\"", value)),
+                            },
+                        };
+                    },
+                    _ => return Err(format!("Unknown option \"{}\"", key)),
+                }
+                Ok(())
+            },
+            Section::Device(index) => {
+                let transform = &mut self.device_transforms[*index].1;
+                match key {
+                    "invert-x" => transform.invert_x = parse_bool(value)?,
+                    "invert-y" => transform.invert_y = parse_bool(value)?,
+                    "rotation" => {
+                        transform.rotation = match value {
+                            "0" => Rotation::None,
+                            "90" => Rotation::Rotate90,
+                            "180" => Rotation::Rotate180,
+                            "270" => Rotation::Rotate270,
+                            _ => return Err(format!("Rotation must be 0, 90, 180 or 270, found \"{}\"", value)),
+                        };
+                    },
+                    _ => return Err(format!("Unknown device setting \"{}\"", key)),
+                }
+                Ok(())
+            },
+            Section::Bindings(profile) => {
+                let profile = &mut self.profiles[*profile];
+                let action = parse_action(value)?;
+                if key.starts_with("pad-") {
+                    profile.pad_bindings.push(PadBinding { trigger: key.parse::<PadTrigger>()?, group: None, mode: None, action });
+                } else if key.starts_with("tap:") || key.starts_with("double-tap:") {
+                    profile.tap_bindings.push((key.parse::<Tap>()?, action));
+                } else if key.starts_with("region-") {
+                    profile.region_bindings.push((key.parse::<RegionTrigger>()?, action));
+                } else if let Some(shape) = key.strip_prefix("shape:") {
+                    profile.shape_bindings.push((shape.to_string(), action));
+                } else if let Some(stroke) = key.strip_prefix("stroke:") {
+                    profile.stroke_bindings.push((stroke.parse::<Stroke>()?, action));
+                } else if key.starts_with("lid-") || key.starts_with("tablet-mode-") {
+                    profile.switch_bindings.push((key.parse::<SwitchTrigger>()?, action));
+                } else if key.split_whitespace().count() > 1 {
+                    profile.sequences.push((key.parse::<GestureSequence>()?, action));
+                } else {
+                    profile.bindings.push((key.parse::<Gesture>()?, action));
+                }
+                Ok(())
+            },
+            Section::App(profile, index) => {
+                let app = &mut self.profiles[*profile].app_bindings[*index];
+                match key {
+                    "title" => app.pattern.title = Some(value.to_string()),
+                    _ if key.split_whitespace().count() > 1 =>
+                        return Err(format!("Gesture sequences can't be bound per application, put \"{}\" in [bindings]", key)),
+                    _ => app.bindings.push((key.parse::<Gesture>()?, parse_action(value)?)),
+                }
+                Ok(())
+            },
+            Section::PadMode(profile, mode, group) => {
+                let action = parse_action(value)?;
+                let binding = PadBinding { trigger: key.parse::<PadTrigger>()?, group: *group, mode: Some(*mode), action };
+                self.profiles[*profile].pad_bindings.push(binding);
+                Ok(())
+            },
+            Section::Proportional(profile, index) => {
+                let action = &mut self.profiles[*profile].proportional_actions[*index].1;
+                match key {
+                    "step" => action.step = parse_number(value)?,
+                    "acceleration" => action.acceleration = parse_number(value)?,
+                    "increase" => action.increase = parse_action(value)?,
+                    "decrease" => action.decrease = parse_action(value)?,
+                    _ => return Err(format!("Unknown proportional setting \"{}\"", key)),
+                }
+                Ok(())
+            },
+            Section::Template(name) => {
+                match key {
+                    "points" => {
+                        let template = StrokeTemplate::new(name, &template::parse_points(value)?)?;
+                        self.templates.retain(|t| t.name != template.name);
+                        self.templates.push(template);
+                    },
+                    _ => return Err(format!("Unknown template setting \"{}\"", key)),
+                }
+                Ok(())
+            },
+            Section::Region(name) => {
+                match key {
+                    "area" => {
+                        let region = Region::new(name, value)?;
+                        self.regions.retain(|r| r.name != region.name);
+                        self.regions.push(region);
+                    },
+                    _ => return Err(format!("Unknown region setting \"{}\"", key)),
+                }
+                Ok(())
+            },
+        }
+    }
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config::new()
+    }
+}
+
+fn parse_number(value: &str) -> Result<f64, String> {
+    value.parse::<f64>().map_err(|_| format!("Invalid number \"{}\"", value))
+}
+
+/* Hexadecimal as lsusb prints it, with or without 0x */
+fn parse_hex_id(value: &str) -> Result<u16, String> {
+    let digits = value.trim().trim_start_matches("0x");
+    u16::from_str_radix(digits, 16).map_err(|_| format!("Invalid hexadecimal id \"{}\"", value.trim()))
+}
+
+fn parse_bool(value: &str) -> Result<bool, String> {
+    match value {
+        "true" | "yes" | "1" => Ok(true),
+        "false" | "no" | "0" => Ok(false),
+        _ => Err(format!("Invalid boolean \"{}\"", value)),
+    }
+}
+
+fn parse_proportional_trigger(trigger: &str) -> Result<(ProportionalAxis, i32), String> {
+    let mut parts = trigger.splitn(2, ':');
+    let axis = match parts.next().unwrap_or("") {
+        "swipe-horizontal" => ProportionalAxis::Horizontal,
+        "swipe-vertical" => ProportionalAxis::Vertical,
+        "pinch" => ProportionalAxis::Scale,
+        _ => return Err(format!("Unknown proportional trigger \"{}\"", trigger)),
+    };
+    let finger_count = parts.next()
+        .and_then(|count| count.parse::<i32>().ok())
+        .ok_or_else(|| format!("Missing or invalid finger count in \"{}\"", trigger))?;
+
+    Ok((axis, finger_count))
+}
+
+/* "keys LeftControl+C" (any name Key parses), "text <text to type>", "command <shell command>", "profile <name>",
+ * "dbus <call>" as DBusCall parses it or "macro <step>; <step>; ..." with steps as MacroStep parses them */
+pub fn parse_action(value: &str) -> Result<Action, String> {
+    let mut parts = value.splitn(2, char::is_whitespace);
+    let kind = parts.next().unwrap_or("");
+    let argument = parts.next().map(|a| a.trim()).unwrap_or("");
+
+    if argument.is_empty() {
+        return Err(format!("Missing argument for action \"{}\"", value));
+    }
+
+    match kind {
+        "keys" => {
+            let keys: Result<Vec<Key>, String> = argument.split('+').map(|k| k.parse::<Key>()).collect();
+            Ok(Action::Keys(keys?))
+        },
+        "text" => Ok(Action::Text(argument.to_string())),
+        "command" => Ok(Action::Command(argument.to_string())),
+        "dbus" => Ok(Action::DBus(argument.parse()?)),
+        "profile" => Ok(Action::Profile(argument.to_string())),
+        "macro" => {
+            let steps: Result<Vec<MacroStep>, String> = argument.split(';').map(|s| s.parse::<MacroStep>()).collect();
+            Ok(Action::Macro(steps?))
+        },
+        _ => Err(format!("Unknown action \"{}\"", kind)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dbus::DBusCall;
+    use virtualinput::BusType;
+
+    #[test]
+    fn parses_configuration() {
+        let config = Config::parse("
+            # Comment
+            [options]
+            swipe-cancel-return-ratio = 0.3
+            sequence-policy = immediate
+            stroke-button = middle
+            touchscreen-stroke-fingers = 2
+            keyboard-layout = de(nodeadkeys)
+            unicode-input = ctrl-shift-u
+            virtual-device-id = 046d:C52B
+            virtual-device-bus = usb
+
+            [device /dev/input/event4]
+            invert-y = true
+            rotation = 270
+
+            [device /dev/input/by-id/usb-Vendor@Touchpad-event-mouse]
+            invert-x = true
+
+            [bindings]
+            swipe-up:3 = keys LeftMeta+PageUp
+            swipe-up:3 then swipe-left:3 within 600ms = command gnome-terminal
+            swipe-down:4 = text Kind regards,
+            pinch-out:4 = dbus org.gnome.Shell /org/gnome/Shell org.gnome.Shell.Eval 'Main.overview.toggle()'
+            pinch-in:4 = macro keys LeftMeta; sleep 100ms; text terminal; keys Enter
+
+            pad-strip-up:0 = keys PageUp
+            tablet-mode-enter = profile touch
+            stroke:UL = keys LeftAlt+LeftArrow
+            shape:lightning = command true
+            double-tap:4 = keys LeftMeta
+            region-slide-up:edge = keys PageUp
+
+            [region edge]
+            area = 0.9 0 1 1
+
+            [template lightning]
+            points = 0,0 40,50 10,50 50,100
+
+            [pad-mode 2]
+            pad-button:1 = command true
+
+            [pad-mode 0 group 1]
+            pad-ring-cw:1 = keys PageDown
+
+            [bindings @touch]
+            tablet-mode-exit = profile default
+            swipe-up:3 = keys Escape
+
+            [proportional pinch:2]
+            step = 0.2
+            increase = keys LeftControl+Equals
+            decrease = keys LeftControl+Minus
+        ").unwrap();
+
+        assert_eq!(config.swipe_cancel_return_ratio, Some(0.3));
+        assert_eq!(config.sequence_policy, AmbiguityPolicy::FireImmediately);
+        assert_eq!(config.stroke_button, Some(Button::Middle));
+        assert_eq!(config.touchscreen_stroke_fingers, Some(2));
+        assert_eq!((config.keyboard_layout.as_str(), config.keyboard_variant.as_str()), ("de", "nodeadkeys"));
+        assert_eq!(config.unicode_input, UnicodeFallback::CtrlShiftU);
+        assert_eq!(config.virtual_device, VirtualInputBuilder::new(VIRTUAL_DEVICE_NAME)
+            .id(0x046d, 0xc52b)
+            .bus_type(BusType::Usb));
+        assert_eq!(config.templates[0].name, "lightning");
+        assert_eq!(config.regions[0].left, 0.9);
+        assert_eq!(config.device_transforms[0].0, "/dev/input/event4");
+        assert_eq!(config.device_transforms[0].1.rotation, Rotation::Rotate270);
+        assert!(config.device_transforms[0].1.invert_y);
+
+        let profile = config.default_profile();
+        assert_eq!(profile.bindings[0], ("swipe-up:3".parse().unwrap(), Action::Keys(vec![Key::LeftMeta, Key::PageUp])));
+        assert_eq!(profile.bindings[1].1, Action::Text(String::from("Kind regards,")));
+        assert_eq!(profile.bindings[2].1, Action::DBus(DBusCall {
+            destination: String::from("org.gnome.Shell"),
+            object_path: String::from("/org/gnome/Shell"),
+            interface: String::from("org.gnome.Shell"),
+            method: String::from("Eval"),
+            arguments: String::from("'Main.overview.toggle()'"),
+        }));
+        assert_eq!(profile.bindings[3].1, Action::Macro(vec![
+            MacroStep::Keys(vec![Key::LeftMeta]),
+            MacroStep::Sleep(std::time::Duration::from_millis(100)),
+            MacroStep::Text(String::from("terminal")),
+            MacroStep::Keys(vec![Key::Enter]),
+        ]));
+        assert_eq!(profile.sequences[0].1, Action::Command(String::from("gnome-terminal")));
+        assert_eq!(profile.sequences[0].0.gestures.len(), 2);
+        assert_eq!(profile.pad_bindings[0].trigger, PadTrigger::StripUp(0));
+        assert_eq!(profile.pad_bindings[0].mode, None);
+        assert_eq!(profile.pad_bindings[1].mode, Some(2));
+        assert_eq!(profile.pad_bindings[1].group, None);
+        assert_eq!(profile.pad_bindings[2].group, Some(1));
+        assert_eq!(profile.pad_bindings[2].mode, Some(0));
+        assert_eq!(profile.stroke_action(&"UL".parse().unwrap()), Some(&Action::Keys(vec![Key::LeftAlt, Key::LeftArrow])));
+        assert_eq!(profile.tap_action("double-tap:4".parse().unwrap()), Some(&Action::Keys(vec![Key::LeftMeta])));
+        assert_eq!(profile.region_bindings[0].0, "region-slide-up:edge".parse().unwrap());
+        assert_eq!(profile.shape_action("lightning"), Some(&Action::Command(String::from("true"))));
+        assert_eq!(profile.switch_action("tablet-mode-enter".parse().unwrap()), Some(&Action::Profile(String::from("touch"))));
+
+        assert_eq!(config.device_transforms[1].0, "/dev/input/by-id/usb-Vendor@Touchpad-event-mouse");
+        assert!(config.profile("Touchpad-event-mouse").is_none());
+
+        let touch = config.profile("touch").unwrap();
+        assert_eq!(touch.bindings[0], ("swipe-up:3".parse().unwrap(), Action::Keys(vec![Key::Escape])));
+        assert_eq!(touch.switch_bindings.len(), 1);
+
+        let (finger_count, proportional) = &profile.proportional_actions[0];
+        assert_eq!(*finger_count, 2);
+        assert_eq!(proportional.axis, ProportionalAxis::Scale);
+        assert_eq!(proportional.step, 0.2);
+        assert_eq!(proportional.decrease, Action::Keys(vec![Key::LeftControl, Key::Minus]));
+    }
+
+    #[test]
+    fn checks_actions_against_virtual_device() {
+        let config = Config::parse("
+            [options]
+            virtual-device-keys = LeftMeta PageUp KEY_ESC
+            virtual-device-buttons = none
+
+            [bindings]
+            swipe-up:3 = keys LeftMeta+PageUp
+            swipe-down:3 = text ok
+        ").unwrap();
+        assert_eq!(config.virtual_device, VirtualInputBuilder::new(VIRTUAL_DEVICE_NAME)
+            .keys(&[Key::LeftMeta, Key::PageUp, Key::Escape])
+            .buttons(&[]));
+
+        let error = |contents: &str| Config::parse(contents).err().unwrap_or_default();
+        assert_eq!(error("
+            [options]
+            virtual-device-keys = LeftMeta
+
+            [bindings @work]
+            swipe-up:3 = keys LeftMeta+PageUp
+        "), "A keys action in profile \"work\" can't run: Key PageUp is not enabled on the virtual device");
+        assert!(error("
+            [options]
+            virtual-device-buttons = left
+            virtual-device-wheel = false
+
+            [proportional swipe-vertical:2]
+            increase = macro scroll 1 0
+            decrease = macro click left
+        ").contains("The wheel is not enabled"));
+        assert!(error("
+            [options]
+            virtual-device-keys = none
+
+            [bindings]
+            tap:3 = text hello
+        ").contains("No keys are enabled"));
+    }
+
+    #[test]
+    fn prefers_bindings_of_the_focused_application() {
+        use crate::focused_window::WindowInfo;
+
+        let config = Config::parse("
+            [bindings]
+            swipe-left:3 = keys LeftMeta+PageUp
+            swipe-right:3 = keys LeftMeta+PageDown
+
+            [app firefox]
+            title = *YouTube*
+            swipe-right:3 = keys L
+
+            [app firefox]
+            swipe-left:3 = keys LeftControl+PageUp
+            swipe-right:3 = keys LeftControl+PageDown
+        ").unwrap();
+
+        let profile = config.default_profile();
+        let window = |class: &str, title: &str| WindowInfo { instance: String::from("Navigator"), class: class.to_string(), title: title.to_string() };
+        let action = |window: Option<&WindowInfo>, gesture: &str| profile.window_gesture_action(window, gesture.parse().unwrap()).cloned();
+
+        let browser = window("firefox", "Inbox");
+        let video = window("firefox", "Music - YouTube");
+        let terminal = window("gnome-terminal-server", "~");
+
+        assert_eq!(action(Some(&browser), "swipe-left:3"), Some(Action::Keys(vec![Key::LeftControl, Key::PageUp])));
+        assert_eq!(action(Some(&browser), "swipe-right:3"), Some(Action::Keys(vec![Key::LeftControl, Key::PageDown])));
+        assert_eq!(action(Some(&video), "swipe-right:3"), Some(Action::Keys(vec![Key::L])));
+        assert_eq!(action(Some(&video), "swipe-left:3"), Some(Action::Keys(vec![Key::LeftControl, Key::PageUp])));
+        assert_eq!(action(Some(&terminal), "swipe-left:3"), Some(Action::Keys(vec![Key::LeftMeta, Key::PageUp])));
+        assert_eq!(action(None, "swipe-left:3"), Some(Action::Keys(vec![Key::LeftMeta, Key::PageUp])));
+        assert_eq!(action(Some(&browser), "swipe-up:3"), None);
+
+        assert!(Config::parse("[app firefox]
+tap:3 = keys L").is_err());
+        let error = Config::parse("[app firefox]
+swipe-left:3 swipe-right:3 = keys L").unwrap_err();
+        assert!(error.contains("[bindings]"));
+        assert!(Config::parse("[app]").is_err());
+    }
+
+    #[test]
+    fn reports_line_of_error() {
+        let error = Config::parse("[bindings]\nswipe-up:3 = keys Hyper").unwrap_err();
+        assert!(error.starts_with("line 2:"));
+
+        let error = Config::parse("[options @touch]").unwrap_err();
+        assert!(error.starts_with("line 1:"));
+
+        let error = Config::parse("[proportional pinch:2]\nincrease = keys LeftControl+Equals\n\n[bindings]").unwrap_err();
+        assert!(error.starts_with("line 1:"));
+        let error = Config::parse("[bindings]\n[proportional swipe-vertical:3]\ndecrease = keys VolumeDown").unwrap_err();
+        assert!(error.starts_with("line 2:"));
+
+        let error = Config::parse("[options]\nstroke-button = left").unwrap_err();
+        assert!(error.starts_with("line 2:"));
+    }
+}
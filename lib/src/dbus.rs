@@ -0,0 +1,427 @@
+/*
+ * Copyright © 2019 Romeo Calota
+ *
+ * This program is free software; you can redistribute it and/or
+ * modify it under the terms of the GNU Lesser General Public
+ * License as published by the Free Software Foundation; either
+ * version 2 of the licence, or (at your option) any later version.
+ *
+ * This software is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+ * Lesser General Public License for more details.
+ *
+ * You should have received a copy of the GNU Lesser General Public
+ * License along with this program; if not, see <http://www.gnu.org/licenses/>.
+ *
+ * Author: Romeo Calota
+ */
+
+use std::ffi::{CStr, CString};
+use std::str::FromStr;
+
+use libc;
+
+const G_BUS_TYPE_SESSION: libc::c_int = 2;
+const G_DBUS_CONNECTION_FLAGS_AUTHENTICATION_CLIENT: libc::c_int = 1 << 0;
+const G_DBUS_CONNECTION_FLAGS_MESSAGE_BUS_CONNECTION: libc::c_int = 1 << 3;
+const G_DBUS_CALL_FLAGS_NONE: libc::c_int = 0;
+
+#[repr(C)]
+pub(crate) struct GError
+{
+    domain: u32,
+    code: libc::c_int,
+    message: *mut libc::c_char,
+}
+
+#[link(name = "gio-2.0")]
+#[link(name = "gobject-2.0")]
+#[link(name = "glib-2.0")]
+extern "C" {
+    #[no_mangle]
+    fn g_free(memory: *mut libc::c_void);
+
+    #[no_mangle]
+    fn g_error_free(error: *mut GError);
+
+    #[no_mangle]
+    fn g_object_unref(object: *mut libc::c_void);
+
+    #[no_mangle]
+    fn g_variant_parse(variant_type: *const libc::c_void, text: *const libc::c_char, limit: *const libc::c_char, endptr: *mut *const libc::c_char, error: *mut *mut GError) -> *mut libc::c_void;
+
+    #[no_mangle]
+    fn g_variant_get_type_string(value: *mut libc::c_void) -> *const libc::c_char;
+
+    #[no_mangle]
+    fn g_variant_new_tuple(children: *const *mut libc::c_void, n_children: libc::size_t) -> *mut libc::c_void;
+
+    #[no_mangle]
+    fn g_variant_ref_sink(value: *mut libc::c_void) -> *mut libc::c_void;
+
+    #[no_mangle]
+    fn g_variant_unref(value: *mut libc::c_void);
+
+    #[no_mangle]
+    fn g_variant_print(value: *mut libc::c_void, type_annotate: libc::c_int) -> *mut libc::c_char;
+
+    #[no_mangle]
+    fn g_dbus_is_name(string: *const libc::c_char) -> libc::c_int;
+
+    #[no_mangle]
+    fn g_dbus_is_interface_name(string: *const libc::c_char) -> libc::c_int;
+
+    #[no_mangle]
+    fn g_dbus_is_member_name(string: *const libc::c_char) -> libc::c_int;
+
+    #[no_mangle]
+    fn g_variant_is_object_path(string: *const libc::c_char) -> libc::c_int;
+
+    #[no_mangle]
+    fn g_bus_get_sync(bus_type: libc::c_int, cancellable: *mut libc::c_void, error: *mut *mut GError) -> *mut libc::c_void;
+
+    #[no_mangle]
+    fn g_dbus_connection_new_for_address_sync(address: *const libc::c_char, flags: libc::c_int, observer: *mut libc::c_void, cancellable: *mut libc::c_void, error: *mut *mut GError) -> *mut libc::c_void;
+
+    #[no_mangle]
+    fn g_dbus_connection_call(
+        connection: *mut libc::c_void,
+        bus_name: *const libc::c_char,
+        object_path: *const libc::c_char,
+        interface_name: *const libc::c_char,
+        method_name: *const libc::c_char,
+        parameters: *mut libc::c_void,
+        reply_type: *const libc::c_void,
+        flags: libc::c_int,
+        timeout_msec: libc::c_int,
+        cancellable: *mut libc::c_void,
+        callback: unsafe extern "C" fn(*mut libc::c_void, *mut libc::c_void, *mut libc::c_void),
+        user_data: *mut libc::c_void);
+
+    #[no_mangle]
+    fn g_dbus_connection_call_finish(connection: *mut libc::c_void, result: *mut libc::c_void, error: *mut *mut GError) -> *mut libc::c_void;
+}
+
+/* Takes the message out of a GError and frees it */
+pub(crate) unsafe fn take_error(error: *mut GError) -> String {
+    if error.is_null() { return String::from("Unknown error"); }
+
+    let message = CStr::from_ptr((*error).message).to_string_lossy().into_owned();
+    g_error_free(error);
+    message
+}
+
+pub(crate) fn to_cstring(value: &str) -> Result<CString, String> {
+    CString::new(value).map_err(|e| e.to_string())
+}
+
+/* An owned reference to a GVariant */
+pub(crate) struct Variant
+{
+    pub(crate) value: *mut libc::c_void,
+}
+
+impl Variant {
+    /* GVariant text format, e.g. "('Main.overview.toggle()',)" or "(uint32 5, 'x')". Method arguments are
+     * always a tuple, anything else becomes the single argument */
+    fn parse_arguments(text: &str) -> Result<Variant, String> {
+        let text_cstr = to_cstring(text)?;
+
+        unsafe {
+            let mut error = 0 as *mut GError;
+            let value = g_variant_parse(0 as *const libc::c_void, text_cstr.as_ptr(), 0 as *const libc::c_char, 0 as *mut *const libc::c_char, &mut error);
+            if value.is_null() {
+                return Err(format!("Invalid D-Bus arguments \"{}\": {}", text, take_error(error)));
+            }
+            let parsed = Variant { value };
+
+            if CStr::from_ptr(g_variant_get_type_string(parsed.value)).to_bytes().starts_with(b"(") {
+                return Ok(parsed);
+            }
+
+            let children = [parsed.value];
+            Ok(Variant { value: g_variant_ref_sink(g_variant_new_tuple(children.as_ptr(), 1)) })
+        }
+    }
+
+    pub(crate) fn print(&self) -> String {
+        unsafe {
+            let text = g_variant_print(self.value, 0);
+            let printed = CStr::from_ptr(text).to_string_lossy().into_owned();
+            g_free(text as *mut libc::c_void);
+            printed
+        }
+    }
+}
+
+impl Drop for Variant {
+    fn drop(&mut self) {
+        unsafe { g_variant_unref(self.value); }
+    }
+}
+
+/* A connection to a message bus */
+pub struct DBusConnection
+{
+    pub(crate) connection: *mut libc::c_void,
+}
+
+impl DBusConnection {
+    /* The shared session bus connection */
+    pub fn session() -> Result<DBusConnection, String> {
+        unsafe {
+            let mut error = 0 as *mut GError;
+            let connection = g_bus_get_sync(G_BUS_TYPE_SESSION, 0 as *mut libc::c_void, &mut error);
+            if connection.is_null() {
+                return Err(format!("Failed to connect to the session bus: {}", take_error(error)));
+            }
+
+            Ok(DBusConnection { connection })
+        }
+    }
+
+    /* A bus at an address like "unix:path=/run/user/1000/bus", e.g. one started for testing */
+    pub fn for_address(address: &str) -> Result<DBusConnection, String> {
+        let address_cstr = to_cstring(address)?;
+        let flags = G_DBUS_CONNECTION_FLAGS_AUTHENTICATION_CLIENT | G_DBUS_CONNECTION_FLAGS_MESSAGE_BUS_CONNECTION;
+
+        unsafe {
+            let mut error = 0 as *mut GError;
+            let connection = g_dbus_connection_new_for_address_sync(address_cstr.as_ptr(), flags, 0 as *mut libc::c_void, 0 as *mut libc::c_void, &mut error);
+            if connection.is_null() {
+                return Err(format!("Failed to connect to {}: {}", address, take_error(error)));
+            }
+
+            Ok(DBusConnection { connection })
+        }
+    }
+}
+
+impl Drop for DBusConnection {
+    fn drop(&mut self) {
+        unsafe { g_object_unref(self.connection); }
+    }
+}
+
+/* Receives the reply, printed in GVariant text format, or the error */
+type ReplyHandler = Box<dyn FnOnce(Result<String, String>)>;
+
+unsafe extern "C" fn on_call_finished(connection: *mut libc::c_void, result: *mut libc::c_void, data: *mut libc::c_void) {
+    let on_reply = Box::from_raw(data as *mut ReplyHandler);
+
+    let mut error = 0 as *mut GError;
+    let reply = g_dbus_connection_call_finish(connection, result, &mut error);
+    if reply.is_null() {
+        on_reply(Err(take_error(error)));
+    } else {
+        on_reply(Ok(Variant { value: reply }.print()));
+    }
+}
+
+/*
+ * A method call, written as in the config file: destination, object path, interface and method joined by a
+ * dot, then the arguments in GVariant text format, e.g.
+ * "org.gnome.Shell /org/gnome/Shell org.gnome.Shell.Eval 'Main.overview.toggle()'"
+ */
+#[derive(Debug, PartialEq, Clone)]
+pub struct DBusCall
+{
+    pub destination: String,
+    pub object_path: String,
+    pub interface: String,
+    pub method: String,
+    /* GVariant text format, "()" when there are none */
+    pub arguments: String,
+}
+
+impl DBusCall {
+    /* Calls the method without waiting for the reply; errors in the reply are logged */
+    pub fn call(&self, connection: &DBusConnection) -> Result<(), String> {
+        let description = format!("{}.{}", self.interface, self.method);
+
+        self.call_on(connection, move |reply| {
+            if let Err(e) = reply {
+                println!("D-Bus call {} failed: {}", description, e);
+            }
+        })
+    }
+
+    /* The reply is handled from the thread default main context once it arrives */
+    pub fn call_on<OnReply: 'static + FnOnce(Result<String, String>)>(&self, connection: &DBusConnection, on_reply: OnReply) -> Result<(), String> {
+        let arguments = Variant::parse_arguments(&self.arguments)?;
+        let destination = to_cstring(&self.destination)?;
+        let object_path = to_cstring(&self.object_path)?;
+        let interface = to_cstring(&self.interface)?;
+        let method = to_cstring(&self.method)?;
+
+        let on_reply: ReplyHandler = Box::new(on_reply);
+        unsafe {
+            g_dbus_connection_call(
+                connection.connection,
+                destination.as_ptr(),
+                object_path.as_ptr(),
+                interface.as_ptr(),
+                method.as_ptr(),
+                arguments.value,
+                0 as *const libc::c_void,
+                G_DBUS_CALL_FLAGS_NONE,
+                -1,
+                0 as *mut libc::c_void,
+                on_call_finished,
+                Box::into_raw(Box::new(on_reply)) as *mut libc::c_void);
+        }
+
+        Ok(())
+    }
+}
+
+impl FromStr for DBusCall {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<DBusCall, String> {
+        let mut parts = s.trim().splitn(4, char::is_whitespace);
+        let destination = parts.next().unwrap_or("");
+        let object_path = parts.next().unwrap_or("");
+        let member = parts.next().unwrap_or("");
+        let arguments = parts.next().map(|a| a.trim()).filter(|a| !a.is_empty()).unwrap_or("()");
+
+        let (interface, method) = member.rsplit_once('.')
+            .ok_or_else(|| format!("Expected INTERFACE.METHOD, found \"{}\"", member))?;
+
+        let checks: [(&str, unsafe extern "C" fn(*const libc::c_char) -> libc::c_int, &str); 4] = [
+            (destination, g_dbus_is_name, "bus name"),
+            (object_path, g_variant_is_object_path, "object path"),
+            (interface, g_dbus_is_interface_name, "interface name"),
+            (method, g_dbus_is_member_name, "method name"),
+        ];
+        for (value, is_valid, what) in checks.iter() {
+            if unsafe { is_valid(to_cstring(value)?.as_ptr()) } == 0 {
+                return Err(format!("Invalid D-Bus {} \"{}\"", what, value));
+            }
+        }
+        Variant::parse_arguments(arguments)?;
+
+        Ok(DBusCall {
+            destination: destination.to_string(),
+            object_path: object_path.to_string(),
+            interface: interface.to_string(),
+            method: method.to_string(),
+            arguments: arguments.to_string(),
+        })
+    }
+}
+
+/* A dbus-daemon of its own with a main context of its own for the calling thread, keeping tests away from
+ * the desktop session and from each other */
+#[cfg(test)]
+pub(crate) struct PrivateBus
+{
+    daemon: std::process::Child,
+    context: *mut libc::c_void,
+    pub(crate) address: String,
+}
+
+#[cfg(test)]
+extern "C" {
+    #[no_mangle]
+    fn g_main_context_new() -> *mut libc::c_void;
+
+    #[no_mangle]
+    fn g_main_context_push_thread_default(context: *mut libc::c_void);
+
+    #[no_mangle]
+    fn g_main_context_pop_thread_default(context: *mut libc::c_void);
+
+    #[no_mangle]
+    fn g_main_context_unref(context: *mut libc::c_void);
+
+    #[no_mangle]
+    fn g_main_context_iteration(context: *mut libc::c_void, may_block: libc::c_int) -> libc::c_int;
+}
+
+#[cfg(test)]
+impl PrivateBus {
+    /* None if dbus-daemon can't be run */
+    pub(crate) fn start() -> Option<PrivateBus> {
+        use std::io::{BufRead, BufReader};
+        use std::process::{Command, Stdio};
+
+        let mut daemon = match Command::new("dbus-daemon").args(["--session", "--nofork", "--print-address=1"])
+            .stdout(Stdio::piped()).stderr(Stdio::null()).spawn() {
+            Ok(daemon) => daemon,
+            Err(e) => { println!("Skipping, dbus-daemon unavailable: {}", e); return None; },
+        };
+        let mut address = String::new();
+        BufReader::new(daemon.stdout.take().unwrap()).read_line(&mut address).unwrap();
+
+        let context = unsafe { g_main_context_new() };
+        unsafe { g_main_context_push_thread_default(context); }
+
+        Some(PrivateBus { daemon, context, address: address.trim().to_string() })
+    }
+
+    pub(crate) fn iterate_until<Done: Fn() -> bool>(&self, done: Done) {
+        while !done() {
+            unsafe { g_main_context_iteration(self.context, 1); }
+        }
+    }
+}
+
+#[cfg(test)]
+impl Drop for PrivateBus {
+    fn drop(&mut self) {
+        unsafe {
+            g_main_context_pop_thread_default(self.context);
+            g_main_context_unref(self.context);
+        }
+        let _ = self.daemon.kill();
+        let _ = self.daemon.wait();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    #[test]
+    fn parses_calls() {
+        let call: DBusCall = "org.gnome.Shell /org/gnome/Shell org.gnome.Shell.Eval 'Main.overview.toggle()'".parse().unwrap();
+        assert_eq!((call.interface.as_str(), call.method.as_str()), ("org.gnome.Shell", "Eval"));
+        assert_eq!(Variant::parse_arguments(&call.arguments).unwrap().print(), "('Main.overview.toggle()',)");
+
+        let call: DBusCall = "org.mpris.MediaPlayer2.vlc /org/mpris/MediaPlayer2 org.mpris.MediaPlayer2.Player.Next".parse().unwrap();
+        assert_eq!(call.arguments, "()");
+        assert_eq!(Variant::parse_arguments("(uint32 5, 'x')").unwrap().print(), "(5, 'x')");
+
+        assert!("org.gnome.Shell gnome/Shell org.gnome.Shell.Eval".parse::<DBusCall>().is_err());
+        assert!("org.gnome.Shell /org/gnome/Shell Eval".parse::<DBusCall>().is_err());
+        assert!("org.gnome.Shell /org/gnome/Shell org.gnome.Shell.Eval ('unterminated".parse::<DBusCall>().is_err());
+    }
+
+    #[test]
+    fn calls_methods_on_a_private_bus() {
+        let bus = match PrivateBus::start() {
+            Some(bus) => bus,
+            None => return,
+        };
+        let connection = DBusConnection::for_address(&bus.address).unwrap();
+        let replies = Rc::new(RefCell::new(Vec::new()));
+
+        for text in &[
+            "org.freedesktop.DBus /org/freedesktop/DBus org.freedesktop.DBus.NameHasOwner 'org.freedesktop.DBus'",
+            "org.freedesktop.DBus /org/freedesktop/DBus org.freedesktop.DBus.NoSuchMethod",
+        ] {
+            let replies = replies.clone();
+            let call: DBusCall = text.parse().unwrap();
+            call.call_on(&connection, move |reply| replies.borrow_mut().push(reply)).unwrap();
+        }
+
+        bus.iterate_until(|| replies.borrow().len() == 2);
+
+        assert_eq!(replies.borrow()[0], Ok(String::from("(true,)")));
+        assert!(replies.borrow()[1].as_ref().unwrap_err().contains("NoSuchMethod"));
+    }
+}
@@ -0,0 +1,514 @@
+/*
+ * Copyright © 2019 Romeo Calota
+ *
+ * This program is free software; you can redistribute it and/or
+ * modify it under the terms of the GNU Lesser General Public
+ * License as published by the Free Software Foundation; either
+ * version 2 of the licence, or (at your option) any later version.
+ *
+ * This software is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+ * Lesser General Public License for more details.
+ *
+ * You should have received a copy of the GNU Lesser General Public
+ * License along with this program; if not, see <http://www.gnu.org/licenses/>.
+ *
+ * Author: Romeo Calota
+ */
+
+use std::cell::Cell;
+use std::ffi::{CStr, CString};
+use std::time::{Duration, Instant};
+
+use libc;
+
+use crate::action::Action;
+use crate::dbus::{take_error, to_cstring, DBusConnection, GError};
+use crate::device::Device;
+use crate::events::BackendEvent;
+use crate::gesture::Gesture;
+
+pub const SERVICE_NAME: &str = "org.kinesix.Daemon";
+pub const OBJECT_PATH: &str = "/org/kinesix/Daemon";
+const INTERFACE_NAME: &str = "org.kinesix.Daemon";
+const ERROR_FAILED: &str = "org.kinesix.Daemon.Error.Failed";
+
+/* GestureUpdate is sent at most this often, updates come with every frame of the touchpad */
+const GESTURE_UPDATE_INTERVAL: Duration = Duration::from_millis(50);
+
+const INTROSPECTION_XML: &str = "
+<node>
+  <interface name='org.kinesix.Daemon'>
+    <method name='Pause'/>
+    <method name='Resume'/>
+    <method name='Reload'/>
+    <method name='ListDevices'>
+      <!-- Path, name, vendor id, product id and kind (touchpad or tablet-pad) -->
+      <arg type='a(ssuus)' name='devices' direction='out'/>
+    </method>
+    <signal name='GestureBegin'>
+      <arg type='s' name='gesture'/>
+      <arg type='i' name='finger_count'/>
+    </signal>
+    <signal name='GestureUpdate'>
+      <arg type='s' name='gesture'/>
+      <arg type='i' name='finger_count'/>
+    </signal>
+    <signal name='GestureEnd'>
+      <arg type='s' name='gesture'/>
+      <arg type='i' name='finger_count'/>
+      <arg type='b' name='cancelled'/>
+    </signal>
+    <property name='ActiveDevice' type='s' access='read'/>
+    <property name='ActiveProfile' type='s' access='read'/>
+    <property name='Paused' type='b' access='read'/>
+  </interface>
+</node>";
+
+const G_BUS_NAME_OWNER_FLAGS_NONE: libc::c_int = 0;
+
+type MethodCall = unsafe extern "C" fn(
+    connection: *mut libc::c_void,
+    sender: *const libc::c_char,
+    object_path: *const libc::c_char,
+    interface_name: *const libc::c_char,
+    method_name: *const libc::c_char,
+    parameters: *mut libc::c_void,
+    invocation: *mut libc::c_void,
+    data: *mut libc::c_void);
+
+type GetProperty = unsafe extern "C" fn(
+    connection: *mut libc::c_void,
+    sender: *const libc::c_char,
+    object_path: *const libc::c_char,
+    interface_name: *const libc::c_char,
+    property_name: *const libc::c_char,
+    error: *mut *mut GError,
+    data: *mut libc::c_void) -> *mut libc::c_void;
+
+#[repr(C)]
+struct GDBusInterfaceVTable
+{
+    method_call: MethodCall,
+    get_property: GetProperty,
+    set_property: *const libc::c_void,
+    padding: [*const libc::c_void; 8],
+}
+
+#[link(name = "gio-2.0")]
+#[link(name = "gobject-2.0")]
+#[link(name = "glib-2.0")]
+extern "C" {
+    #[no_mangle]
+    fn g_dbus_node_info_new_for_xml(xml_data: *const libc::c_char, error: *mut *mut GError) -> *mut libc::c_void;
+
+    #[no_mangle]
+    fn g_dbus_node_info_lookup_interface(info: *mut libc::c_void, name: *const libc::c_char) -> *mut libc::c_void;
+
+    #[no_mangle]
+    fn g_dbus_node_info_unref(info: *mut libc::c_void);
+
+    #[no_mangle]
+    fn g_dbus_connection_register_object(
+        connection: *mut libc::c_void,
+        object_path: *const libc::c_char,
+        interface_info: *mut libc::c_void,
+        vtable: *const GDBusInterfaceVTable,
+        user_data: *mut libc::c_void,
+        user_data_free_func: *const libc::c_void,
+        error: *mut *mut GError) -> u32;
+
+    #[no_mangle]
+    fn g_dbus_connection_unregister_object(connection: *mut libc::c_void, registration_id: u32) -> libc::c_int;
+
+    #[no_mangle]
+    fn g_bus_own_name_on_connection(
+        connection: *mut libc::c_void,
+        name: *const libc::c_char,
+        flags: libc::c_int,
+        name_acquired_handler: *const libc::c_void,
+        name_lost_handler: unsafe extern "C" fn(*mut libc::c_void, *const libc::c_char, *mut libc::c_void),
+        user_data: *mut libc::c_void,
+        user_data_free_func: *const libc::c_void) -> u32;
+
+    #[no_mangle]
+    fn g_bus_unown_name(owner_id: u32);
+
+    #[no_mangle]
+    fn g_dbus_connection_emit_signal(
+        connection: *mut libc::c_void,
+        destination_bus_name: *const libc::c_char,
+        object_path: *const libc::c_char,
+        interface_name: *const libc::c_char,
+        signal_name: *const libc::c_char,
+        parameters: *mut libc::c_void,
+        error: *mut *mut GError) -> libc::c_int;
+
+    #[no_mangle]
+    fn g_dbus_method_invocation_return_value(invocation: *mut libc::c_void, parameters: *mut libc::c_void);
+
+    #[no_mangle]
+    fn g_dbus_method_invocation_return_dbus_error(invocation: *mut libc::c_void, error_name: *const libc::c_char, error_message: *const libc::c_char);
+
+    #[no_mangle]
+    fn g_variant_new(format_string: *const libc::c_char, ...) -> *mut libc::c_void;
+
+    #[no_mangle]
+    fn g_variant_new_string(string: *const libc::c_char) -> *mut libc::c_void;
+
+    #[no_mangle]
+    fn g_variant_new_boolean(value: libc::c_int) -> *mut libc::c_void;
+
+    #[no_mangle]
+    fn g_variant_new_strv(strv: *const *const libc::c_char, length: isize) -> *mut libc::c_void;
+
+    #[no_mangle]
+    fn g_variant_builder_new(variant_type: *const libc::c_char) -> *mut libc::c_void;
+
+    #[no_mangle]
+    fn g_variant_builder_add(builder: *mut libc::c_void, format_string: *const libc::c_char, ...);
+
+    #[no_mangle]
+    fn g_variant_builder_end(builder: *mut libc::c_void) -> *mut libc::c_void;
+
+    #[no_mangle]
+    fn g_variant_builder_unref(builder: *mut libc::c_void);
+}
+
+/* What the service and IPC clients control, the backend outside of tests */
+pub trait DaemonControl
+{
+    fn pause(&mut self);
+    fn resume(&mut self);
+    fn is_paused(&self) -> bool;
+    fn reload(&mut self) -> Result<(), String>;
+    fn devices(&mut self) -> Vec<Device>;
+    /* Path of the device in use, if any */
+    fn active_device(&self) -> Option<String>;
+    fn active_profile(&self) -> String;
+    fn profiles(&self) -> Vec<String>;
+    fn set_profile(&mut self, name: &str) -> Result<(), String>;
+    /* Bindings made at runtime go to the default profile, replacing what the gesture was bound to */
+    fn bind(&mut self, gesture: Gesture, action: Action);
+    /* Returns false if the gesture wasn't bound */
+    fn unbind(&mut self, gesture: Gesture) -> bool;
+}
+
+/* Strings handed to GVariant can't hold NUL characters, these never should */
+fn cstring(value: &str) -> CString {
+    CString::new(value.replace('\0', "")).unwrap()
+}
+
+unsafe fn control<'a>(data: *mut libc::c_void) -> &'a mut dyn DaemonControl {
+    &mut **(data as *mut *mut dyn DaemonControl)
+}
+
+unsafe fn property_value(control: &dyn DaemonControl, name: &str) -> Option<*mut libc::c_void> {
+    match name {
+        "ActiveDevice" => Some(g_variant_new_string(cstring(&control.active_device().unwrap_or_default()).as_ptr())),
+        "ActiveProfile" => Some(g_variant_new_string(cstring(&control.active_profile()).as_ptr())),
+        "Paused" => Some(g_variant_new_boolean(control.is_paused() as libc::c_int)),
+        _ => None,
+    }
+}
+
+unsafe extern "C" fn on_method_call(
+    _connection: *mut libc::c_void,
+    _sender: *const libc::c_char,
+    _object_path: *const libc::c_char,
+    _interface_name: *const libc::c_char,
+    method_name: *const libc::c_char,
+    _parameters: *mut libc::c_void,
+    invocation: *mut libc::c_void,
+    data: *mut libc::c_void) {
+    let control = control(data);
+
+    let result = match CStr::from_ptr(method_name).to_str().unwrap_or("") {
+        "Pause" => { control.pause(); Ok(0 as *mut libc::c_void) },
+        "Resume" => { control.resume(); Ok(0 as *mut libc::c_void) },
+        "Reload" => control.reload().map(|_| 0 as *mut libc::c_void),
+        "ListDevices" => {
+            let builder = g_variant_builder_new(b"a(ssuus)\0".as_ptr() as *const libc::c_char);
+            for device in control.devices() {
+                g_variant_builder_add(builder, b"(ssuus)\0".as_ptr() as *const libc::c_char,
+                    cstring(&device.path).as_ptr(), cstring(&device.name).as_ptr(),
+                    device.vendor_id as libc::c_uint, device.product_id as libc::c_uint,
+                    cstring(device.kind.name()).as_ptr());
+            }
+            let devices = g_variant_builder_end(builder);
+            g_variant_builder_unref(builder);
+
+            Ok(g_variant_new(b"(@a(ssuus))\0".as_ptr() as *const libc::c_char, devices))
+        },
+        /* GDBus only lets through methods from the introspection data */
+        _ => Err(String::from("Unknown method")),
+    };
+
+    match result {
+        Ok(reply) => g_dbus_method_invocation_return_value(invocation, reply),
+        Err(e) => g_dbus_method_invocation_return_dbus_error(invocation, cstring(ERROR_FAILED).as_ptr(), cstring(&e).as_ptr()),
+    }
+}
+
+unsafe extern "C" fn on_get_property(
+    _connection: *mut libc::c_void,
+    _sender: *const libc::c_char,
+    _object_path: *const libc::c_char,
+    _interface_name: *const libc::c_char,
+    property_name: *const libc::c_char,
+    _error: *mut *mut GError,
+    data: *mut libc::c_void) -> *mut libc::c_void {
+    property_value(control(data), CStr::from_ptr(property_name).to_str().unwrap_or("")).unwrap_or(0 as *mut libc::c_void)
+}
+
+/* Also called when the name couldn't be had in the first place */
+unsafe extern "C" fn on_name_lost(_connection: *mut libc::c_void, name: *const libc::c_char, _data: *mut libc::c_void) {
+    println!("Not the owner of {} on the bus, is another instance running?", CStr::from_ptr(name).to_string_lossy());
+}
+
+static VTABLE: GDBusInterfaceVTable = GDBusInterfaceVTable {
+    method_call: on_method_call,
+    get_property: on_get_property,
+    set_property: 0 as *const libc::c_void,
+    padding: [0 as *const libc::c_void; 8],
+};
+
+/* The vtable only ever gets read, by GDBus */
+unsafe impl Sync for GDBusInterfaceVTable {}
+
+/*
+ * org.kinesix.Daemon on a bus: methods to pause, resume, reload the configuration and list devices,
+ * properties for the device and profile in use, and signals as gestures are performed.
+ */
+pub struct DBusService
+{
+    connection: DBusConnection,
+    /* Handed to GDBus as user data, so it needs a stable address; only the GDBus callbacks may use it */
+    _control: Box<*mut dyn DaemonControl>,
+    registration_id: u32,
+    owner_id: u32,
+    /* When the last GestureUpdate went out, None if none has since the gesture began */
+    last_update: Cell<Option<Instant>>,
+}
+
+impl DBusService {
+    /* control has to outlive the service, and stay where it is */
+    pub fn export(connection: DBusConnection, control: *mut dyn DaemonControl) -> Result<DBusService, String> {
+        let xml = to_cstring(INTROSPECTION_XML)?;
+        let interface_name = cstring(INTERFACE_NAME);
+        let object_path = cstring(OBJECT_PATH);
+        let service_name = cstring(SERVICE_NAME);
+        let control = Box::new(control);
+
+        unsafe {
+            let mut error = 0 as *mut GError;
+            let node_info = g_dbus_node_info_new_for_xml(xml.as_ptr(), &mut error);
+            if node_info.is_null() {
+                return Err(take_error(error));
+            }
+
+            let interface_info = g_dbus_node_info_lookup_interface(node_info, interface_name.as_ptr());
+            let registration_id = g_dbus_connection_register_object(
+                connection.connection,
+                object_path.as_ptr(),
+                interface_info,
+                &VTABLE,
+                &*control as *const *mut dyn DaemonControl as *mut libc::c_void,
+                0 as *const libc::c_void,
+                &mut error);
+            g_dbus_node_info_unref(node_info);
+            if registration_id == 0 {
+                return Err(format!("Failed to export {}: {}", OBJECT_PATH, take_error(error)));
+            }
+
+            let owner_id = g_bus_own_name_on_connection(connection.connection, service_name.as_ptr(), G_BUS_NAME_OWNER_FLAGS_NONE,
+                0 as *const libc::c_void, on_name_lost, 0 as *mut libc::c_void, 0 as *const libc::c_void);
+
+            Ok(DBusService { connection, _control: control, registration_id, owner_id, last_update: Cell::new(None) })
+        }
+    }
+
+    fn emit_signal(&self, interface_name: &str, signal_name: &str, parameters: *mut libc::c_void) {
+        let object_path = cstring(OBJECT_PATH);
+        let interface_name = cstring(interface_name);
+        let signal_name = cstring(signal_name);
+
+        unsafe {
+            let mut error = 0 as *mut GError;
+            let emitted = g_dbus_connection_emit_signal(self.connection.connection, 0 as *const libc::c_char,
+                object_path.as_ptr(), interface_name.as_ptr(), signal_name.as_ptr(), parameters, &mut error);
+            if emitted == 0 {
+                println!("Failed to emit {}: {}", signal_name.to_string_lossy(), take_error(error));
+            }
+        }
+    }
+
+    /* The value comes with the event, control is only for the GDBus callbacks to use */
+    unsafe fn emit_property_changed(&self, name: &str, value: *mut libc::c_void) {
+        let builder = g_variant_builder_new(b"a{sv}\0".as_ptr() as *const libc::c_char);
+        g_variant_builder_add(builder, b"{sv}\0".as_ptr() as *const libc::c_char, cstring(name).as_ptr(), value);
+        let changed = g_variant_builder_end(builder);
+        g_variant_builder_unref(builder);
+
+        let parameters = g_variant_new(b"(s@a{sv}@as)\0".as_ptr() as *const libc::c_char,
+            cstring(INTERFACE_NAME).as_ptr(), changed, g_variant_new_strv(0 as *const *const libc::c_char, 0));
+        self.emit_signal("org.freedesktop.DBus.Properties", "PropertiesChanged", parameters);
+    }
+
+    pub fn emit(&self, event: &BackendEvent) {
+        unsafe {
+            match event {
+                BackendEvent::GestureBegin(gesture_type, finger_count) => {
+                    self.last_update.set(None);
+                    let parameters = g_variant_new(b"(si)\0".as_ptr() as *const libc::c_char, cstring(gesture_type.name()).as_ptr(), *finger_count);
+                    self.emit_signal(INTERFACE_NAME, "GestureBegin", parameters);
+                },
+                BackendEvent::GestureUpdate(gesture_type, finger_count) => {
+                    let now = Instant::now();
+                    if self.last_update.get().map(|last| now - last < GESTURE_UPDATE_INTERVAL).unwrap_or(false) { return; }
+                    self.last_update.set(Some(now));
+
+                    let parameters = g_variant_new(b"(si)\0".as_ptr() as *const libc::c_char, cstring(gesture_type.name()).as_ptr(), *finger_count);
+                    self.emit_signal(INTERFACE_NAME, "GestureUpdate", parameters);
+                },
+                BackendEvent::GestureEnd(gesture_type, finger_count, cancelled) => {
+                    let parameters = g_variant_new(b"(sib)\0".as_ptr() as *const libc::c_char,
+                        cstring(gesture_type.name()).as_ptr(), *finger_count, *cancelled as libc::c_int);
+                    self.emit_signal(INTERFACE_NAME, "GestureEnd", parameters);
+                },
+                BackendEvent::ProfileChanged(name) => self.emit_property_changed("ActiveProfile", g_variant_new_string(cstring(name).as_ptr())),
+                BackendEvent::DeviceChanged(path) => self.emit_property_changed("ActiveDevice", g_variant_new_string(cstring(path).as_ptr())),
+                BackendEvent::PausedChanged(paused) => self.emit_property_changed("Paused", g_variant_new_boolean(*paused as libc::c_int)),
+                /* Only on the IPC event stream */
+                BackendEvent::DeviceAdded(..) | BackendEvent::DeviceRemoved(_) | BackendEvent::ActionResult(..) => {},
+            }
+        }
+    }
+}
+
+impl Drop for DBusService {
+    fn drop(&mut self) {
+        unsafe {
+            g_bus_unown_name(self.owner_id);
+            g_dbus_connection_unregister_object(self.connection.connection, self.registration_id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dbus::{DBusCall, PrivateBus, Variant};
+    use crate::device::DeviceKind;
+    use crate::{GestureType, SwipeDirection};
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    type SignalCallback = unsafe extern "C" fn(*mut libc::c_void, *const libc::c_char, *const libc::c_char,
+        *const libc::c_char, *const libc::c_char, *mut libc::c_void, *mut libc::c_void);
+
+    extern "C" {
+        #[no_mangle]
+        fn g_dbus_connection_get_unique_name(connection: *mut libc::c_void) -> *const libc::c_char;
+
+        #[no_mangle]
+        fn g_dbus_connection_signal_subscribe(
+            connection: *mut libc::c_void,
+            sender: *const libc::c_char,
+            interface_name: *const libc::c_char,
+            member: *const libc::c_char,
+            object_path: *const libc::c_char,
+            arg0: *const libc::c_char,
+            flags: libc::c_int,
+            callback: SignalCallback,
+            user_data: *mut libc::c_void,
+            user_data_free_func: *const libc::c_void) -> u32;
+
+        #[no_mangle]
+        fn g_variant_ref(value: *mut libc::c_void) -> *mut libc::c_void;
+    }
+
+    struct MockControl
+    {
+        paused: bool,
+    }
+
+    impl DaemonControl for MockControl {
+        fn pause(&mut self) { self.paused = true; }
+        fn resume(&mut self) { self.paused = false; }
+        fn is_paused(&self) -> bool { self.paused }
+        fn reload(&mut self) -> Result<(), String> { Err(String::from("No configuration file")) }
+
+        fn devices(&mut self) -> Vec<Device> {
+            vec![Device { id: 0, path: String::from("/dev/input/event4"), name: String::from("Touchpad"), product_id: 52, vendor_id: 1739, kind: DeviceKind::Touchpad }]
+        }
+
+        fn active_device(&self) -> Option<String> { None }
+        fn active_profile(&self) -> String { String::from("default") }
+        fn profiles(&self) -> Vec<String> { vec![self.active_profile()] }
+        fn set_profile(&mut self, _name: &str) -> Result<(), String> { Ok(()) }
+        fn bind(&mut self, _gesture: Gesture, _action: Action) {}
+        fn unbind(&mut self, _gesture: Gesture) -> bool { false }
+    }
+
+    unsafe extern "C" fn on_signal(_connection: *mut libc::c_void, _sender: *const libc::c_char, _object_path: *const libc::c_char,
+        _interface_name: *const libc::c_char, signal_name: *const libc::c_char, parameters: *mut libc::c_void, data: *mut libc::c_void) {
+        let signals = &*(data as *const RefCell<Vec<String>>);
+        let parameters = Variant { value: g_variant_ref(parameters) }.print();
+        signals.borrow_mut().push(format!("{} {}", CStr::from_ptr(signal_name).to_string_lossy(), parameters));
+    }
+
+    #[test]
+    fn exports_the_daemon_interface() {
+        let bus = match PrivateBus::start() {
+            Some(bus) => bus,
+            None => return,
+        };
+        let mut control = MockControl { paused: false };
+        let service = DBusService::export(DBusConnection::for_address(&bus.address).unwrap(), &mut control as *mut dyn DaemonControl).unwrap();
+        let destination = unsafe { CStr::from_ptr(g_dbus_connection_get_unique_name(service.connection.connection)).to_string_lossy().into_owned() };
+
+        let client = DBusConnection::for_address(&bus.address).unwrap();
+        let replies = Rc::new(RefCell::new(Vec::new()));
+        let call = |member: &str, arguments: &str| {
+            let sink = replies.clone();
+            let call: DBusCall = format!("{} {} {} {}", destination, OBJECT_PATH, member, arguments).parse().unwrap();
+            call.call_on(&client, move |reply| sink.borrow_mut().push(reply)).unwrap();
+            bus.iterate_until(|| !replies.borrow().is_empty());
+            replies.borrow_mut().remove(0)
+        };
+
+        assert_eq!(call("org.kinesix.Daemon.Pause", ""), Ok(String::from("()")));
+        assert_eq!(call("org.freedesktop.DBus.Properties.Get", "('org.kinesix.Daemon', 'Paused')"), Ok(String::from("(<true>,)")));
+        assert_eq!(call("org.freedesktop.DBus.Properties.Get", "('org.kinesix.Daemon', 'ActiveProfile')"), Ok(String::from("(<'default'>,)")));
+        assert_eq!(call("org.kinesix.Daemon.ListDevices", ""), Ok(String::from("([('/dev/input/event4', 'Touchpad', 1739, 52, 'touchpad')],)")));
+        assert!(call("org.kinesix.Daemon.Reload", "").unwrap_err().contains("No configuration file"));
+
+        let signals = RefCell::new(Vec::new());
+        unsafe {
+            g_dbus_connection_signal_subscribe(client.connection, 0 as *const libc::c_char, 0 as *const libc::c_char, 0 as *const libc::c_char,
+                cstring(OBJECT_PATH).as_ptr(), 0 as *const libc::c_char, 0, on_signal, &signals as *const RefCell<Vec<String>> as *mut libc::c_void, 0 as *const libc::c_void);
+        }
+        /* A round trip, so the match rule is in place before anything is emitted */
+        call("org.kinesix.Daemon.Resume", "").unwrap();
+
+        service.emit(&BackendEvent::GestureBegin(GestureType::Swipe(SwipeDirection::None), 3));
+        service.emit(&BackendEvent::GestureUpdate(GestureType::Swipe(SwipeDirection::None), 3));
+        /* Too soon after the last one */
+        service.emit(&BackendEvent::GestureUpdate(GestureType::Swipe(SwipeDirection::None), 3));
+        service.emit(&BackendEvent::GestureEnd(GestureType::Swipe(SwipeDirection::SwipeUp), 3, false));
+        service.emit(&BackendEvent::PausedChanged(false));
+        service.emit(&BackendEvent::ProfileChanged(String::from("work")));
+        bus.iterate_until(|| signals.borrow().len() == 5);
+
+        assert_eq!(*signals.borrow(), vec![
+            "GestureBegin ('swipe', 3)",
+            "GestureUpdate ('swipe', 3)",
+            "GestureEnd ('swipe-up', 3, false)",
+            "PropertiesChanged ('org.kinesix.Daemon', {'Paused': <false>}, [])",
+            "PropertiesChanged ('org.kinesix.Daemon', {'ActiveProfile': <'work'>}, [])",
+        ]);
+        drop(service);
+    }
+}
@@ -79,6 +79,22 @@ extern "C" {
     fn lstat(__file: *const libc::c_uchar, __buf: *mut stat) -> libc::c_int;
 }
 
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum DeviceKind
+{
+    Touchpad,
+    TabletPad,
+}
+
+impl DeviceKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            DeviceKind::Touchpad => "touchpad",
+            DeviceKind::TabletPad => "tablet-pad",
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct Device
 {
@@ -86,12 +102,13 @@ pub struct Device
     pub path: String,
     pub name: String,
     pub product_id: u32,
-    pub vendor_id: u32
+    pub vendor_id: u32,
+    pub kind: DeviceKind
 }
 
 impl Device
 {
-    fn new_with_id(id: u32, path: String, name: String, product_id: u32, vendor_id: u32) -> Option<Device> {
+    fn new_with_id(id: u32, path: String, name: String, product_id: u32, vendor_id: u32, kind: DeviceKind) -> Option<Device> {
         unsafe {
             /* Check if file exists */
             let file_exists = access(path.as_ptr(), Access::F_OK as libc::c_int) != (-1 as libc::c_int);
@@ -99,7 +116,7 @@ impl Device
             let mut sb = std::mem::MaybeUninit::<stat>::uninit();
             if file_exists && lstat(path.as_ptr(), sb.as_mut_ptr()) != (-1 as libc::c_int) {
                 if sb.assume_init().st_mode & FileTypes::__S_IFMT as libc::c_uint == FileTypes::__S_IFCHR as libc::c_uint {
-                    let device = Device { id, path, name, product_id, vendor_id };
+                    let device = Device { id, path, name, product_id, vendor_id, kind };
                     return Some(device);
                 }
             }
@@ -107,12 +124,12 @@ impl Device
         None
     }
 
-    pub fn new(path: &str, name: &str, product_id: u32, vendor_id: u32) -> Option<Device> {
+    pub fn new(path: &str, name: &str, product_id: u32, vendor_id: u32, kind: DeviceKind) -> Option<Device> {
         let new_id: u32;
         unsafe {
             LAST_ASSIGNED_ID += 1;
             new_id = LAST_ASSIGNED_ID;
         }
-        Device::new_with_id(new_id, String::from(path), String::from(name), product_id, vendor_id)
+        Device::new_with_id(new_id, String::from(path), String::from(name), product_id, vendor_id, kind)
     }
 }
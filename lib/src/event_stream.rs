@@ -0,0 +1,170 @@
+/*
+ * Copyright © 2019 Romeo Calota
+ *
+ * This program is free software; you can redistribute it and/or
+ * modify it under the terms of the GNU Lesser General Public
+ * License as published by the Free Software Foundation; either
+ * version 2 of the licence, or (at your option) any later version.
+ *
+ * This software is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+ * Lesser General Public License for more details.
+ *
+ * You should have received a copy of the GNU Lesser General Public
+ * License along with this program; if not, see <http://www.gnu.org/licenses/>.
+ *
+ * Author: Romeo Calota
+ */
+
+
+use std::io::{ErrorKind, Write};
+
+use crate::events::{BackendEvent, json_string};
+
+/* Output queued for a subscriber before further events are dropped, so a reader that stalls
+ * can't hold up gesture handling */
+pub const MAX_PENDING: usize = 64 * 1024;
+
+/* A comma separated list of event kinds, e.g. "gesture-end,device-added"; empty for all of them */
+pub fn parse_kinds(filter: &str) -> Result<Vec<&'static str>, String> {
+    filter.split(',').map(|kind| kind.trim()).filter(|kind| !kind.is_empty())
+        .map(|kind| {
+            BackendEvent::KINDS.iter().find(|k| **k == kind).cloned()
+                .ok_or_else(|| format!("Unknown event kind \"{}\", expected one of {}", kind, BackendEvent::KINDS.join(", ")))
+        })
+        .collect()
+}
+
+pub fn matches(kinds: &[&'static str], event: &BackendEvent) -> bool {
+    kinds.is_empty() || kinds.contains(&event.kind())
+}
+
+/*
+ * Newline delimited JSON events for one reader, written without blocking. Events that don't fit are
+ * dropped and reported with {"event":"events-dropped","count":N} once the reader catches up.
+ */
+pub struct EventSubscriber<W: Write>
+{
+    pub writer: W,
+    pub kinds: Vec<&'static str>,
+    pending: Vec<u8>,
+    dropped: u64,
+}
+
+impl<W: Write> EventSubscriber<W> {
+    /* writer should be non-blocking */
+    pub fn new(writer: W, kinds: Vec<&'static str>) -> EventSubscriber<W> {
+        EventSubscriber { writer, kinds, pending: Vec::new(), dropped: 0 }
+    }
+
+    /* Fails once the reader has gone away */
+    pub fn send(&mut self, event: &BackendEvent) -> Result<(), String> {
+        if !matches(&self.kinds, event) { return Ok(()); }
+
+        self.flush()?;
+        if self.pending.len() >= MAX_PENDING {
+            self.dropped += 1;
+            return Ok(());
+        }
+
+        self.pending.extend_from_slice(event.to_json().as_bytes());
+        self.pending.push(b'\n');
+        self.flush()
+    }
+
+    /* Queues a line that must not be dropped, such as the reply to a request */
+    pub fn send_line(&mut self, line: &str) -> Result<(), String> {
+        self.pending.extend_from_slice(line.as_bytes());
+        self.pending.push(b'\n');
+        self.flush()
+    }
+
+    /* Whether anything is still waiting for the reader to take it */
+    pub fn has_pending(&self) -> bool {
+        !self.pending.is_empty() || self.dropped > 0
+    }
+
+    /* Writes as much of the queue as the reader takes right now */
+    pub fn flush(&mut self) -> Result<(), String> {
+        loop {
+            if self.pending.is_empty() {
+                if self.dropped == 0 { return Ok(()); }
+
+                let notice = format!("{{\"event\":{},\"count\":{}}}\n", json_string("events-dropped"), self.dropped);
+                self.pending.extend_from_slice(notice.as_bytes());
+                self.dropped = 0;
+            }
+
+            match self.writer.write(&self.pending) {
+                Ok(0) => return Err(String::from("Subscriber went away")),
+                Ok(written) => { self.pending.drain(..written); },
+                Err(ref e) if e.kind() == ErrorKind::WouldBlock => return Ok(()),
+                Err(ref e) if e.kind() == ErrorKind::Interrupted => {},
+                Err(e) => return Err(e.to_string()),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{GestureType, SwipeDirection};
+    use std::io;
+
+    /* Takes up to capacity bytes, then would block */
+    struct SlowReader
+    {
+        written: Vec<u8>,
+        capacity: usize,
+    }
+
+    impl Write for SlowReader {
+        fn write(&mut self, buffer: &[u8]) -> io::Result<usize> {
+            let free = self.capacity - self.written.len();
+            if free == 0 { return Err(io::Error::from(ErrorKind::WouldBlock)); }
+
+            let written = free.min(buffer.len());
+            self.written.extend_from_slice(&buffer[..written]);
+            Ok(written)
+        }
+
+        fn flush(&mut self) -> io::Result<()> { Ok(()) }
+    }
+
+    #[test]
+    fn parses_kind_filters() {
+        assert_eq!(parse_kinds("").unwrap(), Vec::<&str>::new());
+        assert_eq!(parse_kinds("gesture-end, device-added").unwrap(), vec!["gesture-end", "device-added"]);
+        assert!(parse_kinds("gesture-end,gestures").is_err());
+    }
+
+    #[test]
+    fn drops_events_a_slow_reader_has_no_room_for() {
+        let end = BackendEvent::GestureEnd(GestureType::Swipe(SwipeDirection::SwipeUp), 3, false);
+        let line = format!("{}\n", end.to_json());
+
+        let reader = SlowReader { written: Vec::new(), capacity: line.len() };
+        let mut subscriber = EventSubscriber::new(reader, vec!["gesture-end", "action-result"]);
+
+        subscriber.send(&BackendEvent::GestureBegin(GestureType::Swipe(SwipeDirection::None), 3)).unwrap();
+        for _ in 0..MAX_PENDING / line.len() + 10 {
+            subscriber.send(&end).unwrap();
+        }
+        assert_eq!(subscriber.writer.written, line.as_bytes());
+        assert!(subscriber.pending.len() >= MAX_PENDING);
+        assert!(subscriber.dropped > 0);
+
+        /* Once the reader catches up it learns how many events it missed */
+        let dropped = subscriber.dropped;
+        subscriber.writer.capacity = usize::MAX;
+        subscriber.writer.written.clear();
+        subscriber.send(&BackendEvent::ActionResult("keys", Err(String::from("No virtual input device available")))).unwrap();
+
+        let output = String::from_utf8(subscriber.writer.written.clone()).unwrap();
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines[lines.len() - 2], format!("{{\"event\":\"events-dropped\",\"count\":{}}}", dropped));
+        assert_eq!(lines[lines.len() - 1], "{\"event\":\"action-result\",\"action\":\"keys\",\"ok\":false,\"error\":\"No virtual input device available\"}");
+    }
+}
@@ -0,0 +1,126 @@
+/*
+ * Copyright © 2019 Romeo Calota
+ *
+ * This program is free software; you can redistribute it and/or
+ * modify it under the terms of the GNU Lesser General Public
+ * License as published by the Free Software Foundation; either
+ * version 2 of the licence, or (at your option) any later version.
+ *
+ * This software is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+ * Lesser General Public License for more details.
+ *
+ * You should have received a copy of the GNU Lesser General Public
+ * License along with this program; if not, see <http://www.gnu.org/licenses/>.
+ *
+ * Author: Romeo Calota
+ */
+
+use crate::GestureType;
+
+/* What the backend reports to the D-Bus service, IPC clients and other listeners */
+#[derive(Debug, PartialEq, Clone)]
+pub enum BackendEvent
+{
+    /* A swipe or pinch with no direction yet, and its finger count */
+    GestureBegin(GestureType, i32),
+    /* The direction recognized so far, the gesture type it began with until there is one */
+    GestureUpdate(GestureType, i32),
+    /* Last, with whether the gesture was cancelled */
+    GestureEnd(GestureType, i32, bool),
+    ProfileChanged(String),
+    /* Path of the device now in use */
+    DeviceChanged(String),
+    PausedChanged(bool),
+    /* Path and name of a device Kinesix started reading from */
+    DeviceAdded(String, String),
+    /* Path of a device that went away */
+    DeviceRemoved(String),
+    /* The kind of action a gesture ran, and whether it failed */
+    ActionResult(&'static str, Result<(), String>),
+}
+
+/* A JSON string literal */
+pub(crate) fn json_string(value: &str) -> String {
+    let mut json = String::from("\"");
+    for c in value.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            c if (c as u32) < 0x20 => json.push_str(&format!("\\u{:04x}", c as u32)),
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+    json
+}
+
+impl BackendEvent {
+    pub const KINDS: [&'static str; 9] = ["gesture-begin", "gesture-update", "gesture-end", "profile-changed",
+        "device-changed", "paused-changed", "device-added", "device-removed", "action-result"];
+
+    /* One of KINDS */
+    pub fn kind(&self) -> &'static str {
+        match self {
+            BackendEvent::GestureBegin(..) => "gesture-begin",
+            BackendEvent::GestureUpdate(..) => "gesture-update",
+            BackendEvent::GestureEnd(..) => "gesture-end",
+            BackendEvent::ProfileChanged(_) => "profile-changed",
+            BackendEvent::DeviceChanged(_) => "device-changed",
+            BackendEvent::PausedChanged(_) => "paused-changed",
+            BackendEvent::DeviceAdded(..) => "device-added",
+            BackendEvent::DeviceRemoved(_) => "device-removed",
+            BackendEvent::ActionResult(..) => "action-result",
+        }
+    }
+
+    /* One line of JSON, e.g. {"event":"gesture-end","gesture":"swipe-up","finger_count":3,"cancelled":false} */
+    pub fn to_json(&self) -> String {
+        let fields = match self {
+            BackendEvent::GestureBegin(gesture_type, finger_count) | BackendEvent::GestureUpdate(gesture_type, finger_count) => {
+                format!("\"gesture\":{},\"finger_count\":{}", json_string(gesture_type.name()), finger_count)
+            },
+            BackendEvent::GestureEnd(gesture_type, finger_count, cancelled) => {
+                format!("\"gesture\":{},\"finger_count\":{},\"cancelled\":{}", json_string(gesture_type.name()), finger_count, cancelled)
+            },
+            BackendEvent::ProfileChanged(name) => format!("\"profile\":{}", json_string(name)),
+            BackendEvent::DeviceChanged(path) => format!("\"device\":{}", json_string(path)),
+            BackendEvent::PausedChanged(paused) => format!("\"paused\":{}", paused),
+            BackendEvent::DeviceAdded(path, name) => format!("\"device\":{},\"name\":{}", json_string(path), json_string(name)),
+            BackendEvent::DeviceRemoved(path) => format!("\"device\":{}", json_string(path)),
+            BackendEvent::ActionResult(action, Ok(())) => format!("\"action\":{},\"ok\":true", json_string(action)),
+            BackendEvent::ActionResult(action, Err(e)) => format!("\"action\":{},\"ok\":false,\"error\":{}", json_string(action), json_string(e)),
+        };
+
+        format!("{{\"event\":{},{}}}", json_string(self.kind()), fields)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{PinchType, SwipeDirection};
+
+    #[test]
+    fn lists_every_kind() {
+        /* One of each variant, extend it along with BackendEvent */
+        let events = [
+            BackendEvent::GestureBegin(GestureType::Swipe(SwipeDirection::None), 3),
+            BackendEvent::GestureUpdate(GestureType::Pinch(PinchType::PinchIn), 4),
+            BackendEvent::GestureEnd(GestureType::Swipe(SwipeDirection::SwipeUp), 3, true),
+            BackendEvent::ProfileChanged(String::from("default")),
+            BackendEvent::DeviceChanged(String::from("/dev/input/event4")),
+            BackendEvent::PausedChanged(true),
+            BackendEvent::DeviceAdded(String::from("/dev/input/event4"), String::from("Touchpad")),
+            BackendEvent::DeviceRemoved(String::from("/dev/input/event4")),
+            BackendEvent::ActionResult("keys", Ok(())),
+        ];
+
+        let kinds: Vec<&str> = events.iter().map(|event| event.kind()).collect();
+        assert_eq!(kinds, BackendEvent::KINDS.to_vec());
+    }
+}
@@ -0,0 +1,349 @@
+/*
+ * Copyright © 2019 Romeo Calota
+ *
+ * This program is free software; you can redistribute it and/or
+ * modify it under the terms of the GNU Lesser General Public
+ * License as published by the Free Software Foundation; either
+ * version 2 of the licence, or (at your option) any later version.
+ *
+ * This software is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+ * Lesser General Public License for more details.
+ *
+ * You should have received a copy of the GNU Lesser General Public
+ * License along with this program; if not, see <http://www.gnu.org/licenses/>.
+ *
+ * Author: Romeo Calota
+ */
+
+
+#[cfg(feature = "x11")]
+use std::ffi::CString;
+
+#[cfg(feature = "x11")]
+use libc;
+
+#[cfg(feature = "x11")]
+const ANY_PROPERTY_TYPE: libc::c_ulong = 0;
+#[cfg(feature = "x11")]
+const SUCCESS: libc::c_int = 0;
+
+#[cfg(feature = "x11")]
+type XErrorHandler = unsafe extern "C" fn(*mut libc::c_void, *mut libc::c_void) -> libc::c_int;
+
+#[cfg(feature = "x11")]
+#[link(name = "X11")]
+extern "C" {
+    #[no_mangle]
+    fn XOpenDisplay(display_name: *const libc::c_char) -> *mut libc::c_void;
+
+    #[no_mangle]
+    fn XCloseDisplay(display: *mut libc::c_void) -> libc::c_int;
+
+    #[no_mangle]
+    fn XDefaultRootWindow(display: *mut libc::c_void) -> libc::c_ulong;
+
+    #[no_mangle]
+    fn XInternAtom(display: *mut libc::c_void, atom_name: *const libc::c_char, only_if_exists: libc::c_int) -> libc::c_ulong;
+
+    #[no_mangle]
+    fn XGetWindowProperty(display: *mut libc::c_void, window: libc::c_ulong, property: libc::c_ulong, long_offset: libc::c_long,
+        long_length: libc::c_long, delete: libc::c_int, req_type: libc::c_ulong, actual_type_return: *mut libc::c_ulong,
+        actual_format_return: *mut libc::c_int, nitems_return: *mut libc::c_ulong, bytes_after_return: *mut libc::c_ulong,
+        prop_return: *mut *mut libc::c_uchar) -> libc::c_int;
+
+    #[no_mangle]
+    fn XFree(data: *mut libc::c_void) -> libc::c_int;
+
+    #[no_mangle]
+    fn XSetErrorHandler(handler: Option<XErrorHandler>) -> Option<XErrorHandler>;
+}
+
+/* The window that has the focus */
+#[derive(Debug, PartialEq, Clone)]
+pub struct WindowInfo
+{
+    /* The two parts of WM_CLASS, e.g. "Navigator" and "firefox" */
+    pub instance: String,
+    pub class: String,
+    pub title: String,
+}
+
+/* Tells which window has the focus, so bindings can differ between applications */
+pub trait WindowContext
+{
+    fn focused_window(&mut self) -> Option<WindowInfo>;
+}
+
+/* Case insensitive, with '*' for any text and '?' for any one character */
+pub fn glob_matches(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.to_lowercase().chars().collect();
+    let text: Vec<char> = text.to_lowercase().chars().collect();
+
+    /* Where to resume after the last '*' if what followed it stops matching */
+    let mut backtrack: Option<(usize, usize)> = None;
+    let (mut p, mut t) = (0, 0);
+    while t < text.len() {
+        if p < pattern.len() && pattern[p] == '*' {
+            backtrack = Some((p + 1, t));
+            p += 1;
+        } else if p < pattern.len() && (pattern[p] == '?' || pattern[p] == text[t]) {
+            p += 1;
+            t += 1;
+        } else if let Some((star_p, star_t)) = backtrack {
+            backtrack = Some((star_p, star_t + 1));
+            p = star_p;
+            t = star_t + 1;
+        } else {
+            return false;
+        }
+    }
+
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+/* Which windows an [app] section applies to */
+#[derive(Debug, PartialEq, Clone)]
+pub struct AppPattern
+{
+    /* Matched against both parts of WM_CLASS */
+    pub class: String,
+    pub title: Option<String>,
+}
+
+impl AppPattern {
+    pub fn new(class: &str) -> AppPattern {
+        AppPattern { class: class.to_string(), title: None }
+    }
+
+    pub fn matches(&self, window: &WindowInfo) -> bool {
+        let class_matches = glob_matches(&self.class, &window.class) || glob_matches(&self.class, &window.instance);
+        class_matches && self.title.as_ref().map(|title| glob_matches(title, &window.title)).unwrap_or(true)
+    }
+}
+
+/* Xlib exits on errors by default, and windows can go away between reading _NET_ACTIVE_WINDOW and
+ * reading their properties */
+#[cfg(feature = "x11")]
+unsafe extern "C" fn ignore_x_error(_: *mut libc::c_void, _: *mut libc::c_void) -> libc::c_int {
+    0
+}
+
+/* Reads the focused window from _NET_ACTIVE_WINDOW on the root window, as maintained by the window manager.
+ * Only built with the "x11" feature, which links libX11. */
+#[cfg(feature = "x11")]
+pub struct X11WindowContext
+{
+    display: *mut libc::c_void,
+    root: libc::c_ulong,
+    net_active_window: libc::c_ulong,
+    net_wm_name: libc::c_ulong,
+    wm_name: libc::c_ulong,
+    wm_class: libc::c_ulong,
+}
+
+#[cfg(feature = "x11")]
+impl X11WindowContext {
+    /* Connects to display, or $DISPLAY if None */
+    pub fn open(display_name: Option<&str>) -> Result<X11WindowContext, String> {
+        let display_name = match display_name {
+            Some(name) => Some(CString::new(name).map_err(|e| e.to_string())?),
+            None => None,
+        };
+
+        unsafe {
+            let display = XOpenDisplay(display_name.as_ref().map(|n| n.as_ptr()).unwrap_or(0 as *const libc::c_char));
+            if display.is_null() {
+                return Err(String::from("Cannot open X display"));
+            }
+
+            let atom = |name: &[u8]| XInternAtom(display, name.as_ptr() as *const libc::c_char, 0);
+            Ok(X11WindowContext {
+                display,
+                root: XDefaultRootWindow(display),
+                net_active_window: atom(b"_NET_ACTIVE_WINDOW\0"),
+                net_wm_name: atom(b"_NET_WM_NAME\0"),
+                wm_name: atom(b"WM_NAME\0"),
+                wm_class: atom(b"WM_CLASS\0"),
+            })
+        }
+    }
+
+    /* The property's format and up to 4 KiB of its data */
+    unsafe fn property(&self, window: libc::c_ulong, property: libc::c_ulong) -> Option<(libc::c_int, Vec<u8>)> {
+        let mut actual_type = 0;
+        let mut format = 0;
+        let mut item_count = 0;
+        let mut bytes_after = 0;
+        let mut data = 0 as *mut libc::c_uchar;
+
+        let status = XGetWindowProperty(self.display, window, property, 0, 1024, 0, ANY_PROPERTY_TYPE,
+            &mut actual_type, &mut format, &mut item_count, &mut bytes_after, &mut data);
+        if status != SUCCESS || data.is_null() { return None; }
+
+        /* Items of format 32 are longs on the client side */
+        let item_size = match format {
+            8 => 1,
+            16 => std::mem::size_of::<libc::c_short>(),
+            _ => std::mem::size_of::<libc::c_long>(),
+        };
+        let value = std::slice::from_raw_parts(data, item_count as usize * item_size).to_vec();
+        XFree(data as *mut libc::c_void);
+
+        if actual_type == 0 { None } else { Some((format, value)) }
+    }
+
+    unsafe fn string_property(&self, window: libc::c_ulong, property: libc::c_ulong) -> Option<String> {
+        match self.property(window, property) {
+            Some((8, value)) => Some(String::from_utf8_lossy(&value).into_owned()),
+            _ => None,
+        }
+    }
+
+    unsafe fn active_window(&self) -> Option<libc::c_ulong> {
+        match self.property(self.root, self.net_active_window) {
+            Some((32, value)) if value.len() >= std::mem::size_of::<libc::c_ulong>() => {
+                let window = (value.as_ptr() as *const libc::c_ulong).read_unaligned();
+                if window == 0 { None } else { Some(window) }
+            },
+            _ => None,
+        }
+    }
+}
+
+#[cfg(feature = "x11")]
+impl WindowContext for X11WindowContext {
+    fn focused_window(&mut self) -> Option<WindowInfo> {
+        unsafe {
+            /* The handler is process wide, whoever else uses Xlib, e.g. GTK, keeps theirs otherwise */
+            let previous_handler = XSetErrorHandler(Some(ignore_x_error));
+            let window = self.read_focused_window();
+            XSetErrorHandler(previous_handler);
+
+            window
+        }
+    }
+}
+
+#[cfg(feature = "x11")]
+impl X11WindowContext {
+    unsafe fn read_focused_window(&self) -> Option<WindowInfo> {
+        let window = self.active_window()?;
+
+        /* "instance\0class\0" */
+        let wm_class = self.string_property(window, self.wm_class)?;
+        let mut parts = wm_class.split('\0');
+        let instance = parts.next().unwrap_or("").to_string();
+        let class = parts.next().unwrap_or("").to_string();
+
+        let title = self.string_property(window, self.net_wm_name)
+            .or_else(|| self.string_property(window, self.wm_name))
+            .unwrap_or_default();
+
+        Some(WindowInfo { instance, class, title })
+    }
+}
+
+#[cfg(feature = "x11")]
+impl Drop for X11WindowContext {
+    fn drop(&mut self) {
+        unsafe { XCloseDisplay(self.display); }
+    }
+}
+
+#[cfg(all(test, feature = "x11"))]
+extern "C" {
+    #[no_mangle]
+    fn XCreateSimpleWindow(display: *mut libc::c_void, parent: libc::c_ulong, x: libc::c_int, y: libc::c_int, width: libc::c_uint,
+        height: libc::c_uint, border_width: libc::c_uint, border: libc::c_ulong, background: libc::c_ulong) -> libc::c_ulong;
+
+    #[no_mangle]
+    fn XChangeProperty(display: *mut libc::c_void, window: libc::c_ulong, property: libc::c_ulong, property_type: libc::c_ulong,
+        format: libc::c_int, mode: libc::c_int, data: *const libc::c_uchar, element_count: libc::c_int) -> libc::c_int;
+
+    #[no_mangle]
+    fn XDestroyWindow(display: *mut libc::c_void, window: libc::c_ulong) -> libc::c_int;
+
+    #[no_mangle]
+    fn XSync(display: *mut libc::c_void, discard: libc::c_int) -> libc::c_int;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(feature = "x11")]
+    use std::io::{BufRead, BufReader};
+    #[cfg(feature = "x11")]
+    use std::process::{Command, Stdio};
+
+    #[cfg(feature = "x11")]
+    const XA_STRING: libc::c_ulong = 31;
+    #[cfg(feature = "x11")]
+    const XA_WINDOW: libc::c_ulong = 33;
+
+    #[test]
+    fn matches_class_and_title_patterns() {
+        assert!(glob_matches("firefox", "Firefox"));
+        assert!(glob_matches("*YouTube*", "Music - YouTube — Mozilla Firefox"));
+        assert!(glob_matches("gnome-?erminal", "gnome-terminal"));
+        assert!(glob_matches("*a*b", "xaxxab"));
+        assert!(!glob_matches("fire", "firefox"));
+        assert!(!glob_matches("*.pdf", "report.pdf - Evince"));
+
+        let window = WindowInfo { instance: String::from("Navigator"), class: String::from("firefox"), title: String::from("Inbox - Mail") };
+        assert!(AppPattern::new("firefox").matches(&window));
+        assert!(AppPattern::new("navigator").matches(&window));
+        assert!(!AppPattern::new("chromium").matches(&window));
+        assert!(AppPattern { class: String::from("*"), title: Some(String::from("inbox*")) }.matches(&window));
+        assert!(!AppPattern { class: String::from("firefox"), title: Some(String::from("*YouTube*")) }.matches(&window));
+    }
+
+    /* Needs Xvfb, run it with `cargo test -- --ignored` */
+    #[test]
+    #[ignore]
+    #[cfg(feature = "x11")]
+    fn reads_the_active_window_from_xvfb() {
+        let mut server = Command::new("Xvfb").args(["-displayfd", "1", "-nolisten", "tcp"])
+            .stdout(Stdio::piped()).stderr(Stdio::null()).spawn()
+            .expect("Xvfb is needed for this test");
+        /* Printed once it accepts connections */
+        let mut display_number = String::new();
+        BufReader::new(server.stdout.take().unwrap()).read_line(&mut display_number).unwrap();
+        let display_name = format!(":{}", display_number.trim());
+
+        let mut context = X11WindowContext::open(Some(&display_name)).unwrap();
+        /* There is no window manager to maintain _NET_ACTIVE_WINDOW */
+        assert_eq!(context.focused_window(), None);
+
+        unsafe {
+            let display = context.display;
+            let window = XCreateSimpleWindow(display, context.root, 0, 0, 100, 100, 0, 0, 0);
+            let wm_class = b"Navigator\0firefox\0";
+            XChangeProperty(display, window, context.wm_class, XA_STRING, 8, 0, wm_class.as_ptr(), wm_class.len() as libc::c_int);
+            let title = "Music - YouTube — Mozilla Firefox";
+            let utf8_string = XInternAtom(display, b"UTF8_STRING\0".as_ptr() as *const libc::c_char, 0);
+            XChangeProperty(display, window, context.net_wm_name, utf8_string, 8, 0, title.as_ptr(), title.len() as libc::c_int);
+
+            let active: libc::c_ulong = window;
+            XChangeProperty(display, context.root, context.net_active_window, XA_WINDOW, 32, 0,
+                &active as *const libc::c_ulong as *const libc::c_uchar, 1);
+            XSync(display, 0);
+
+            assert_eq!(context.focused_window(), Some(WindowInfo {
+                instance: String::from("Navigator"),
+                class: String::from("firefox"),
+                title: title.to_string(),
+            }));
+
+            /* A window that went away is no window, rather than an X error */
+            XDestroyWindow(display, window);
+            XSync(display, 0);
+            assert_eq!(context.focused_window(), None);
+        }
+
+        drop(context);
+        let _ = server.kill();
+        let _ = server.wait();
+    }
+}
@@ -0,0 +1,335 @@
+/*
+ * Copyright © 2019 Romeo Calota
+ *
+ * This program is free software; you can redistribute it and/or
+ * modify it under the terms of the GNU Lesser General Public
+ * License as published by the Free Software Foundation; either
+ * version 2 of the licence, or (at your option) any later version.
+ *
+ * This software is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+ * Lesser General Public License for more details.
+ *
+ * You should have received a copy of the GNU Lesser General Public
+ * License along with this program; if not, see <http://www.gnu.org/licenses/>.
+ *
+ * Author: Romeo Calota
+ */
+
+use std::fmt;
+use std::str::FromStr;
+
+use crate::modifiers::Modifiers;
+use crate::{GestureType, PinchType, SwipeDirection};
+
+pub(crate) const GESTURE_DELTA: f64 = 10.0;
+
+/* A recognized gesture together with the number of fingers it was performed with */
+/* and the keyboard modifiers held when it started */
+/* Written as "swipe-up:3" or "super+pinch-in:2" in configuration files */
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Gesture
+{
+    pub gesture_type: GestureType,
+    pub finger_count: i32,
+    pub modifiers: Modifiers,
+}
+
+impl Gesture {
+    pub fn new(gesture_type: GestureType, finger_count: i32) -> Gesture {
+        Gesture { gesture_type, finger_count, modifiers: Modifiers::NONE }
+    }
+
+    pub fn with_modifiers(gesture_type: GestureType, finger_count: i32, modifiers: Modifiers) -> Gesture {
+        Gesture { gesture_type, finger_count, modifiers }
+    }
+}
+
+impl GestureType {
+    /* As in configuration files, "swipe" or "pinch" while the direction isn't known yet */
+    pub fn name(&self) -> &'static str {
+        match self {
+            GestureType::Swipe(SwipeDirection::SwipeUp) => "swipe-up",
+            GestureType::Swipe(SwipeDirection::SwipeDown) => "swipe-down",
+            GestureType::Swipe(SwipeDirection::SwipeLeft) => "swipe-left",
+            GestureType::Swipe(SwipeDirection::SwipeRight) => "swipe-right",
+            GestureType::Swipe(SwipeDirection::None) => "swipe",
+            GestureType::Pinch(PinchType::PinchIn) => "pinch-in",
+            GestureType::Pinch(PinchType::PinchOut) => "pinch-out",
+            GestureType::Pinch(PinchType::None) => "pinch",
+            GestureType::Unknown => "unknown",
+        }
+    }
+}
+
+impl fmt::Display for Gesture {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self.gesture_type {
+            GestureType::Swipe(SwipeDirection::None) | GestureType::Pinch(PinchType::None) => "unknown",
+            gesture_type => gesture_type.name(),
+        };
+
+        if !self.modifiers.is_empty() {
+            write!(f, "{}+", self.modifiers)?;
+        }
+
+        write!(f, "{}:{}", name, self.finger_count)
+    }
+}
+
+impl FromStr for Gesture {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Gesture, String> {
+        let mut modifiers = Modifiers::NONE;
+        let mut names: Vec<&str> = s.trim().split('+').collect();
+        let gesture = names.pop().unwrap_or("");
+        for name in names {
+            let modifier = Modifiers::from_name(name).ok_or_else(|| format!("Unknown modifier \"{}\"", name))?;
+            modifiers = modifiers.union(modifier);
+        }
+
+        let mut parts = gesture.splitn(2, ':');
+        let name = parts.next().unwrap_or("");
+        let finger_count = parts.next()
+            .and_then(|count| count.parse::<i32>().ok())
+            .ok_or_else(|| format!("Missing or invalid finger count in gesture \"{}\"", s))?;
+
+        let gesture_type = match name {
+            "swipe-up" => GestureType::Swipe(SwipeDirection::SwipeUp),
+            "swipe-down" => GestureType::Swipe(SwipeDirection::SwipeDown),
+            "swipe-left" => GestureType::Swipe(SwipeDirection::SwipeLeft),
+            "swipe-right" => GestureType::Swipe(SwipeDirection::SwipeRight),
+            "pinch-in" => GestureType::Pinch(PinchType::PinchIn),
+            "pinch-out" => GestureType::Pinch(PinchType::PinchOut),
+            _ => return Err(format!("Unknown gesture \"{}\"", name)),
+        };
+
+        Ok(Gesture { gesture_type, finger_count, modifiers })
+    }
+}
+
+/* Clockwise rotation of the device relative to the screen */
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Rotation
+{
+    None,
+    Rotate90,
+    Rotate180,
+    Rotate270,
+}
+
+impl Rotation {
+    /* Maps an angle in degrees (as reported by libinput) to the closest supported rotation */
+    pub fn from_degrees(degrees: u32) -> Rotation {
+        match ((degrees + 45) % 360) / 90 {
+            1 => Rotation::Rotate90,
+            2 => Rotation::Rotate180,
+            3 => Rotation::Rotate270,
+            _ => Rotation::None,
+        }
+    }
+}
+
+/* Remaps gesture deltas for natural direction and rotated devices */
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct DirectionTransform
+{
+    pub invert_x: bool,
+    pub invert_y: bool,
+    pub rotation: Rotation,
+}
+
+impl DirectionTransform {
+    pub fn identity() -> DirectionTransform {
+        DirectionTransform { invert_x: false, invert_y: false, rotation: Rotation::None }
+    }
+
+    pub fn apply(&self, dx: f64, dy: f64) -> (f64, f64) {
+        /* Screen coordinates grow downwards, so a clockwise rotation maps right onto down */
+        let (x, y) = match self.rotation {
+            Rotation::None => (dx, dy),
+            Rotation::Rotate90 => (-dy, dx),
+            Rotation::Rotate180 => (-dx, -dy),
+            Rotation::Rotate270 => (dy, -dx),
+        };
+
+        (if self.invert_x { -x } else { x }, if self.invert_y { -y } else { y })
+    }
+}
+
+/* Determines swipe direction from the unaccelerated deltas of a swipe gesture */
+#[derive(Debug)]
+pub struct SwipeRecognizer
+{
+    /* The absolute maximum value for swipe velocity */
+    /* These help determine swipe direction */
+    x_max: f64,
+    y_max: f64,
+
+    /* Distance travelled from the point where the swipe started */
+    x_total: f64,
+    y_total: f64,
+    peak_distance: f64,
+
+    /* When set, a swipe that ends closer to its origin than this fraction */
+    /* of the farthest distance it reached is treated as cancelled */
+    pub cancel_return_ratio: Option<f64>,
+}
+
+impl SwipeRecognizer {
+    pub fn new() -> SwipeRecognizer {
+        SwipeRecognizer {
+            x_max: 0.0,
+            y_max: 0.0,
+            x_total: 0.0,
+            y_total: 0.0,
+            peak_distance: 0.0,
+            cancel_return_ratio: None,
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.x_max = 0.0;
+        self.y_max = 0.0;
+        self.x_total = 0.0;
+        self.y_total = 0.0;
+        self.peak_distance = 0.0;
+    }
+
+    /* Feed one update and return the direction recognized so far */
+    pub fn update(&mut self, dx: f64, dy: f64) -> SwipeDirection {
+        if self.x_max.abs() < dx.abs() { self.x_max = dx; }
+        if self.y_max.abs() < dy.abs() { self.y_max = dy; }
+
+        self.x_total += dx;
+        self.y_total += dy;
+
+        let distance = self.x_total.hypot(self.y_total);
+        if distance > self.peak_distance { self.peak_distance = distance; }
+
+        self.direction()
+    }
+
+    pub fn direction(&self) -> SwipeDirection {
+        let x_max = self.x_max;
+        let y_max = self.y_max;
+
+        if y_max.abs() > x_max.abs() {
+            if y_max < -GESTURE_DELTA {
+                return SwipeDirection::SwipeUp;
+            } else if y_max > GESTURE_DELTA {
+                return SwipeDirection::SwipeDown;
+            }
+        } else if x_max.abs() > y_max.abs() {
+            if x_max < -GESTURE_DELTA {
+                return SwipeDirection::SwipeLeft;
+            } else if x_max > GESTURE_DELTA {
+                return SwipeDirection::SwipeRight;
+            }
+        }
+
+        SwipeDirection::None
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        match self.cancel_return_ratio {
+            Some(ratio) => {
+                let distance = self.x_total.hypot(self.y_total);
+                self.peak_distance > 0.0 && distance <= self.peak_distance * ratio
+            },
+            None => false,
+        }
+    }
+
+    /* Called when the fingers are lifted, returns SwipeDirection::None if the swipe was cancelled */
+    pub fn finish(&mut self) -> SwipeDirection {
+        let direction = if self.is_cancelled() { SwipeDirection::None } else { self.direction() };
+        self.reset();
+
+        direction
+    }
+}
+
+impl Default for SwipeRecognizer {
+    fn default() -> SwipeRecognizer {
+        SwipeRecognizer::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(recognizer: &mut SwipeRecognizer, deltas: &[(f64, f64)]) -> SwipeDirection {
+        for (dx, dy) in deltas {
+            recognizer.update(*dx, *dy);
+        }
+        recognizer.finish()
+    }
+
+    #[test]
+    fn recognizes_swipe_direction() {
+        let mut recognizer = SwipeRecognizer::new();
+        assert_eq!(run(&mut recognizer, &[(-4.0, 0.5), (-12.0, 1.0), (-8.0, 0.0)]), SwipeDirection::SwipeLeft);
+        assert_eq!(run(&mut recognizer, &[(1.0, -15.0), (0.0, -11.0)]), SwipeDirection::SwipeUp);
+        assert_eq!(run(&mut recognizer, &[(2.0, 3.0)]), SwipeDirection::None);
+    }
+
+    #[test]
+    fn gesture_round_trips_through_string() {
+        let gesture: Gesture = "pinch-out:2".parse().unwrap();
+        assert_eq!(gesture, Gesture::new(GestureType::Pinch(PinchType::PinchOut), 2));
+        assert_eq!(gesture.to_string(), "pinch-out:2");
+        assert!("swipe-up".parse::<Gesture>().is_err());
+
+        let gesture: Gesture = "Super+ctrl+swipe-left:3".parse().unwrap();
+        assert_eq!(gesture.modifiers, Modifiers::SUPER.union(Modifiers::CONTROL));
+        assert_ne!(gesture, "swipe-left:3".parse().unwrap());
+        assert_eq!(gesture.to_string(), "ctrl+super+swipe-left:3");
+        assert!("hyper+swipe-left:3".parse::<Gesture>().is_err());
+        assert!("swipe-sideways:3".parse::<Gesture>().is_err());
+    }
+
+    #[test]
+    fn transform_remaps_direction() {
+        let mut recognizer = SwipeRecognizer::new();
+        let mut transform = DirectionTransform::identity();
+        transform.rotation = Rotation::from_degrees(90);
+
+        let (dx, dy) = transform.apply(20.0, 0.0);
+        assert_eq!(run(&mut recognizer, &[(dx, dy)]), SwipeDirection::SwipeDown);
+
+        transform.rotation = Rotation::None;
+        transform.invert_y = true;
+        let (dx, dy) = transform.apply(0.0, -20.0);
+        assert_eq!(run(&mut recognizer, &[(dx, dy)]), SwipeDirection::SwipeDown);
+    }
+
+    #[test]
+    fn reversed_swipe_fires_when_cancellation_disabled() {
+        let mut recognizer = SwipeRecognizer::new();
+        let deltas = [(15.0, 0.0), (15.0, 0.0), (-14.0, 0.0), (-15.0, 0.0)];
+        assert_eq!(run(&mut recognizer, &deltas), SwipeDirection::SwipeRight);
+    }
+
+    #[test]
+    fn reversed_swipe_is_cancelled() {
+        let mut recognizer = SwipeRecognizer::new();
+        recognizer.cancel_return_ratio = Some(0.25);
+        let deltas = [(15.0, 0.0), (15.0, 0.0), (-14.0, 0.0), (-15.0, 0.0)];
+        assert_eq!(run(&mut recognizer, &deltas), SwipeDirection::None);
+
+        /* State is reset, so the next swipe is recognized normally */
+        assert_eq!(run(&mut recognizer, &[(0.0, 12.0), (0.0, 20.0)]), SwipeDirection::SwipeDown);
+    }
+
+    #[test]
+    fn partial_return_beyond_ratio_still_fires() {
+        let mut recognizer = SwipeRecognizer::new();
+        recognizer.cancel_return_ratio = Some(0.25);
+        let deltas = [(-20.0, 0.0), (-20.0, 0.0), (12.0, 0.0), (12.0, 0.0)];
+        assert_eq!(run(&mut recognizer, &deltas), SwipeDirection::SwipeLeft);
+    }
+}
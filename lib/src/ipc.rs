@@ -0,0 +1,361 @@
+/*
+ * Copyright © 2019 Romeo Calota
+ *
+ * This program is free software; you can redistribute it and/or
+ * modify it under the terms of the GNU Lesser General Public
+ * License as published by the Free Software Foundation; either
+ * version 2 of the licence, or (at your option) any later version.
+ *
+ * This software is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+ * Lesser General Public License for more details.
+ *
+ * You should have received a copy of the GNU Lesser General Public
+ * License along with this program; if not, see <http://www.gnu.org/licenses/>.
+ *
+ * Author: Romeo Calota
+ */
+
+use std::env;
+use std::fs;
+use std::io::{ErrorKind, Read};
+use std::os::unix::fs::PermissionsExt;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+
+use libc;
+
+use crate::config::parse_action;
+use crate::dbus_service::DaemonControl;
+use crate::device::Device;
+use crate::event_stream::{parse_kinds, EventSubscriber};
+use crate::events::{json_string, BackendEvent};
+use crate::gesture::Gesture;
+
+/* $XDG_RUNTIME_DIR/kinesix.sock. There is no fallback: anyone who can connect can bind commands, so the
+ * socket has to live in a directory only the user can enter, not in a guessable spot in /tmp. */
+pub fn default_socket_path() -> Option<PathBuf> {
+    match env::var_os("XDG_RUNTIME_DIR") {
+        Some(runtime_dir) if !runtime_dir.is_empty() => Some(PathBuf::from(runtime_dir).join("kinesix.sock")),
+        _ => None,
+    }
+}
+
+/* A device as one line of JSON */
+pub fn device_json(device: &Device, active: bool) -> String {
+    format!("{{\"path\":{},\"name\":{},\"vendor_id\":{},\"product_id\":{},\"kind\":{},\"active\":{}}}",
+        json_string(&device.path), json_string(&device.name), device.vendor_id, device.product_id, json_string(device.kind.name()), active)
+}
+
+fn error_json(message: &str) -> String {
+    format!("{{\"error\":{}}}", json_string(message))
+}
+
+enum Reply
+{
+    /* JSON lines, after which the connection is closed */
+    Lines(Vec<String>),
+    /* The client stays connected to receive events of these kinds, all of them if empty */
+    Watch(Vec<&'static str>),
+}
+
+/*
+ * One request per connection, a line such as "devices", "bind swipe-up:3 keys LeftMeta+PageUp",
+ * "unbind swipe-up:3", "profile", "profile NAME", "pause", "resume", "reload" or "watch [KINDS]".
+ * Replies are lines of JSON, {"error":"..."} if the request failed; a watch streams events as they
+ * happen, optionally only the kinds listed, e.g. "watch gesture-end,device-added,device-removed".
+ */
+fn handle_request(control: &mut dyn DaemonControl, request: &str) -> Result<Reply, String> {
+    let mut parts = request.trim().splitn(2, char::is_whitespace);
+    let command = parts.next().unwrap_or("");
+    let argument = parts.next().map(|a| a.trim()).unwrap_or("");
+    let ok = || Ok(Reply::Lines(vec![String::from("{\"ok\":true}")]));
+
+    match command {
+        "devices" => {
+            let active = control.active_device();
+            let lines = control.devices().iter()
+                .map(|device| device_json(device, Some(&device.path) == active.as_ref()))
+                .collect();
+            Ok(Reply::Lines(lines))
+        },
+        "watch" => Ok(Reply::Watch(parse_kinds(argument)?)),
+        "bind" => {
+            let mut parts = argument.splitn(2, char::is_whitespace);
+            let gesture = parts.next().unwrap_or("").parse::<Gesture>()?;
+            let action = parse_action(parts.next().map(|a| a.trim()).unwrap_or(""))?;
+            control.bind(gesture, action);
+            ok()
+        },
+        "unbind" => {
+            let gesture = argument.parse::<Gesture>()?;
+            if !control.unbind(gesture) {
+                return Err(format!("Nothing is bound to {}", gesture));
+            }
+            ok()
+        },
+        "profile" if argument.is_empty() => {
+            let profiles: Vec<String> = control.profiles().iter().map(|p| json_string(p)).collect();
+            Ok(Reply::Lines(vec![format!("{{\"active\":{},\"profiles\":[{}]}}", json_string(&control.active_profile()), profiles.join(","))]))
+        },
+        "profile" => {
+            control.set_profile(argument)?;
+            ok()
+        },
+        "pause" => { control.pause(); ok() },
+        "resume" => { control.resume(); ok() },
+        "reload" => { control.reload()?; ok() },
+        _ => Err(format!("Unknown command \"{}\"", command)),
+    }
+}
+
+struct IpcClient
+{
+    stream: UnixStream,
+    request: Vec<u8>,
+    subscriber: Option<EventSubscriber<UnixStream>>,
+    /* The reply still being written, the connection is closed once all of it is */
+    reply: Option<EventSubscriber<UnixStream>>,
+}
+
+/* The socket kinesixctl talks to, serviced from the event loop without blocking it */
+pub struct IpcServer
+{
+    listener: UnixListener,
+    path: PathBuf,
+    clients: Vec<IpcClient>,
+}
+
+impl IpcServer {
+    pub fn bind(path: &Path) -> Result<IpcServer, String> {
+        if path.exists() {
+            /* Left behind by an instance that didn't exit cleanly, unless something still answers */
+            if UnixStream::connect(path).is_ok() {
+                return Err(format!("Another instance is listening on {}", path.display()));
+            }
+            let _ = fs::remove_file(path);
+        }
+
+        /* Created without group and other permissions, so nobody else can connect before the chmod.
+         * The umask is process wide, other threads only lose group and other permissions meanwhile. */
+        let umask = unsafe { libc::umask(0o077) };
+        let listener = UnixListener::bind(path);
+        unsafe { libc::umask(umask); }
+
+        let listener = listener.map_err(|e| format!("{}: {}", path.display(), e))?;
+        listener.set_nonblocking(true).map_err(|e| e.to_string())?;
+        fs::set_permissions(path, fs::Permissions::from_mode(0o600)).map_err(|e| e.to_string())?;
+
+        Ok(IpcServer { listener, path: path.to_path_buf(), clients: Vec::new() })
+    }
+
+    /* Like Vec::retain_mut, which would raise the minimum Rust version to 1.61 */
+    fn retain_clients<Keep: FnMut(&mut IpcClient) -> bool>(&mut self, mut keep: Keep) {
+        let clients = std::mem::take(&mut self.clients);
+        self.clients = clients.into_iter()
+            .filter_map(|mut client| if keep(&mut client) { Some(client) } else { None })
+            .collect();
+    }
+
+    /* Accepts connections and answers complete requests */
+    pub fn poll(&mut self, control: &mut dyn DaemonControl) {
+        while let Ok((stream, _)) = self.listener.accept() {
+            if stream.set_nonblocking(true).is_ok() {
+                self.clients.push(IpcClient { stream, request: Vec::new(), subscriber: None, reply: None });
+            }
+        }
+
+        self.retain_clients(|client| {
+            if let Some(subscriber) = client.subscriber.as_mut() {
+                /* Reading is only here to notice the client going away */
+                let mut buffer = [0u8; 256];
+                let connected = match client.stream.read(&mut buffer) {
+                    Ok(0) => false,
+                    Err(e) => e.kind() == ErrorKind::WouldBlock,
+                    Ok(_) => true,
+                };
+                return connected && subscriber.flush().is_ok();
+            }
+
+            if let Some(reply) = client.reply.as_mut() {
+                return reply.flush().is_ok() && reply.has_pending();
+            }
+
+            let mut buffer = [0u8; 1024];
+            let mut closed = false;
+            loop {
+                match client.stream.read(&mut buffer) {
+                    Ok(0) => { closed = true; break; },
+                    Ok(read) => client.request.extend_from_slice(&buffer[..read]),
+                    Err(ref e) if e.kind() == ErrorKind::WouldBlock => break,
+                    Err(_) => return false,
+                }
+            }
+
+            let end = match client.request.iter().position(|b| *b == b'\n') {
+                Some(end) => end,
+                /* A client that stops sending won't finish its request */
+                None => return !closed && client.request.len() < 64 * 1024,
+            };
+            let request = String::from_utf8_lossy(&client.request[..end]).into_owned();
+
+            let lines = match handle_request(control, &request) {
+                Ok(Reply::Watch(kinds)) => {
+                    return match client.stream.try_clone() {
+                        Ok(writer) => {
+                            client.subscriber = Some(EventSubscriber::new(writer, kinds));
+                            true
+                        },
+                        Err(_) => false,
+                    };
+                },
+                Ok(Reply::Lines(lines)) => lines,
+                Err(e) => vec![error_json(&e)],
+            };
+
+            let mut reply = match client.stream.try_clone() {
+                Ok(writer) => EventSubscriber::new(writer, Vec::new()),
+                Err(_) => return false,
+            };
+            let sent = lines.iter().try_for_each(|line| reply.send_line(line)).is_ok();
+            let pending = reply.has_pending();
+            client.reply = Some(reply);
+            sent && pending
+        });
+    }
+
+    /* Queues the event for watchers, never waiting on them */
+    pub fn broadcast(&mut self, event: &BackendEvent) {
+        self.retain_clients(|client| {
+            match client.subscriber.as_mut() {
+                Some(subscriber) => subscriber.send(event).is_ok(),
+                None => true,
+            }
+        });
+    }
+}
+
+impl Drop for IpcServer {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::action::Action;
+    use crate::device::DeviceKind;
+    use crate::{GestureType, SwipeDirection};
+    use std::io::{BufRead, BufReader, Write};
+
+    struct MockControl
+    {
+        paused: bool,
+        bindings: Vec<(Gesture, Action)>,
+        profile: String,
+    }
+
+    impl DaemonControl for MockControl {
+        fn pause(&mut self) { self.paused = true; }
+        fn resume(&mut self) { self.paused = false; }
+        fn is_paused(&self) -> bool { self.paused }
+        fn reload(&mut self) -> Result<(), String> { Err(String::from("No configuration file")) }
+
+        fn devices(&mut self) -> Vec<Device> {
+            vec![Device { id: 0, path: String::from("/dev/input/event4"), name: String::from("Touchpad \"X\""), product_id: 52, vendor_id: 1739, kind: DeviceKind::Touchpad }]
+        }
+
+        fn active_device(&self) -> Option<String> { Some(String::from("/dev/input/event4")) }
+        fn active_profile(&self) -> String { self.profile.clone() }
+        fn profiles(&self) -> Vec<String> { vec![String::from("default"), String::from("touch")] }
+
+        fn set_profile(&mut self, name: &str) -> Result<(), String> {
+            if !self.profiles().iter().any(|p| p == name) { return Err(format!("Unknown profile \"{}\"", name)); }
+            self.profile = name.to_string();
+            Ok(())
+        }
+
+        fn bind(&mut self, gesture: Gesture, action: Action) { self.bindings.push((gesture, action)); }
+
+        fn unbind(&mut self, gesture: Gesture) -> bool {
+            let count = self.bindings.len();
+            self.bindings.retain(|(g, _)| *g != gesture);
+            self.bindings.len() != count
+        }
+    }
+
+    fn request(server: &mut IpcServer, control: &mut MockControl, request: &str) -> Vec<String> {
+        let mut client = UnixStream::connect(&server.path).unwrap();
+        client.write_all(request.as_bytes()).unwrap();
+        client.write_all(b"\n").unwrap();
+        server.poll(control);
+
+        BufReader::new(client).lines().map(|l| l.unwrap()).collect()
+    }
+
+    #[test]
+    fn answers_requests_over_the_socket() {
+        let path = env::temp_dir().join(format!("kinesix-ipc-test-{}.sock", std::process::id()));
+        let mut server = IpcServer::bind(&path).unwrap();
+        let mut control = MockControl { paused: false, bindings: Vec::new(), profile: String::from("default") };
+        assert_eq!(fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o600);
+
+        assert_eq!(request(&mut server, &mut control, "devices"), vec![
+            "{\"path\":\"/dev/input/event4\",\"name\":\"Touchpad \\\"X\\\"\",\"vendor_id\":1739,\"product_id\":52,\"kind\":\"touchpad\",\"active\":true}",
+        ]);
+        assert_eq!(request(&mut server, &mut control, "bind swipe-up:3 keys LeftMeta+PageUp"), vec!["{\"ok\":true}"]);
+        assert_eq!(control.bindings.len(), 1);
+        assert_eq!(request(&mut server, &mut control, "unbind swipe-up:3"), vec!["{\"ok\":true}"]);
+        assert_eq!(request(&mut server, &mut control, "unbind swipe-up:3"), vec!["{\"error\":\"Nothing is bound to swipe-up:3\"}"]);
+        assert_eq!(request(&mut server, &mut control, "profile touch"), vec!["{\"ok\":true}"]);
+        assert_eq!(request(&mut server, &mut control, "profile"), vec!["{\"active\":\"touch\",\"profiles\":[\"default\",\"touch\"]}"]);
+        assert_eq!(request(&mut server, &mut control, "pause"), vec!["{\"ok\":true}"]);
+        assert!(control.paused);
+        assert_eq!(request(&mut server, &mut control, "reload"), vec!["{\"error\":\"No configuration file\"}"]);
+        assert_eq!(request(&mut server, &mut control, "jump"), vec!["{\"error\":\"Unknown command \\\"jump\\\"\"}"]);
+
+        assert_eq!(request(&mut server, &mut control, "watch gesture-end,gestures"), vec![
+            "{\"error\":\"Unknown event kind \\\"gestures\\\", expected one of gesture-begin, gesture-update, gesture-end, profile-changed, device-changed, paused-changed, device-added, device-removed, action-result\"}",
+        ]);
+
+        /* Watchers get the kinds of event they asked for, or all of them */
+        let mut watcher = UnixStream::connect(&path).unwrap();
+        watcher.write_all(b"watch gesture-end,device-removed\n").unwrap();
+        let mut everything = UnixStream::connect(&path).unwrap();
+        everything.write_all(b"watch\n").unwrap();
+        server.poll(&mut control);
+        server.broadcast(&BackendEvent::GestureBegin(GestureType::Swipe(SwipeDirection::None), 3));
+        server.broadcast(&BackendEvent::GestureEnd(GestureType::Swipe(SwipeDirection::SwipeLeft), 3, false));
+        server.broadcast(&BackendEvent::ActionResult("command", Ok(())));
+        server.broadcast(&BackendEvent::DeviceRemoved(String::from("/dev/input/event4")));
+        drop(server);
+
+        let lines: Vec<String> = BufReader::new(watcher).lines().map(|l| l.unwrap()).collect();
+        assert_eq!(lines, vec![
+            "{\"event\":\"gesture-end\",\"gesture\":\"swipe-left\",\"finger_count\":3,\"cancelled\":false}",
+            "{\"event\":\"device-removed\",\"device\":\"/dev/input/event4\"}",
+        ]);
+        let lines: Vec<String> = BufReader::new(everything).lines().map(|l| l.unwrap()).collect();
+        assert_eq!(lines.len(), 4);
+        assert_eq!(lines[2], "{\"event\":\"action-result\",\"action\":\"command\",\"ok\":true}");
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn drops_clients_that_hang_up_early() {
+        let path = env::temp_dir().join(format!("kinesix-ipc-hangup-test-{}.sock", std::process::id()));
+        let mut server = IpcServer::bind(&path).unwrap();
+        let mut control = MockControl { paused: false, bindings: Vec::new(), profile: String::from("default") };
+
+        let mut client = UnixStream::connect(&path).unwrap();
+        client.write_all(b"devi").unwrap();
+        server.poll(&mut control);
+        assert_eq!(server.clients.len(), 1);
+
+        client.shutdown(std::net::Shutdown::Write).unwrap();
+        server.poll(&mut control);
+        assert!(server.clients.is_empty());
+    }
+}
@@ -17,19 +17,67 @@
  * Author: Romeo Calota
  */
 
+pub mod action;
+pub mod config;
+pub mod dbus;
+pub mod dbus_service;
 pub mod device;
+pub mod event_stream;
+pub mod events;
+pub mod focused_window;
+pub mod gesture;
+pub mod ipc;
+pub mod macros;
+pub mod modifiers;
+pub mod multitouch;
+pub mod profile;
+pub mod proportional;
+pub mod raw_gesture;
+pub mod raw_touchpad;
+pub mod region;
+pub mod sequence;
+pub mod stroke;
+pub mod stroke_mouse;
+pub mod switches;
+pub mod tablet_pad;
+pub mod tap;
+pub mod two_finger_swipe;
+pub mod template;
 
 use std::fs;
 use std::os::unix::fs::FileTypeExt;
 use std::str;
-use std::sync::mpsc;
-use std::time::Duration;
+use std::sync::{mpsc, Once};
+use std::time::{Duration, Instant};
 
 use libc;
 
-use crate::device::Device;
-use std::borrow::Borrow;
-use std::ffi::{CStr};
+use crate::action::Action;
+use crate::config::{Config, VIRTUAL_DEVICE_NAME};
+use crate::dbus::DBusConnection;
+use crate::dbus_service::{DaemonControl, DBusService};
+use crate::device::{Device, DeviceKind};
+use crate::events::BackendEvent;
+use crate::focused_window::WindowContext;
+use crate::gesture::{DirectionTransform, Gesture, Rotation, SwipeRecognizer};
+use crate::ipc::IpcServer;
+use crate::macros::RunningMacro;
+use crate::modifiers::{ModifierState, Modifiers};
+use crate::profile::{Profile, DEFAULT_PROFILE};
+use crate::proportional::{ProportionalAction, ProportionalTracker};
+use crate::raw_gesture::RawGestureEvent;
+use crate::raw_touchpad::{RawTouchpad, TouchpadClaims};
+use crate::region::{Region, RegionTrigger};
+use crate::sequence::{AmbiguityPolicy, GestureSequence, SequenceEvent, SequenceMatcher};
+use crate::stroke::{Stroke, StrokeRecorder, TouchStrokeTracker};
+use crate::stroke_mouse::{StrokeMouse, StrokeMouseEvent};
+use crate::switches::SwitchTrigger;
+use crate::tablet_pad::{PadBinding, PadTracker, PadTrigger};
+use crate::tap::Tap;
+use crate::template::{StrokeTemplate, TemplateRecognizer};
+use std::ffi::{CStr, CString};
+
+use virtualinput::{Button, Keymap, KeymapSource, UnicodeFallback, VirtualInputBuilder};
 
 const POLLIN: libc::c_short = 0x1;
 
@@ -53,6 +101,9 @@ extern "C" {
     #[no_mangle]
     fn strncpy(destination: *mut libc::c_char, source: *const libc::c_char, length: libc::size_t) -> *mut libc::c_char;
 
+    #[no_mangle]
+    fn g_unix_signal_add(signum: libc::c_int, handler: unsafe extern "C" fn(*mut libc::c_void) -> i32, data: *mut libc::c_void) -> u32;
+
     #[no_mangle]
     fn g_timeout_add_full(priority: i32, interval: u32, fun: unsafe extern "C" fn(*mut libc::c_void) -> i32, data: *mut libc::c_void, notify: *mut libc::c_void) -> u32;
 
@@ -74,6 +125,69 @@ extern "C" {
     #[no_mangle]
     fn libinput_event_get_type(event: *mut libc::c_void) -> libinput::EventType;
 
+    #[no_mangle]
+    fn libinput_event_get_device(event: *mut libc::c_void) -> *mut libc::c_void;
+
+    #[no_mangle]
+    fn libinput_event_get_keyboard_event(event: *mut libc::c_void) -> *mut libc::c_void;
+
+    #[no_mangle]
+    fn libinput_event_keyboard_get_key(keyboard_event: *mut libc::c_void) -> u32;
+
+    #[no_mangle]
+    fn libinput_event_get_touch_event(event: *mut libc::c_void) -> *mut libc::c_void;
+
+    #[no_mangle]
+    fn libinput_event_touch_get_seat_slot(touch_event: *mut libc::c_void) -> i32;
+
+    #[no_mangle]
+    fn libinput_event_touch_get_x(touch_event: *mut libc::c_void) -> f64;
+
+    #[no_mangle]
+    fn libinput_event_touch_get_y(touch_event: *mut libc::c_void) -> f64;
+
+    #[no_mangle]
+    fn libinput_event_keyboard_get_key_state(keyboard_event: *mut libc::c_void) -> i32;
+
+    #[no_mangle]
+    fn libinput_event_get_tablet_pad_event(event: *mut libc::c_void) -> *mut libc::c_void;
+
+    #[no_mangle]
+    fn libinput_event_tablet_pad_get_button_number(pad_event: *mut libc::c_void) -> u32;
+
+    #[no_mangle]
+    fn libinput_event_tablet_pad_get_button_state(pad_event: *mut libc::c_void) -> i32;
+
+    #[no_mangle]
+    fn libinput_event_tablet_pad_get_ring_position(pad_event: *mut libc::c_void) -> f64;
+
+    #[no_mangle]
+    fn libinput_event_tablet_pad_get_ring_number(pad_event: *mut libc::c_void) -> u32;
+
+    #[no_mangle]
+    fn libinput_event_tablet_pad_get_strip_position(pad_event: *mut libc::c_void) -> f64;
+
+    #[no_mangle]
+    fn libinput_event_tablet_pad_get_strip_number(pad_event: *mut libc::c_void) -> u32;
+
+    #[no_mangle]
+    fn libinput_event_tablet_pad_get_mode(pad_event: *mut libc::c_void) -> u32;
+
+    #[no_mangle]
+    fn libinput_event_tablet_pad_get_mode_group(pad_event: *mut libc::c_void) -> *mut libc::c_void;
+
+    #[no_mangle]
+    fn libinput_tablet_pad_mode_group_get_index(group: *mut libc::c_void) -> u32;
+
+    #[no_mangle]
+    fn libinput_event_get_switch_event(event: *mut libc::c_void) -> *mut libc::c_void;
+
+    #[no_mangle]
+    fn libinput_event_switch_get_switch(switch_event: *mut libc::c_void) -> u32;
+
+    #[no_mangle]
+    fn libinput_event_switch_get_switch_state(switch_event: *mut libc::c_void) -> u32;
+
     #[no_mangle]
     fn libinput_event_gesture_get_finger_count(gesture_event: *mut libc::c_void) -> i32;
 
@@ -113,11 +227,17 @@ extern "C" {
     #[no_mangle]
     fn libinput_device_get_name(device: *mut libc::c_void) -> *const libc::c_char;
 
+    #[no_mangle]
+    fn libinput_device_get_sysname(device: *mut libc::c_void) -> *const libc::c_char;
+
     #[no_mangle]
     fn libinput_device_get_id_product(device: *mut libc::c_void) -> u32;
 
     #[no_mangle]
     fn libinput_device_get_id_vendor(device: *mut libc::c_void) -> u32;
+
+    #[no_mangle]
+    fn libinput_device_config_rotation_get_angle(device: *mut libc::c_void) -> u32;
 }
 
 mod libinput {
@@ -192,13 +312,24 @@ mod libinput {
     }
 }
 
-unsafe extern "C" fn open_restricted(path: *const libc::c_char, flags: i32, _: *mut libc::c_void) -> i32 {
-    let fd;
-    fd = open(path, flags);
+unsafe extern "C" fn open_restricted(path: *const libc::c_char, flags: i32, user_data: *mut libc::c_void) -> i32 {
+    let mut flags = flags;
+
+    /* Devices we only listen to are opened read-only */
+    if !user_data.is_null() {
+        let listen_only_paths = &*(user_data as *const Vec<String>);
+        let path = CStr::from_ptr(path).to_str().unwrap_or("");
+        if listen_only_paths.iter().any(|p| p == path) {
+            flags = (flags & !libc::O_ACCMODE) | libc::O_RDONLY;
+        }
+    }
+
+    /* libinput expects -errno, e.g. for devices the user may not read while scanning /dev/input */
+    let fd = open(path, flags);
     if fd == -1 {
-        println!("Failed to open file descriptor.");
-        exit(-1);
+        return -std::io::Error::last_os_error().raw_os_error().unwrap_or(libc::EIO);
     }
+
     fd
 }
 
@@ -212,10 +343,11 @@ pub struct Input {
     pub instance: *mut libc::c_void,
     pub active_device: *mut libc::c_void,
 
-    /* The absolute maximum value for swipe velocity */
-    /* These help determine swipe direction */
-    pub swipe_x_max: f64,
-    pub swipe_y_max: f64,
+    /* Keyboards and switches added in listen-only mode, to track modifiers and lid/tablet mode */
+    pub listen_only_devices: Vec<*mut libc::c_void>,
+    /* Boxed so open_restricted's user_data keeps pointing at it when Input moves */
+    #[allow(clippy::box_collection)]
+    listen_only_paths: Box<Vec<String>>,
 }
 
 impl Input {
@@ -226,12 +358,15 @@ impl Input {
                 close_restricted: Some(close_restricted)
             }));
 
+            let listen_only_paths = Box::new(Vec::new());
+            let user_data = &*listen_only_paths as *const Vec<String> as *const libc::c_void;
+
             let mut self_ = Input {
                 interface: Box::from_raw(0 as *mut libinput::Interface),
-                instance: libinput_path_create_context(interface as *const libinput::Interface, 0 as *const libc::c_void),
+                instance: libinput_path_create_context(interface as *const libinput::Interface, user_data),
                 active_device: 0 as *mut libc::c_void,
-                swipe_x_max: 0.0,
-                swipe_y_max: 0.0,
+                listen_only_devices: Vec::new(),
+                listen_only_paths,
             };
             self_.interface = Box::from_raw(interface);
 
@@ -243,6 +378,9 @@ impl Input {
 impl Drop for Input {
     fn drop(&mut self) {
         unsafe {
+            for device in self.listen_only_devices.drain(..) {
+                libinput_device_unref(device);
+            }
             libinput_unref(self.instance);
         }
     }
@@ -292,7 +430,7 @@ pub enum GestureEventState
 }
 
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq, Clone, Copy)]
 pub enum GestureType
 {
     Swipe(SwipeDirection),
@@ -301,7 +439,12 @@ pub enum GestureType
 }
 
 const DEVICES_PATH: &str = "/dev/input/";
-const GESTURE_DELTA: f64 = 10.0;
+/* Touchscreens report millimeters, strokes are measured in pixels of a roughly 100 dpi screen */
+const TOUCHSCREEN_PIXELS_PER_MM: f64 = 4.0;
+
+/* Receives the template once the stroke being recorded is done */
+type TemplateRecorded = Box<dyn FnMut(StrokeTemplate)>;
+type EventListener = Box<dyn FnMut(&BackendEvent)>;
 
 pub struct KinesixBackend
 {
@@ -314,20 +457,83 @@ pub struct KinesixBackend
     ongoing_gesture_type: GestureType,
     input: Input,
 
+    /* Modifiers held on any keyboard, and the ones held when the ongoing gesture started */
+    modifier_state: ModifierState,
+    gesture_modifiers: Modifiers,
+    gesture_finger_count: i32,
+
+    swipe_recognizer: SwipeRecognizer,
+
+    /* Direction transforms keyed by device path, the active one applies to the active device */
+    device_transforms: Vec<(String, DirectionTransform)>,
+    use_device_rotation: bool,
+    active_transform: DirectionTransform,
+
+    virtual_input: Option<virtualinput::VirtualInput>,
+    /* What virtual_input was created with, or failed to be */
+    virtual_device: VirtualInputBuilder,
+    /* Layout text actions are typed with, kept for when virtual_input is created */
+    keymap: Option<Keymap>,
+    unicode_fallback: UnicodeFallback,
+    /* Where keymap is compiled from once a text action needs it */
+    keymap_source: Option<KeymapSource>,
+
+    /* The default profile always comes first */
+    profiles: Vec<Profile>,
+    active_profile: usize,
+
+    /* The proportional action driven by the ongoing gesture */
+    active_proportional_action: Option<ProportionalAction>,
+    proportional_tracker: ProportionalTracker,
+
+    pad_tracker: PadTracker,
+
+    /* Mouse strokes, drawn while holding stroke_button */
+    stroke_button: Option<Button>,
+    stroke_recorder: StrokeRecorder,
+    stroke_in_progress: bool,
+    /* Mice with stroke_button, grabbed and read through evdev */
+    stroke_mice: Vec<StrokeMouse>,
+    /* Touchscreen strokes, None while disabled */
+    touch_stroke: Option<TouchStrokeTracker>,
+    template_recognizer: TemplateRecognizer,
+    /* Set while the next stroke is to be recorded as a template, instead of triggering actions */
+    template_recording: Option<(String, TemplateRecorded)>,
+    /* The active touchpad read through evdev, when enabled */
+    use_raw_touchpad: bool,
+    grab_touchpad: bool,
+    raw_touchpad: Option<RawTouchpad>,
+    regions: Vec<Region>,
+
+    /* Actions for the sequences registered with the matcher, in the same order */
+    sequence_matcher: SequenceMatcher,
+    sequence_actions: Vec<Action>,
+
+    /* Macros waiting out a sleep step, advanced from the event loop */
+    running_macros: Vec<RunningMacro>,
+
+    /* While paused only keyboard modifiers are tracked, gestures and mouse strokes are left to the desktop */
+    paused: bool,
+    dbus_service: Option<DBusService>,
+    ipc_server: Option<IpcServer>,
+    /* Events raised while ipc_server is taken out to handle requests, broadcast once it is back */
+    ipc_held_events: Option<Vec<BackendEvent>>,
+    /* What D-Bus actions call on, connected once on first use instead of for every call */
+    session_bus: Option<DBusConnection>,
+    event_listeners: Vec<EventListener>,
+    /* Asked for the focused window when a profile has [app] bindings */
+    window_context: Option<Box<dyn WindowContext>>,
+
     event_poller_thread: Option<EventPollerThread>,
 }
 
 impl KinesixBackend
 {
     pub fn new<SwipeDelegate: 'static + FnMut(SwipeDirection, i32), PinchDelegate: 'static + FnMut(PinchType, i32)>(swipe_delegate: SwipeDelegate, pinch_delegate: PinchDelegate) -> KinesixBackend {
-        let vi = virtualinput::VirtualInput::new("dummy");
-        if vi.is_ok() {
-            let result = vi.ok().unwrap().press(vec![virtualinput::Key::LeftShift, virtualinput::Key::A].borrow(), true);
-            if !result.is_ok() {
-                println!("{}", result.err().unwrap());
-            }
-        } else {
-            println!("{}", vi.err().unwrap());
+        let virtual_device = VirtualInputBuilder::new(VIRTUAL_DEVICE_NAME);
+        let vi = virtual_device.clone().build();
+        if vi.is_err() {
+            println!("{}", vi.as_ref().err().unwrap());
         }
         KinesixBackend {
             active_device: std::ptr::null(),
@@ -336,6 +542,44 @@ impl KinesixBackend
             pinch_delegate: Box::new(pinch_delegate),
             ongoing_gesture_type: GestureType::Unknown,
             input: Input::new(),
+            modifier_state: ModifierState::new(),
+            gesture_modifiers: Modifiers::NONE,
+            gesture_finger_count: 0,
+            swipe_recognizer: SwipeRecognizer::new(),
+            device_transforms: Vec::new(),
+            use_device_rotation: false,
+            active_transform: DirectionTransform::identity(),
+            virtual_input: vi.ok(),
+            virtual_device,
+            keymap: None,
+            unicode_fallback: UnicodeFallback::Disabled,
+            keymap_source: None,
+            profiles: vec![Profile::new(DEFAULT_PROFILE)],
+            active_profile: 0,
+            active_proportional_action: None,
+            proportional_tracker: ProportionalTracker::new(),
+            pad_tracker: PadTracker::new(),
+            stroke_button: None,
+            stroke_recorder: StrokeRecorder::new(),
+            stroke_in_progress: false,
+            stroke_mice: Vec::new(),
+            touch_stroke: None,
+            template_recognizer: TemplateRecognizer::new(),
+            template_recording: None,
+            use_raw_touchpad: false,
+            grab_touchpad: false,
+            raw_touchpad: None,
+            regions: Vec::new(),
+            sequence_matcher: SequenceMatcher::new(AmbiguityPolicy::WaitForSequence),
+            sequence_actions: Vec::new(),
+            running_macros: Vec::new(),
+            paused: false,
+            dbus_service: None,
+            ipc_server: None,
+            ipc_held_events: None,
+            session_bus: None,
+            event_listeners: Vec::new(),
+            window_context: None,
             event_poller_thread: None,
         }
     }
@@ -351,11 +595,19 @@ impl KinesixBackend
 
             let libinput_dev = libinput_path_add_device(self.input.instance, device_path_cstr as *const libc::c_char);
             if libinput_dev as usize != 0 {
-                if libinput_device_has_capability(libinput_dev, libinput::DeviceCapability::Gesture) != 0 {
+                let kind = if libinput_device_has_capability(libinput_dev, libinput::DeviceCapability::Gesture) != 0 {
+                    Some(DeviceKind::Touchpad)
+                } else if libinput_device_has_capability(libinput_dev, libinput::DeviceCapability::TabletPad) != 0 {
+                    Some(DeviceKind::TabletPad)
+                } else {
+                    None
+                };
+
+                if let Some(kind) = kind {
                     let device_name = CStr::from_ptr(libinput_device_get_name(libinput_dev)).to_str().unwrap();
                     let product_id = libinput_device_get_id_product(libinput_dev);
                     let vendor_id = libinput_device_get_id_vendor(libinput_dev);
-                    return Device::new(device_path, device_name, product_id, vendor_id);
+                    return Device::new(device_path, device_name, product_id, vendor_id, kind);
                 }
 
                 libinput_path_remove_device(libinput_dev);
@@ -401,6 +653,10 @@ impl KinesixBackend
                 libinput_path_remove_device(active_device);
                 libinput_device_unref(active_device);
             }
+            /* Libinput reports the removal once the device is no longer the active one, it would go unnoticed */
+            if let Some(path) = self.active_device_path() {
+                self.notify(BackendEvent::DeviceRemoved(path));
+            }
         }
 
         unsafe {
@@ -418,6 +674,934 @@ impl KinesixBackend
                 self.active_device = &self.valid_device_list[search_result.ok().unwrap()] as *const Device;
             }
         }
+
+        self.update_active_transform();
+        self.update_raw_touchpad();
+
+        if let Some(path) = self.active_device_path() {
+            self.notify(BackendEvent::DeviceChanged(path));
+        }
+    }
+
+    /* Path of the device gestures are read from */
+    pub fn active_device_path(&self) -> Option<String> {
+        if self.active_device.is_null() { return None; }
+
+        unsafe { Some((*(self.active_device)).path.clone()) }
+    }
+
+    /* Keyboards, switches, tablet pads and touchscreens are added next to the touchpad, without grabbing
+     * them, so gestures can be qualified by the modifiers held while performing them, lid/tablet mode
+     * changes seen, pad controls bound and strokes drawn on the touchscreen while the touchpad is in use. */
+    fn open_auxiliary_devices(&mut self) {
+        if !self.input.listen_only_devices.is_empty() { return; }
+
+        let devices = match fs::read_dir(DEVICES_PATH) {
+            Ok(devices) => devices,
+            Err(_) => return,
+        };
+
+        /* Skip our own virtual keyboard, otherwise actions pressing modifiers would feed back */
+        let virtual_devnode = self.virtual_input.as_ref().and_then(|vi| vi.devnode());
+        let active_path = if self.active_device.is_null() { None } else { unsafe { Some((*(self.active_device)).path.clone()) } };
+
+        for device in devices.flatten() {
+            let is_char_device = device.file_type().map(|t| t.is_char_device()).unwrap_or(false);
+            let path = device.path().to_string_lossy().into_owned();
+            if !is_char_device || Some(&path) == virtual_devnode.as_ref() || Some(&path) == active_path.as_ref() {
+                continue;
+            }
+
+            let device_path_cstr = match CString::new(path.as_str()) {
+                Ok(cstr) => cstr,
+                Err(_) => continue,
+            };
+
+            self.input.listen_only_paths.push(path);
+            unsafe {
+                let libinput_dev = libinput_path_add_device(self.input.instance, device_path_cstr.as_ptr());
+                if libinput_dev as usize != 0 {
+                    let has_capability = |capability| libinput_device_has_capability(libinput_dev, capability) != 0;
+
+                    if has_capability(libinput::DeviceCapability::Keyboard) || has_capability(libinput::DeviceCapability::Switch) ||
+                        has_capability(libinput::DeviceCapability::TabletPad) || has_capability(libinput::DeviceCapability::Touch) {
+                        libinput_device_ref(libinput_dev);
+                        self.input.listen_only_devices.push(libinput_dev);
+                        continue;
+                    }
+                    libinput_path_remove_device(libinput_dev);
+                }
+            }
+            self.input.listen_only_paths.pop();
+        }
+    }
+
+    fn handle_keyboard_key(&mut self, event: *mut libc::c_void) {
+        let (key, state) = unsafe {
+            let keyboard_event = libinput_event_get_keyboard_event(event);
+            (libinput_event_keyboard_get_key(keyboard_event), libinput_event_keyboard_get_key_state(keyboard_event))
+        };
+
+        self.modifier_state.update(key, state != 0);
+    }
+
+    fn handle_device_event(&mut self, event: *mut libc::c_void, event_type: libinput::EventType) {
+        let device = unsafe { libinput_event_get_device(event) };
+
+        /* Devices only probed while listing them come and go too, report the ones in use */
+        let in_use = device == self.input.active_device || self.input.listen_only_devices.contains(&device);
+        if !in_use { return; }
+
+        let (path, name) = unsafe {
+            let sysname = CStr::from_ptr(libinput_device_get_sysname(device)).to_string_lossy();
+            let name = CStr::from_ptr(libinput_device_get_name(device)).to_string_lossy().into_owned();
+            (format!("{}{}", DEVICES_PATH, sysname), name)
+        };
+
+        match event_type {
+            libinput::EventType::DeviceAdded => self.notify(BackendEvent::DeviceAdded(path, name)),
+            _ => {
+                /* Unplugged, libinput has dropped the device already and only our reference is left */
+                if device == self.input.active_device {
+                    self.input.active_device = 0 as *mut libc::c_void;
+                    self.active_device = 0 as *const Device;
+                    self.update_raw_touchpad();
+                } else {
+                    self.input.listen_only_devices.retain(|d| *d != device);
+                }
+                unsafe { libinput_device_unref(device); }
+
+                self.notify(BackendEvent::DeviceRemoved(path));
+            },
+        }
+    }
+
+    fn handle_event(&mut self, event: *mut libc::c_void) {
+        let event_type = unsafe {
+            libinput_event_get_type(event)
+        };
+
+        match event_type {
+            libinput::EventType::DeviceAdded |
+            libinput::EventType::DeviceRemoved => return self.handle_device_event(event, event_type),
+            _ => {},
+        }
+
+        if self.paused {
+            if let libinput::EventType::KeyboardKey = event_type { self.handle_keyboard_key(event); }
+            return;
+        }
+
+        match event_type {
+            libinput::EventType::KeyboardKey => self.handle_keyboard_key(event),
+            libinput::EventType::TabletPadButton |
+            libinput::EventType::TabletPadRing |
+            libinput::EventType::TabletPadStrip => self.handle_tablet_pad(event, event_type),
+            libinput::EventType::SwitchToggle => self.handle_switch(event),
+            libinput::EventType::TouchDown |
+            libinput::EventType::TouchUp |
+            libinput::EventType::TouchMotion |
+            libinput::EventType::TouchCancel => self.handle_touch(event, event_type),
+            libinput::EventType::TouchFrame => {},
+            /* Mice are read through evdev, see update_stroke_mice */
+            libinput::EventType::PointerMotion |
+            libinput::EventType::PointerButton |
+            libinput::EventType::PointerAxis => {},
+            _ => self.handle_gesture(event),
+        }
+    }
+
+    /* Set the direction transform for a device, None falls back to the default */
+    pub fn set_device_transform(&mut self, device: &Device, transform: Option<DirectionTransform>) {
+        self.set_device_transform_for_path(&device.path, transform);
+    }
+
+    fn set_device_transform_for_path(&mut self, device_path: &str, transform: Option<DirectionTransform>) {
+        self.device_transforms.retain(|(path, _)| path != device_path);
+        if let Some(transform) = transform {
+            self.device_transforms.push((device_path.to_string(), transform));
+        }
+
+        self.update_active_transform();
+    }
+
+    /* When enabled, devices without an explicit transform use the rotation configured in libinput */
+    pub fn set_use_device_rotation(&mut self, enabled: bool) {
+        self.use_device_rotation = enabled;
+        self.update_active_transform();
+    }
+
+    fn update_active_transform(&mut self) {
+        self.active_transform = DirectionTransform::identity();
+        if self.active_device.is_null() { return; }
+
+        let active_path = unsafe { &(*(self.active_device)).path };
+        let transform = self.device_transforms.iter().find(|(path, _)| path == active_path);
+
+        if let Some((_, transform)) = transform {
+            self.active_transform = *transform;
+        } else if self.use_device_rotation && !self.input.active_device.is_null() {
+            let angle = unsafe { libinput_device_config_rotation_get_angle(self.input.active_device) };
+            self.active_transform.rotation = Rotation::from_degrees(angle);
+        }
+    }
+
+    /* Replace all bindings and settings with the ones from config */
+    pub fn apply_config(&mut self, config: &Config) {
+        self.set_swipe_cancel_return_ratio(config.swipe_cancel_return_ratio);
+        self.set_sequence_policy(config.sequence_policy);
+
+        self.device_transforms.clear();
+        for (path, transform) in &config.device_transforms {
+            self.set_device_transform_for_path(path, Some(*transform));
+        }
+        self.set_use_device_rotation(config.use_device_rotation);
+        self.set_stroke_button(config.stroke_button);
+        self.set_touchscreen_stroke_fingers(config.touchscreen_stroke_fingers);
+        self.set_regions(config.regions.clone());
+        self.grab_touchpad = config.grab_touchpad;
+        self.set_use_raw_touchpad(config.raw_touchpad);
+
+        self.set_virtual_device(config.virtual_device.clone());
+        let keymap_source = match &config.keymap_file {
+            Some(path) => KeymapSource::File(path.clone()),
+            None => KeymapSource::Layout(config.keyboard_layout.clone(), config.keyboard_variant.clone()),
+        };
+        self.set_keymap_source(keymap_source, config.unicode_input);
+        /* Report a broken layout right away if anything will type with it */
+        if config.profiles.iter().flat_map(|p| p.actions()).any(|action| action.types_text()) {
+            self.load_keymap();
+        }
+
+        self.template_recognizer = TemplateRecognizer::new();
+        self.template_recognizer.threshold = config.template_threshold;
+        for template in &config.templates {
+            self.add_template(template.clone());
+        }
+
+        /* Stay in the same profile across reloads, if it still exists */
+        let active_profile = self.active_profile_name().to_string();
+        self.profiles = config.profiles.clone();
+        self.active_profile = 0;
+        if !self.set_active_profile(&active_profile) {
+            self.load_sequences();
+        }
+    }
+
+    pub fn active_profile_name(&self) -> &str {
+        &self.profiles[self.active_profile].name
+    }
+
+    /* Switch the bindings in use, returns false if there is no profile with that name */
+    pub fn set_active_profile(&mut self, name: &str) -> bool {
+        match self.profiles.iter().position(|p| p.name == name) {
+            Some(index) => {
+                let changed = index != self.active_profile;
+                self.active_profile = index;
+                self.load_sequences();
+                if changed { self.notify(BackendEvent::ProfileChanged(name.to_string())); }
+                true
+            },
+            None => false,
+        }
+    }
+
+    /* Looks up an action in the active profile, falling back to the default one */
+    fn find_action<F: Fn(&Profile) -> Option<&Action>>(&self, find: F) -> Option<Action> {
+        find(&self.profiles[self.active_profile]).or_else(|| find(&self.profiles[0])).cloned()
+    }
+
+    /* Bindings for the focused application take precedence over global ones of the same profile */
+    fn find_gesture_action(&mut self, gesture: Gesture) -> Option<Action> {
+        let has_app_bindings = !self.profiles[self.active_profile].app_bindings.is_empty() || !self.profiles[0].app_bindings.is_empty();
+        let window = match self.window_context.as_mut() {
+            Some(context) if has_app_bindings => context.focused_window(),
+            _ => None,
+        };
+
+        self.find_action(|p| p.window_gesture_action(window.as_ref(), gesture))
+    }
+
+    /* Where the focused window is read from for [app] bindings, e.g. an X11WindowContext */
+    pub fn set_window_context(&mut self, context: Option<Box<dyn WindowContext>>) {
+        self.window_context = context;
+    }
+
+    /* Returns false if the action failed */
+    fn run_action(&mut self, action: &Action) -> bool {
+        if action.types_text() { self.load_keymap(); }
+
+        let result = match action {
+            Action::Profile(name) => {
+                if self.set_active_profile(name) { Ok(()) } else { Err(format!("Unknown profile \"{}\"", name)) }
+            },
+            Action::Macro(steps) => {
+                /* Steps up to the first sleep run right away */
+                let mut running_macro = RunningMacro::new(steps.clone());
+                let finished = match self.virtual_input.as_mut() {
+                    Some(virtual_input) => running_macro.poll(Instant::now(), virtual_input),
+                    None => Err(String::from("No virtual input device available")),
+                };
+                finished.map(|finished| if !finished { self.running_macros.push(running_macro); })
+            },
+            Action::DBus(call) => self.session_bus().and_then(|connection| call.call(connection)),
+            _ => action.execute(self.virtual_input.as_mut()),
+        };
+
+        if let Err(e) = result.as_ref() {
+            println!("{}", e);
+        }
+
+        let succeeded = result.is_ok();
+        self.notify(BackendEvent::ActionResult(action.kind(), result));
+        succeeded
+    }
+
+    fn session_bus(&mut self) -> Result<&DBusConnection, String> {
+        if self.session_bus.is_none() {
+            self.session_bus = Some(DBusConnection::session()?);
+        }
+
+        Ok(self.session_bus.as_ref().unwrap())
+    }
+
+    /* Stops handling gestures, leaving them and the touchpad to the desktop, until resumed */
+    pub fn set_paused(&mut self, paused: bool) {
+        if paused == self.paused { return; }
+
+        self.paused = paused;
+        if paused {
+            self.cancel_macros();
+            self.ongoing_gesture_type = GestureType::Unknown;
+            self.active_proportional_action = None;
+        }
+        /* Releases the touchpad and the stroke mice while paused, and grabs them again after */
+        self.update_raw_touchpad();
+        self.update_stroke_mice();
+
+        self.notify(BackendEvent::PausedChanged(paused));
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    /* Applies the configuration file again */
+    pub fn reload_config(&mut self) -> Result<(), String> {
+        let config_path = Config::default_path().ok_or_else(|| String::from("No configuration directory"))?;
+        let config = Config::load(&config_path)?;
+        self.apply_config(&config);
+
+        Ok(())
+    }
+
+    /* Exports org.kinesix.Daemon on connection; like with start_polling, the backend must not move afterwards */
+    pub fn export_dbus_service(&mut self, connection: DBusConnection) -> Result<(), String> {
+        self.dbus_service = None;
+        self.dbus_service = Some(DBusService::export(connection, self as *mut KinesixBackend as *mut dyn DaemonControl)?);
+
+        Ok(())
+    }
+
+    /* Serves kinesixctl on a Unix socket, see ipc::default_socket_path */
+    pub fn listen_ipc(&mut self, path: &std::path::Path) -> Result<(), String> {
+        self.ipc_server = None;
+        self.ipc_server = Some(IpcServer::bind(path)?);
+
+        Ok(())
+    }
+
+    fn poll_ipc(&mut self) {
+        if let Some(mut server) = self.ipc_server.take() {
+            self.ipc_held_events = Some(Vec::new());
+            server.poll(self);
+
+            /* Requests can cause events, e.g. switching profiles, which watchers should see as well */
+            for event in self.ipc_held_events.take().unwrap_or_default() {
+                server.broadcast(&event);
+            }
+            self.ipc_server = Some(server);
+        }
+    }
+
+    /* Called with every BackendEvent, e.g. gestures as they are performed */
+    pub fn add_event_listener<Listener: 'static + FnMut(&BackendEvent)>(&mut self, listener: Listener) {
+        self.event_listeners.push(Box::new(listener));
+    }
+
+    fn notify(&mut self, event: BackendEvent) {
+        if let Some(service) = self.dbus_service.as_ref() {
+            service.emit(&event);
+        }
+        if let Some(server) = self.ipc_server.as_mut() {
+            server.broadcast(&event);
+        } else if let Some(held_events) = self.ipc_held_events.as_mut() {
+            held_events.push(event.clone());
+        }
+        for listener in self.event_listeners.iter_mut() {
+            listener(&event);
+        }
+    }
+
+    fn poll_macros(&mut self) {
+        if self.running_macros.is_empty() { return; }
+
+        let virtual_input = match self.virtual_input.as_mut() {
+            Some(virtual_input) => virtual_input,
+            None => { self.running_macros.clear(); return; },
+        };
+
+        let now = Instant::now();
+        let running_macros = std::mem::take(&mut self.running_macros);
+        self.running_macros = running_macros.into_iter()
+            .filter_map(|mut running_macro| {
+                match running_macro.poll(now, virtual_input) {
+                    Ok(false) => Some(running_macro),
+                    Ok(true) => None,
+                    Err(e) => { println!("{}", e); None },
+                }
+            })
+            .collect();
+    }
+
+    /* Stops every macro still playing, releasing the keys and buttons they hold */
+    pub fn cancel_macros(&mut self) {
+        for mut running_macro in self.running_macros.drain(..) {
+            if let Some(virtual_input) = self.virtual_input.as_mut() {
+                running_macro.cancel(virtual_input);
+            }
+        }
+    }
+
+    /* Run action whenever gesture is recognized, in addition to calling the delegates */
+    pub fn add_binding(&mut self, gesture: Gesture, action: Action) {
+        self.profiles[0].bindings.push((gesture, action));
+    }
+
+    /* Returns false if gesture wasn't bound in the default profile */
+    pub fn remove_binding(&mut self, gesture: Gesture) -> bool {
+        let bindings = &mut self.profiles[0].bindings;
+        let count = bindings.len();
+        bindings.retain(|(g, _)| *g != gesture);
+        bindings.len() != count
+    }
+
+    pub fn profile_names(&self) -> Vec<String> {
+        self.profiles.iter().map(|p| p.name.clone()).collect()
+    }
+
+    /* Run action when a tablet pad control is used, in the given mode or in all modes */
+    pub fn add_pad_binding(&mut self, trigger: PadTrigger, mode: Option<u32>, action: Action) {
+        self.profiles[0].pad_bindings.push(PadBinding { trigger, group: None, mode, action });
+    }
+
+    pub fn add_switch_binding(&mut self, trigger: SwitchTrigger, action: Action) {
+        self.profiles[0].switch_bindings.push((trigger, action));
+    }
+
+    fn handle_tablet_pad(&mut self, event: *mut libc::c_void, event_type: libinput::EventType) {
+        let (triggers, group, mode) = unsafe {
+            let pad_event = libinput_event_get_tablet_pad_event(event);
+            let mode = libinput_event_tablet_pad_get_mode(pad_event);
+            let group = libinput_tablet_pad_mode_group_get_index(libinput_event_tablet_pad_get_mode_group(pad_event));
+
+            let triggers = match event_type {
+                libinput::EventType::TabletPadButton => {
+                    let button = libinput_event_tablet_pad_get_button_number(pad_event);
+                    let pressed = libinput_event_tablet_pad_get_button_state(pad_event) != 0;
+                    if pressed { vec![PadTrigger::Button(button)] } else { Vec::new() }
+                },
+                libinput::EventType::TabletPadRing => {
+                    let number = libinput_event_tablet_pad_get_ring_number(pad_event);
+                    self.pad_tracker.ring_update(number, libinput_event_tablet_pad_get_ring_position(pad_event))
+                },
+                libinput::EventType::TabletPadStrip => {
+                    let number = libinput_event_tablet_pad_get_strip_number(pad_event);
+                    self.pad_tracker.strip_update(number, libinput_event_tablet_pad_get_strip_position(pad_event))
+                },
+                _ => Vec::new(),
+            };
+
+            (triggers, group, mode)
+        };
+
+        for trigger in triggers {
+            if let Some(action) = self.find_action(|p| p.pad_action(trigger, group, mode)) {
+                self.run_action(&action);
+            }
+        }
+    }
+
+    fn handle_switch(&mut self, event: *mut libc::c_void) {
+        let trigger = unsafe {
+            let switch_event = libinput_event_get_switch_event(event);
+            SwitchTrigger::from_libinput(libinput_event_switch_get_switch(switch_event), libinput_event_switch_get_switch_state(switch_event))
+        };
+
+        if let Some(trigger) = trigger {
+            if let Some(action) = self.find_action(|p| p.switch_action(trigger)) {
+                self.run_action(&action);
+            }
+        }
+    }
+
+    /* Mice with the button are grabbed right away, the ones grabbed for another button released */
+    pub fn set_stroke_button(&mut self, button: Option<Button>) {
+        if button == self.stroke_button { return; }
+
+        self.stroke_button = button;
+        self.update_stroke_mice();
+    }
+
+    /* Grabs every mouse that has the stroke button, none while paused */
+    fn update_stroke_mice(&mut self) {
+        /* Release the current ones first, their uinput copies must not be taken for mice */
+        self.stroke_mice.clear();
+        self.stroke_in_progress = false;
+        if self.paused { return; }
+
+        let button = match self.stroke_button {
+            Some(button) => button,
+            None => return,
+        };
+        let devices = match fs::read_dir(DEVICES_PATH) {
+            Ok(devices) => devices,
+            Err(_) => return,
+        };
+
+        let mut own_devnodes: Vec<String> = self.virtual_input.as_ref().and_then(|vi| vi.devnode()).into_iter().collect();
+        for device in devices.flatten() {
+            let is_char_device = device.file_type().map(|t| t.is_char_device()).unwrap_or(false);
+            let path = device.path().to_string_lossy().into_owned();
+            if !is_char_device || own_devnodes.contains(&path) { continue; }
+
+            match StrokeMouse::open(&path, button) {
+                Ok(Some(mouse)) => {
+                    own_devnodes.extend(mouse.passthrough_devnode());
+                    self.stroke_mice.push(mouse);
+                },
+                Ok(None) => {},
+                Err(e) => println!("Failed to grab {}: {}", path, e),
+            }
+        }
+    }
+
+    pub fn add_stroke_binding(&mut self, stroke: Stroke, action: Action) {
+        self.profiles[0].stroke_bindings.push((stroke, action));
+    }
+
+    /* Recreates the virtual input device if its identity or capabilities change, keeping the keymap */
+    pub fn set_virtual_device(&mut self, description: VirtualInputBuilder) {
+        if description == self.virtual_device && self.virtual_input.is_some() { return; }
+
+        /* Macros hold keys on the old device */
+        self.cancel_macros();
+        self.virtual_input = None;
+        self.virtual_device = description.clone();
+
+        match description.build() {
+            Ok(virtual_input) => self.virtual_input = Some(virtual_input),
+            Err(e) => println!("{}", e),
+        }
+        self.apply_keymap();
+    }
+
+    /* Layout used by text actions, and how characters it lacks are typed */
+    pub fn set_keymap(&mut self, keymap: Keymap, unicode_fallback: UnicodeFallback) {
+        self.keymap = Some(keymap);
+        self.unicode_fallback = unicode_fallback;
+        self.keymap_source = None;
+        self.apply_keymap();
+    }
+
+    /* Like set_keymap, but the keymap is only compiled when a text action first needs it */
+    pub fn set_keymap_source(&mut self, source: KeymapSource, unicode_fallback: UnicodeFallback) {
+        if self.keymap_source.as_ref() != Some(&source) {
+            self.keymap = None;
+            self.keymap_source = Some(source);
+        }
+        self.unicode_fallback = unicode_fallback;
+        self.apply_keymap();
+    }
+
+    fn load_keymap(&mut self) {
+        if self.keymap.is_some() { return; }
+
+        if let Some(source) = self.keymap_source.as_ref() {
+            match source.load() {
+                Ok(keymap) => self.keymap = Some(keymap),
+                Err(e) => println!("{}", e),
+            }
+            self.apply_keymap();
+        }
+    }
+
+    fn apply_keymap(&mut self) {
+        if let (Some(virtual_input), Some(keymap)) = (self.virtual_input.as_mut(), self.keymap.as_ref()) {
+            virtual_input.set_keymap(keymap.clone(), self.unicode_fallback);
+        }
+    }
+
+    /* Read the active touchpad through evdev as well, needed for taps */
+    pub fn set_use_raw_touchpad(&mut self, enabled: bool) {
+        self.use_raw_touchpad = enabled;
+        self.update_raw_touchpad();
+    }
+
+    /* Handle bound gestures of three or more fingers from the raw touchpad only, keeping them from
+     * the desktop; everything else is passed on through a virtual copy of the touchpad. Two-finger
+     * swipes are only recognized while grabbed, so they don't scroll as well, see touchpad_claims */
+    pub fn set_grab_touchpad(&mut self, enabled: bool) {
+        self.grab_touchpad = enabled;
+        self.update_raw_touchpad();
+    }
+
+    fn update_raw_touchpad(&mut self) {
+        let active_path = unsafe {
+            if self.active_device.is_null() || (*(self.active_device)).kind != DeviceKind::Touchpad { None } else { Some((*(self.active_device)).path.clone()) }
+        };
+
+        let path = match active_path {
+            Some(path) if !self.paused && (self.use_raw_touchpad || self.grab_touchpad) => path,
+            _ => {
+                self.raw_touchpad = None;
+                return;
+            }
+        };
+
+        if !self.raw_touchpad.as_ref().map(|t| t.path() == path).unwrap_or(false) {
+            self.raw_touchpad = match RawTouchpad::open(&path, self.regions.clone()) {
+                Ok(touchpad) => Some(touchpad),
+                Err(e) => {
+                    println!("{}", e);
+                    None
+                }
+            };
+        }
+
+        if let Some(touchpad) = self.raw_touchpad.as_mut() {
+            if let Err(e) = touchpad.set_grab(self.grab_touchpad) {
+                println!("Failed to grab touchpad: {}", e);
+            }
+        }
+    }
+
+    /* Finger counts the grabbed touchpad keeps to itself, those of bound gestures and taps. The touch
+     * is claimed before its direction is known, so unbound directions of these counts are lost too */
+    fn claimed_finger_counts(&self) -> Vec<u32> {
+        if !self.grab_touchpad { return Vec::new(); }
+
+        let profiles = [&self.profiles[self.active_profile], &self.profiles[0]];
+        let mut finger_counts: Vec<u32> = profiles.iter()
+            .flat_map(|p| p.gesture_finger_counts())
+            .map(|fc| fc as u32)
+            .chain(profiles.iter().flat_map(|p| p.tap_bindings.iter().map(|(tap, _)| tap.finger_count)))
+            .filter(|fc| *fc >= 3)
+            .collect();
+        finger_counts.sort();
+        finger_counts.dedup();
+        finger_counts
+    }
+
+    /* Touchpad areas whose taps and slides can be bound, read through the raw touchpad */
+    pub fn set_regions(&mut self, regions: Vec<Region>) {
+        if let Some(touchpad) = self.raw_touchpad.as_mut() {
+            touchpad.set_regions(regions.clone());
+        }
+        self.regions = regions;
+    }
+
+    pub fn add_region_binding(&mut self, trigger: RegionTrigger, action: Action) {
+        self.profiles[0].region_bindings.push((trigger, action));
+    }
+
+    pub fn add_tap_binding(&mut self, tap: Tap, action: Action) {
+        self.profiles[0].tap_bindings.push((tap, action));
+    }
+
+    /* What the grabbed touchpad keeps to itself: bound finger counts, touches starting in bound
+     * regions and, if two-finger swipes are bound, flicks picked out of the scrolling */
+    fn touchpad_claims(&self) -> TouchpadClaims {
+        if !self.grab_touchpad { return TouchpadClaims::default(); }
+
+        let profiles = [&self.profiles[self.active_profile], &self.profiles[0]];
+        let mut regions: Vec<String> = profiles.iter()
+            .flat_map(|p| p.region_bindings.iter().map(|(trigger, _)| trigger.region().to_string()))
+            .collect();
+        regions.sort();
+        regions.dedup();
+        let flicks_bound = profiles.iter().any(|p| p.gesture_finger_counts().contains(&2));
+
+        TouchpadClaims {
+            finger_counts: self.claimed_finger_counts(),
+            regions,
+            flick_transform: if flicks_bound { Some(self.active_transform) } else { None },
+        }
+    }
+
+    fn handle_raw_touchpad(&mut self) {
+        let claims = self.touchpad_claims();
+        let touchpad = match self.raw_touchpad.as_mut() {
+            Some(touchpad) => touchpad,
+            None => return,
+        };
+
+        let now = Instant::now();
+        let frames = match touchpad.read_frames(&claims) {
+            Ok(frames) => frames,
+            Err(e) => {
+                /* The device went away */
+                println!("{}", e);
+                self.raw_touchpad = None;
+                return;
+            }
+        };
+
+        let mut taps = Vec::new();
+        let mut region_triggers = Vec::new();
+        for frame in &frames {
+            taps.extend(touchpad.update_taps(frame));
+            region_triggers.extend(touchpad.update_regions(frame));
+        }
+        taps.extend(touchpad.poll_taps());
+        let swipes = touchpad.take_flicks();
+        let gesture_events = touchpad.take_gesture_events();
+
+        for event in gesture_events {
+            self.handle_raw_gesture(event);
+        }
+
+        for trigger in region_triggers {
+            if let Some(action) = self.find_action(|p| p.region_action(&trigger)) {
+                self.run_action(&action);
+            }
+        }
+
+        for tap in taps {
+            if let Some(action) = self.find_action(|p| p.tap_action(tap)) {
+                self.run_action(&action);
+            }
+        }
+
+        /* Two-finger swipes go through the same path as the ones libinput reports */
+        for direction in swipes {
+            let gesture = Gesture::with_modifiers(GestureType::Swipe(direction), 2, self.modifier_state.current());
+            let events = self.sequence_matcher.push(gesture, now);
+            self.dispatch_sequence_events(events);
+        }
+    }
+
+    /* Bind a shape, matched against the stroke templates by name */
+    pub fn add_shape_binding(&mut self, name: &str, action: Action) {
+        self.profiles[0].shape_bindings.push((name.to_string(), action));
+    }
+
+    pub fn add_template(&mut self, template: StrokeTemplate) {
+        self.template_recognizer.add_template(template);
+    }
+
+    /* The next stroke becomes a template called name, which is handed to on_recorded for storing */
+    pub fn record_template<OnRecorded: 'static + FnMut(StrokeTemplate)>(&mut self, name: &str, on_recorded: OnRecorded) {
+        self.template_recording = Some((name.to_string(), Box::new(on_recorded)));
+    }
+
+    fn handle_stroke_mice(&mut self) {
+        let mut index = 0;
+        while index < self.stroke_mice.len() {
+            let events = match self.stroke_mice[index].read_events() {
+                Ok(events) => events,
+                Err(e) => {
+                    /* The mouse went away */
+                    println!("{}", e);
+                    self.stroke_mice.remove(index);
+                    self.stroke_in_progress = false;
+                    continue;
+                }
+            };
+
+            for event in events {
+                match event {
+                    StrokeMouseEvent::Begin => {
+                        self.stroke_recorder.reset();
+                        self.stroke_in_progress = true;
+                    },
+                    StrokeMouseEvent::Motion(dx, dy) => if self.stroke_in_progress {
+                        self.stroke_recorder.update(dx, dy);
+                    },
+                    StrokeMouseEvent::End => if let Err(e) = self.finish_stroke(index) {
+                        println!("{}", e);
+                    },
+                }
+            }
+            index += 1;
+        }
+    }
+
+    fn finish_stroke(&mut self, mouse: usize) -> Result<(), String> {
+        let in_progress = self.stroke_in_progress;
+        self.stroke_in_progress = false;
+
+        if !in_progress || !self.stroke_recorder.is_stroke() {
+            /* Just a click */
+            return self.stroke_mice[mouse].replay_click();
+        }
+
+        self.recognize_stroke()
+    }
+
+    /* Touchscreen strokes drawn with fewer fingers, or more, are left alone */
+    pub fn set_touchscreen_stroke_fingers(&mut self, finger_count: Option<u32>) {
+        self.touch_stroke = finger_count.map(TouchStrokeTracker::new);
+    }
+
+    fn handle_touch(&mut self, event: *mut libc::c_void, event_type: libinput::EventType) {
+        let tracker = match self.touch_stroke.as_mut() {
+            Some(tracker) => tracker,
+            None => return,
+        };
+        let (slot, position) = unsafe {
+            let touch_event = libinput_event_get_touch_event(event);
+            let slot = libinput_event_touch_get_seat_slot(touch_event);
+            match event_type {
+                libinput::EventType::TouchDown | libinput::EventType::TouchMotion => {
+                    let position = (libinput_event_touch_get_x(touch_event), libinput_event_touch_get_y(touch_event));
+                    (slot, (position.0 * TOUCHSCREEN_PIXELS_PER_MM, position.1 * TOUCHSCREEN_PIXELS_PER_MM))
+                },
+                _ => (slot, (0.0, 0.0)),
+            }
+        };
+
+        match event_type {
+            libinput::EventType::TouchDown => if tracker.touch_down(slot, position) {
+                self.stroke_recorder.reset();
+            },
+            libinput::EventType::TouchMotion => if let Some((dx, dy)) = tracker.touch_motion(slot, position) {
+                self.stroke_recorder.update(dx, dy);
+            },
+            libinput::EventType::TouchUp => if tracker.touch_up(slot) && self.stroke_recorder.is_stroke() {
+                if let Err(e) = self.recognize_stroke() {
+                    println!("{}", e);
+                }
+            },
+            _ => tracker.cancel(),
+        }
+    }
+
+    /* Records the finished stroke as a template, or runs what it is bound to */
+    fn recognize_stroke(&mut self) -> Result<(), String> {
+        if let Some((name, mut on_recorded)) = self.template_recording.take() {
+            let template = StrokeTemplate::new(&name, self.stroke_recorder.points())?;
+            self.add_template(template.clone());
+            on_recorded(template);
+            return Ok(());
+        }
+
+        /* Direction strokes take precedence over shapes */
+        let stroke = self.stroke_recorder.directions();
+        let mut action = self.find_action(|p| p.stroke_action(&stroke));
+        if action.is_none() {
+            if let Some(shape) = self.template_recognizer.recognize(self.stroke_recorder.points()) {
+                action = self.find_action(|p| p.shape_action(&shape.name));
+            }
+        }
+
+        if let Some(action) = action {
+            self.run_action(&action);
+        }
+
+        Ok(())
+    }
+
+    pub fn add_sequence(&mut self, sequence: GestureSequence, action: Action) {
+        self.profiles[0].sequences.push((sequence, action));
+        self.load_sequences();
+    }
+
+    /* Removes the sequences of the default profile */
+    pub fn clear_sequences(&mut self) {
+        self.profiles[0].sequences.clear();
+        self.load_sequences();
+    }
+
+    pub fn set_sequence_policy(&mut self, policy: AmbiguityPolicy) {
+        self.sequence_matcher.policy = policy;
+    }
+
+    /* The matcher knows the sequences of the active profile followed by the default profile's */
+    fn load_sequences(&mut self) {
+        self.sequence_matcher.clear();
+        self.sequence_actions.clear();
+
+        let mut profiles = vec![self.active_profile];
+        if self.active_profile != 0 { profiles.push(0); }
+
+        for index in profiles {
+            for (sequence, action) in &self.profiles[index].sequences {
+                self.sequence_matcher.add_sequence(sequence.clone());
+                self.sequence_actions.push(action.clone());
+            }
+        }
+    }
+
+    fn dispatch_sequence_events(&mut self, events: Vec<SequenceEvent>) {
+        for event in events {
+            let action = match event {
+                SequenceEvent::Single(gesture) => {
+                    match gesture.gesture_type {
+                        GestureType::Swipe(swipe_direction) => {
+                            (self.swipe_delegate)(swipe_direction, gesture.finger_count);
+                        },
+                        GestureType::Pinch(pinch_type) => {
+                            (self.pinch_delegate)(pinch_type, gesture.finger_count);
+                        },
+                        GestureType::Unknown => {},
+                    }
+
+                    self.find_gesture_action(gesture)
+                },
+                SequenceEvent::Sequence(index) => self.sequence_actions.get(index).cloned(),
+            };
+
+            if let Some(action) = action {
+                self.run_action(&action);
+            }
+        }
+    }
+
+    /* Treat swipes that return within ratio of their farthest distance as cancelled, None disables it */
+    pub fn set_swipe_cancel_return_ratio(&mut self, ratio: Option<f64>) {
+        self.swipe_recognizer.cancel_return_ratio = ratio;
+    }
+
+    /* Bind a proportional action to swipes or pinches (depending on its axis) with finger_count fingers */
+    pub fn add_proportional_action(&mut self, finger_count: i32, action: ProportionalAction) {
+        self.profiles[0].proportional_actions.push((finger_count, action));
+    }
+
+    /* Removes the proportional actions of the default profile */
+    pub fn clear_proportional_actions(&mut self) {
+        self.profiles[0].proportional_actions.clear();
+        self.active_proportional_action = None;
+    }
+
+    fn begin_proportional_action(&mut self, pinch: bool, finger_count: i32) {
+        self.proportional_tracker.reset();
+        self.active_proportional_action = self.profiles[self.active_profile].proportional_action(pinch, finger_count)
+            .or_else(|| self.profiles[0].proportional_action(pinch, finger_count))
+            .cloned();
+    }
+
+    fn update_proportional_action(&mut self, dx: f64, dy: f64, scale: f64) {
+        if let Some(action) = self.active_proportional_action.take() {
+            let steps = self.proportional_tracker.update(&action, dx, dy, scale);
+            let step_action = if steps > 0 { &action.increase } else { &action.decrease };
+
+            for _ in 0..steps.abs() {
+                if !self.run_action(step_action) { break; }
+            }
+
+            self.active_proportional_action = Some(action);
+        }
     }
 
     fn handle_swipe_gesture(&mut self, gesture_event: *mut libc::c_void, event_type: libinput::EventType) -> (GestureEventState, i32) {
@@ -427,45 +1611,26 @@ impl KinesixBackend
             libinput_event_gesture_get_finger_count(gesture_event)
         };
 
-        let mut x_max = self.input.swipe_x_max;
-        let mut y_max = self.input.swipe_y_max;
-
         match event_type {
             libinput::EventType::GestureSwipeBegin => {
                 gesture_state = GestureEventState::Started;
+                self.begin_swipe(finger_count);
             },
             libinput::EventType::GestureSwipeUpdate => {
                 gesture_state = GestureEventState::Ongoing;
 
-                let x_current = unsafe { libinput_event_gesture_get_dx_unaccelerated(gesture_event) };
-                let y_current = unsafe { libinput_event_gesture_get_dy_unaccelerated(gesture_event) };
-
-                if x_max.abs() < x_current.abs() { x_max = x_current; }
-                if y_max.abs() < y_current.abs() { y_max = y_current; }
-
-                if y_max.abs() > x_max.abs() {
-                    if y_max < -GESTURE_DELTA {
-                        self.ongoing_gesture_type = GestureType::Swipe(SwipeDirection::SwipeUp);
-                    } else if y_max > GESTURE_DELTA {
-                        self.ongoing_gesture_type = GestureType::Swipe(SwipeDirection::SwipeDown);
-                    }
-                } else if x_max.abs() > y_max.abs() {
-                    if x_max < -GESTURE_DELTA {
-                        self.ongoing_gesture_type = GestureType::Swipe(SwipeDirection::SwipeLeft);
-                    } else if x_max > GESTURE_DELTA {
-                        self.ongoing_gesture_type = GestureType::Swipe(SwipeDirection::SwipeRight);
-                    }
-                }
+                let (dx, dy) = unsafe {
+                    (libinput_event_gesture_get_dx_unaccelerated(gesture_event), libinput_event_gesture_get_dy_unaccelerated(gesture_event))
+                };
+                self.update_swipe(dx, dy);
             },
             libinput::EventType::GestureSwipeEnd => {
                 gesture_state = GestureEventState::Finished;
+                self.end_swipe();
             }
             _ => { gesture_state = GestureEventState::Unknown; }
         }
 
-        self.input.swipe_x_max = x_max;
-        self.input.swipe_y_max = y_max;
-
         (gesture_state, finger_count)
     }
 
@@ -479,6 +1644,7 @@ impl KinesixBackend
         match event_type {
             libinput::EventType::GesturePinchBegin => {
                 gesture_state = GestureEventState::Started;
+                self.begin_pinch(finger_count);
             },
             libinput::EventType::GesturePinchUpdate => {
                 gesture_state = GestureEventState::Ongoing;
@@ -486,9 +1652,7 @@ impl KinesixBackend
                 let scale = unsafe {
                     libinput_event_gesture_get_scale(gesture_event)
                 };
-
-                if scale > 1.0 { self.ongoing_gesture_type = GestureType::Pinch(PinchType::PinchOut); }
-                if scale < 1.0 { self.ongoing_gesture_type = GestureType::Pinch(PinchType::PinchIn); }
+                self.update_pinch(scale);
             },
             libinput::EventType::GesturePinchEnd => {
                 gesture_state = GestureEventState::Finished;
@@ -571,22 +1735,88 @@ impl KinesixBackend
         }
 
         if gesture_state == GestureEventState::Finished {
-            unsafe {
-                if libinput_event_gesture_get_cancelled(gesture_event) == 0 {
-                    match self.ongoing_gesture_type.borrow() {
-                        GestureType::Swipe(swipe_direction) => {
-                            (self.swipe_delegate)(*swipe_direction, finger_count);
-                        },
-                        GestureType::Pinch(pinch_type) => {
-                            (self.pinch_delegate)(*pinch_type, finger_count);
-                        },
-                        GestureType::Unknown => {},
-                    }
-                    self.ongoing_gesture_type = GestureType::Unknown;
-                    self.input.swipe_x_max = 0.0;
-                    self.input.swipe_y_max = 0.0;
-                }
-            }
+            let cancelled = unsafe { libinput_event_gesture_get_cancelled(gesture_event) != 0 };
+            self.finish_gesture(finger_count, cancelled);
+        }
+    }
+
+    /* The steps of a gesture, shared by libinput gestures and the ones read from a grabbed touchpad */
+    fn begin_swipe(&mut self, finger_count: i32) {
+        self.gesture_modifiers = self.modifier_state.current();
+        self.gesture_finger_count = finger_count;
+        self.notify(BackendEvent::GestureBegin(GestureType::Swipe(SwipeDirection::None), finger_count));
+        self.swipe_recognizer.reset();
+        self.begin_proportional_action(false, finger_count);
+    }
+
+    fn update_swipe(&mut self, dx: f64, dy: f64) {
+        let (x_current, y_current) = self.active_transform.apply(dx, dy);
+
+        self.update_proportional_action(x_current, y_current, 1.0);
+
+        let direction = self.swipe_recognizer.update(x_current, y_current);
+        if direction != SwipeDirection::None {
+            self.ongoing_gesture_type = GestureType::Swipe(direction);
+        }
+
+        let gesture_type = match self.ongoing_gesture_type {
+            GestureType::Unknown => GestureType::Swipe(SwipeDirection::None),
+            gesture_type => gesture_type,
+        };
+        self.notify(BackendEvent::GestureUpdate(gesture_type, self.gesture_finger_count));
+    }
+
+    fn end_swipe(&mut self) {
+        let direction = self.swipe_recognizer.finish();
+        if direction == SwipeDirection::None {
+            self.ongoing_gesture_type = GestureType::Unknown;
+        }
+    }
+
+    fn begin_pinch(&mut self, finger_count: i32) {
+        self.gesture_modifiers = self.modifier_state.current();
+        self.gesture_finger_count = finger_count;
+        self.notify(BackendEvent::GestureBegin(GestureType::Pinch(PinchType::None), finger_count));
+        self.begin_proportional_action(true, finger_count);
+    }
+
+    fn update_pinch(&mut self, scale: f64) {
+        self.update_proportional_action(0.0, 0.0, scale);
+
+        if scale > 1.0 { self.ongoing_gesture_type = GestureType::Pinch(PinchType::PinchOut); }
+        if scale < 1.0 { self.ongoing_gesture_type = GestureType::Pinch(PinchType::PinchIn); }
+
+        let gesture_type = match self.ongoing_gesture_type {
+            GestureType::Unknown => GestureType::Pinch(PinchType::None),
+            gesture_type => gesture_type,
+        };
+        self.notify(BackendEvent::GestureUpdate(gesture_type, self.gesture_finger_count));
+    }
+
+    fn finish_gesture(&mut self, finger_count: i32, cancelled: bool) {
+        self.notify(BackendEvent::GestureEnd(self.ongoing_gesture_type, finger_count, cancelled));
+
+        /* Gestures driving a proportional action have already been handled while ongoing */
+        let consumed = self.active_proportional_action.take().is_some();
+
+        if !consumed && !cancelled && self.ongoing_gesture_type != GestureType::Unknown {
+            let gesture = Gesture::with_modifiers(self.ongoing_gesture_type, finger_count, self.gesture_modifiers);
+            let events = self.sequence_matcher.push(gesture, Instant::now());
+            self.dispatch_sequence_events(events);
+        }
+        self.ongoing_gesture_type = GestureType::Unknown;
+    }
+
+    fn handle_raw_gesture(&mut self, event: RawGestureEvent) {
+        match event {
+            RawGestureEvent::SwipeBegin(finger_count) => self.begin_swipe(finger_count),
+            RawGestureEvent::SwipeUpdate(dx, dy) => self.update_swipe(dx, dy),
+            RawGestureEvent::PinchBegin(finger_count) => self.begin_pinch(finger_count),
+            RawGestureEvent::PinchUpdate(scale) => self.update_pinch(scale),
+            RawGestureEvent::End(finger_count) => {
+                if let GestureType::Swipe(_) = self.ongoing_gesture_type { self.end_swipe(); }
+                self.finish_gesture(finger_count, false);
+            },
         }
     }
 
@@ -599,22 +1829,53 @@ impl KinesixBackend
             if evt_poller.cancelation_requested { return 0; }
 
             if evt_poller.libinput_event_listener.try_recv().is_ok() {
-                loop {
-                    let ev = libinput_get_event(self_.input.instance);
-                    if ev != 0 as *mut libc::c_void {
-                        self_.handle_gesture(ev);
-                        libinput_event_destroy(ev);
-                    } else {
-                        break;
-                    }
-                }
+                self_.handle_queued_events();
             }
+
+            self_.handle_raw_touchpad();
+            self_.handle_stroke_mice();
+
+            /* Fire gestures held back for a sequence that timed out */
+            let events = self_.sequence_matcher.poll(Instant::now());
+            self_.dispatch_sequence_events(events);
+
+            self_.poll_macros();
+            self_.poll_ipc();
         }
 
         1
     }
 
+    fn handle_queued_events(&mut self) {
+        loop {
+            let ev = unsafe { libinput_get_event(self.input.instance) };
+            if ev == 0 as *mut libc::c_void { break; }
+
+            self.handle_event(ev);
+            unsafe { libinput_event_destroy(ev); }
+        }
+    }
+
+    /* Reads and handles whatever input is pending, for driving the backend without polling,
+     * e.g. from tests */
+    pub fn dispatch_pending_events(&mut self) {
+        unsafe { libinput_dispatch(self.input.instance); }
+        self.handle_queued_events();
+        self.handle_raw_touchpad();
+        self.handle_stroke_mice();
+
+        let events = self.sequence_matcher.poll(Instant::now());
+        self.dispatch_sequence_events(events);
+
+        self.poll_macros();
+        self.poll_ipc();
+    }
+
+    /* Also installs the stuck key guard, the polling depends on the GLib main loop it needs as well */
     pub fn start_polling(&mut self) {
+        install_stuck_key_guard();
+        self.open_auxiliary_devices();
+
         let (cancel_token_sender, cancel_token_receiver) = mpsc::channel();
         let (libinput_event_listener_sender, libinput_event_listener_receiver) = mpsc::channel();
         let input_fd = unsafe {
@@ -671,8 +1932,84 @@ impl KinesixBackend
     }
 }
 
+impl DaemonControl for KinesixBackend {
+    fn pause(&mut self) {
+        self.set_paused(true);
+    }
+
+    fn resume(&mut self) {
+        self.set_paused(false);
+    }
+
+    fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    fn reload(&mut self) -> Result<(), String> {
+        self.reload_config()
+    }
+
+    fn devices(&mut self) -> Vec<Device> {
+        self.get_valid_device_list()
+    }
+
+    fn active_device(&self) -> Option<String> {
+        self.active_device_path()
+    }
+
+    fn active_profile(&self) -> String {
+        self.active_profile_name().to_string()
+    }
+
+    fn profiles(&self) -> Vec<String> {
+        self.profile_names()
+    }
+
+    fn set_profile(&mut self, name: &str) -> Result<(), String> {
+        if self.set_active_profile(name) { Ok(()) } else { Err(format!("Unknown profile \"{}\"", name)) }
+    }
+
+    fn bind(&mut self, gesture: Gesture, action: Action) {
+        self.remove_binding(gesture);
+        self.add_binding(gesture, action);
+    }
+
+    fn unbind(&mut self, gesture: Gesture) -> bool {
+        self.remove_binding(gesture)
+    }
+}
+
 impl Drop for KinesixBackend {
     fn drop(&mut self) {
+        self.dbus_service = None;
+        self.cancel_macros();
         self.stop_polling();
     }
 }
+
+unsafe extern "C" fn on_termination_signal(data: *mut libc::c_void) -> i32 {
+    virtualinput::release_all_devices();
+    exit(128 + data as libc::c_int)
+}
+
+/*
+ * Makes sure no key stays pressed on the virtual devices when Kinesix goes down, by releasing them from a
+ * panic hook and on SIGINT, SIGTERM or SIGHUP. The signals are delivered through the GLib main loop,
+ * which has to be running. start_polling calls it, installing more than once does nothing.
+ */
+pub fn install_stuck_key_guard() {
+    static INSTALL: Once = Once::new();
+    INSTALL.call_once(|| {
+        let previous_hook = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |info| {
+            virtualinput::release_all_devices();
+            previous_hook(info);
+        }));
+
+        for signal in &[libc::SIGINT, libc::SIGTERM, libc::SIGHUP] {
+            unsafe {
+                g_unix_signal_add(*signal, on_termination_signal, *signal as usize as *mut libc::c_void);
+            }
+        }
+    });
+}
@@ -0,0 +1,343 @@
+/*
+ * Copyright © 2019 Romeo Calota
+ *
+ * This program is free software; you can redistribute it and/or
+ * modify it under the terms of the GNU Lesser General Public
+ * License as published by the Free Software Foundation; either
+ * version 2 of the licence, or (at your option) any later version.
+ *
+ * This software is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+ * Lesser General Public License for more details.
+ *
+ * You should have received a copy of the GNU Lesser General Public
+ * License along with this program; if not, see <http://www.gnu.org/licenses/>.
+ *
+ * Author: Romeo Calota
+ */
+
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+use virtualinput::{Button, Key, VirtualInput, VirtualInputBuilder};
+
+use crate::action::Action;
+use crate::sequence::parse_duration;
+
+/* One step of a macro action, written as in the config file: "keys LeftControl+C", "key-down LeftMeta",
+ * "key-up LeftMeta", "text hello", "sleep 100ms", "click left", "button-down left", "button-up left",
+ * "move 10 -5", "scroll -1 0" or "command notify-send hi" */
+#[derive(Debug, PartialEq, Clone)]
+pub enum MacroStep
+{
+    /* Press and release a chord */
+    Keys(Vec<Key>),
+    KeyDown(Vec<Key>),
+    KeyUp(Vec<Key>),
+    Text(String),
+    Sleep(Duration),
+    Click(Button),
+    ButtonDown(Button),
+    ButtonUp(Button),
+    Move(i32, i32),
+    /* Wheel clicks, positive values scroll up and right */
+    Scroll(i32, i32),
+    Command(String),
+}
+
+fn parse_keys(keys: &str) -> Result<Vec<Key>, String> {
+    keys.split('+').map(|k| k.parse::<Key>()).collect()
+}
+
+fn parse_pair(value: &str) -> Result<(i32, i32), String> {
+    let values = value.split_whitespace()
+        .map(|v| v.parse::<i32>().map_err(|_| format!("Invalid number \"{}\"", v)))
+        .collect::<Result<Vec<i32>, String>>()?;
+
+    match values.as_slice() {
+        [first, second] => Ok((*first, *second)),
+        _ => Err(format!("Expected two numbers, found \"{}\"", value)),
+    }
+}
+
+impl FromStr for MacroStep {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<MacroStep, String> {
+        let mut parts = s.trim().splitn(2, char::is_whitespace);
+        let kind = parts.next().unwrap_or("");
+        let argument = parts.next().map(|a| a.trim()).unwrap_or("");
+
+        if argument.is_empty() {
+            return Err(format!("Missing argument for macro step \"{}\"", s.trim()));
+        }
+
+        match kind {
+            "keys" => Ok(MacroStep::Keys(parse_keys(argument)?)),
+            "key-down" => Ok(MacroStep::KeyDown(parse_keys(argument)?)),
+            "key-up" => Ok(MacroStep::KeyUp(parse_keys(argument)?)),
+            "text" => Ok(MacroStep::Text(argument.to_string())),
+            "sleep" => Ok(MacroStep::Sleep(parse_duration(argument)?)),
+            "click" => Ok(MacroStep::Click(argument.parse()?)),
+            "button-down" => Ok(MacroStep::ButtonDown(argument.parse()?)),
+            "button-up" => Ok(MacroStep::ButtonUp(argument.parse()?)),
+            "move" => parse_pair(argument).map(|(dx, dy)| MacroStep::Move(dx, dy)),
+            "scroll" => parse_pair(argument).map(|(vertical, horizontal)| MacroStep::Scroll(vertical, horizontal)),
+            "command" => Ok(MacroStep::Command(argument.to_string())),
+            _ => Err(format!("Unknown macro step \"{}\"", kind)),
+        }
+    }
+}
+
+impl MacroStep {
+    /* Fails if the virtual device described lacks what the step needs */
+    pub fn check_virtual_device(&self, device: &VirtualInputBuilder) -> Result<(), String> {
+        match self {
+            MacroStep::Keys(keys) | MacroStep::KeyDown(keys) | MacroStep::KeyUp(keys) => device.check_keys(keys),
+            MacroStep::Text(_) => device.check_typing(),
+            MacroStep::Click(button) | MacroStep::ButtonDown(button) | MacroStep::ButtonUp(button) => device.check_button(*button),
+            MacroStep::Move(_, _) => device.check_pointer_motion(),
+            MacroStep::Scroll(_, _) => device.check_wheel(),
+            MacroStep::Sleep(_) | MacroStep::Command(_) => Ok(()),
+        }
+    }
+}
+
+/* What macro steps act on, the virtual input device outside of tests */
+pub trait MacroTarget
+{
+    fn hold(&mut self, keys: &[Key]) -> Result<(), String>;
+    fn release(&mut self, keys: &[Key]) -> Result<(), String>;
+    fn type_text(&mut self, text: &str) -> Result<(), String>;
+    fn button(&mut self, button: Button, pressed: bool) -> Result<(), String>;
+    fn move_by(&mut self, dx: i32, dy: i32) -> Result<(), String>;
+    fn scroll(&mut self, vertical: i32, horizontal: i32) -> Result<(), String>;
+}
+
+impl MacroTarget for VirtualInput {
+    fn hold(&mut self, keys: &[Key]) -> Result<(), String> {
+        VirtualInput::hold(self, keys)
+    }
+
+    fn release(&mut self, keys: &[Key]) -> Result<(), String> {
+        VirtualInput::release(self, keys)
+    }
+
+    fn type_text(&mut self, text: &str) -> Result<(), String> {
+        VirtualInput::type_text(self, text)
+    }
+
+    fn button(&mut self, button: Button, pressed: bool) -> Result<(), String> {
+        VirtualInput::button(self, button, pressed)
+    }
+
+    fn move_by(&mut self, dx: i32, dy: i32) -> Result<(), String> {
+        VirtualInput::move_by(self, dx, dy)
+    }
+
+    fn scroll(&mut self, vertical: i32, horizontal: i32) -> Result<(), String> {
+        VirtualInput::scroll(self, vertical, horizontal)
+    }
+}
+
+/*
+ * A macro being played. It is advanced from the event loop instead of a thread of its own, so sleeping
+ * never holds up gesture handling. Whatever it left pressed is released once it finishes, fails or is
+ * cancelled.
+ */
+#[derive(Debug)]
+pub struct RunningMacro
+{
+    steps: Vec<MacroStep>,
+    next_step: usize,
+    resume_at: Option<Instant>,
+
+    held_keys: Vec<Key>,
+    held_buttons: Vec<Button>,
+}
+
+impl RunningMacro {
+    pub fn new(steps: Vec<MacroStep>) -> RunningMacro {
+        RunningMacro { steps, next_step: 0, resume_at: None, held_keys: Vec::new(), held_buttons: Vec::new() }
+    }
+
+    /* Runs the steps that are due, returns true once the macro is done */
+    pub fn poll(&mut self, now: Instant, target: &mut dyn MacroTarget) -> Result<bool, String> {
+        if let Some(resume_at) = self.resume_at {
+            if now < resume_at { return Ok(false); }
+            self.resume_at = None;
+        }
+
+        while self.next_step < self.steps.len() {
+            let step = self.steps[self.next_step].clone();
+            self.next_step += 1;
+
+            if let MacroStep::Sleep(duration) = step {
+                self.resume_at = Some(now + duration);
+                return Ok(false);
+            }
+
+            if let Err(e) = self.run_step(&step, target) {
+                self.release_held(target);
+                return Err(e);
+            }
+        }
+
+        self.release_held(target);
+        Ok(true)
+    }
+
+    /* Stops before the next step */
+    pub fn cancel(&mut self, target: &mut dyn MacroTarget) {
+        self.next_step = self.steps.len();
+        self.resume_at = None;
+        self.release_held(target);
+    }
+
+    fn run_step(&mut self, step: &MacroStep, target: &mut dyn MacroTarget) -> Result<(), String> {
+        /* Keys and buttons count as held before pressing them, in case pressing fails halfway */
+        match step {
+            MacroStep::Keys(keys) => {
+                self.held_keys.extend_from_slice(keys);
+                target.hold(keys)?;
+                target.release(keys)?;
+                self.held_keys.retain(|k| !keys.contains(k));
+            },
+            MacroStep::KeyDown(keys) => {
+                self.held_keys.extend_from_slice(keys);
+                target.hold(keys)?;
+            },
+            MacroStep::KeyUp(keys) => {
+                target.release(keys)?;
+                self.held_keys.retain(|k| !keys.contains(k));
+            },
+            MacroStep::Text(text) => target.type_text(text)?,
+            MacroStep::Click(button) => {
+                self.held_buttons.push(*button);
+                target.button(*button, true)?;
+                target.button(*button, false)?;
+                self.held_buttons.retain(|b| b != button);
+            },
+            MacroStep::ButtonDown(button) => {
+                self.held_buttons.push(*button);
+                target.button(*button, true)?;
+            },
+            MacroStep::ButtonUp(button) => {
+                target.button(*button, false)?;
+                self.held_buttons.retain(|b| b != button);
+            },
+            MacroStep::Move(dx, dy) => target.move_by(*dx, *dy)?,
+            MacroStep::Scroll(vertical, horizontal) => target.scroll(*vertical, *horizontal)?,
+            MacroStep::Command(command) => Action::Command(command.clone()).execute(None)?,
+            MacroStep::Sleep(_) => {},
+        }
+
+        Ok(())
+    }
+
+    fn release_held(&mut self, target: &mut dyn MacroTarget) {
+        /* Best effort, a failing device should not keep the rest pressed */
+        for key in self.held_keys.drain(..).rev() {
+            let _ = target.release(&[key]);
+        }
+        for button in self.held_buttons.drain(..).rev() {
+            let _ = target.button(button, false);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /* Logs what it is asked to do, failing to type text if told to */
+    struct RecordingTarget
+    {
+        log: Vec<String>,
+        fail_text: bool,
+    }
+
+    impl MacroTarget for RecordingTarget {
+        fn hold(&mut self, keys: &[Key]) -> Result<(), String> {
+            self.log.push(format!("hold {:?}", keys));
+            Ok(())
+        }
+
+        fn release(&mut self, keys: &[Key]) -> Result<(), String> {
+            self.log.push(format!("release {:?}", keys));
+            Ok(())
+        }
+
+        fn type_text(&mut self, text: &str) -> Result<(), String> {
+            if self.fail_text { return Err(String::from("no layout")); }
+            self.log.push(format!("type {}", text));
+            Ok(())
+        }
+
+        fn button(&mut self, button: Button, pressed: bool) -> Result<(), String> {
+            self.log.push(format!("button {:?} {}", button, pressed));
+            Ok(())
+        }
+
+        fn move_by(&mut self, dx: i32, dy: i32) -> Result<(), String> {
+            self.log.push(format!("move {} {}", dx, dy));
+            Ok(())
+        }
+
+        fn scroll(&mut self, vertical: i32, horizontal: i32) -> Result<(), String> {
+            self.log.push(format!("scroll {} {}", vertical, horizontal));
+            Ok(())
+        }
+    }
+
+    fn steps(steps: &[&str]) -> Vec<MacroStep> {
+        steps.iter().map(|s| s.parse().unwrap()).collect()
+    }
+
+    #[test]
+    fn waits_between_steps_without_blocking() {
+        let mut target = RecordingTarget { log: Vec::new(), fail_text: false };
+        let mut running = RunningMacro::new(steps(&["key-down LeftMeta", "sleep 100ms", "text terminal", "keys Enter", "click middle"]));
+        let start = Instant::now();
+
+        assert_eq!(running.poll(start, &mut target), Ok(false));
+        assert_eq!(target.log, vec!["hold [LeftMeta]"]);
+        assert_eq!(running.poll(start + Duration::from_millis(50), &mut target), Ok(false));
+        assert_eq!(target.log.len(), 1);
+
+        assert_eq!(running.poll(start + Duration::from_millis(100), &mut target), Ok(true));
+        assert_eq!(target.log, vec![
+            "hold [LeftMeta]", "type terminal", "hold [Enter]", "release [Enter]",
+            "button Middle true", "button Middle false",
+            /* Never released by the macro itself */
+            "release [LeftMeta]",
+        ]);
+    }
+
+    #[test]
+    fn releases_held_keys_on_failure_and_cancel() {
+        let mut target = RecordingTarget { log: Vec::new(), fail_text: true };
+        let mut running = RunningMacro::new(steps(&["key-down LeftControl+LeftShift", "button-down left", "text x", "key-up LeftShift"]));
+
+        assert!(running.poll(Instant::now(), &mut target).is_err());
+        assert_eq!(target.log, vec!["hold [LeftControl, LeftShift]", "button Left true", "release [LeftShift]", "release [LeftControl]", "button Left false"]);
+
+        let mut target = RecordingTarget { log: Vec::new(), fail_text: false };
+        let mut running = RunningMacro::new(steps(&["key-down LeftAlt", "sleep 1s", "keys Tab"]));
+        assert_eq!(running.poll(Instant::now(), &mut target), Ok(false));
+        running.cancel(&mut target);
+        assert_eq!(target.log, vec!["hold [LeftAlt]", "release [LeftAlt]"]);
+    }
+
+    #[test]
+    fn parses_steps() {
+        assert_eq!("scroll -1 0".parse::<MacroStep>(), Ok(MacroStep::Scroll(-1, 0)));
+        assert_eq!("sleep 250".parse::<MacroStep>(), Ok(MacroStep::Sleep(Duration::from_millis(250))));
+        assert_eq!("text  hello world".parse::<MacroStep>(), Ok(MacroStep::Text(String::from("hello world"))));
+        assert_eq!("button-down LEFT".parse::<MacroStep>(), Ok(MacroStep::ButtonDown(Button::Left)));
+        assert!("move 10".parse::<MacroStep>().is_err());
+        assert!("click thumb".parse::<MacroStep>().is_err());
+        assert!("wiggle 3".parse::<MacroStep>().is_err());
+    }
+}
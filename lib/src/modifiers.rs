@@ -0,0 +1,159 @@
+/*
+ * Copyright © 2019 Romeo Calota
+ *
+ * This program is free software; you can redistribute it and/or
+ * modify it under the terms of the GNU Lesser General Public
+ * License as published by the Free Software Foundation; either
+ * version 2 of the licence, or (at your option) any later version.
+ *
+ * This software is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+ * Lesser General Public License for more details.
+ *
+ * You should have received a copy of the GNU Lesser General Public
+ * License along with this program; if not, see <http://www.gnu.org/licenses/>.
+ *
+ * Author: Romeo Calota
+ */
+
+use std::fmt;
+
+use virtualinput::Key;
+
+/* Set of keyboard modifiers, left and right variants are not distinguished */
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub struct Modifiers(u8);
+
+impl Modifiers {
+    pub const NONE: Modifiers = Modifiers(0);
+    pub const CONTROL: Modifiers = Modifiers(1 << 0);
+    pub const ALT: Modifiers = Modifiers(1 << 1);
+    pub const SHIFT: Modifiers = Modifiers(1 << 2);
+    pub const SUPER: Modifiers = Modifiers(1 << 3);
+
+    const NAMES: [(Modifiers, &'static str); 4] = [
+        (Modifiers::CONTROL, "ctrl"),
+        (Modifiers::ALT, "alt"),
+        (Modifiers::SHIFT, "shift"),
+        (Modifiers::SUPER, "super"),
+    ];
+
+    pub fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    pub fn contains(&self, other: Modifiers) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    pub fn union(&self, other: Modifiers) -> Modifiers {
+        Modifiers(self.0 | other.0)
+    }
+
+    pub fn from_name(name: &str) -> Option<Modifiers> {
+        match name.to_lowercase().as_str() {
+            "ctrl" | "control" => Some(Modifiers::CONTROL),
+            "alt" => Some(Modifiers::ALT),
+            "shift" => Some(Modifiers::SHIFT),
+            "super" | "meta" | "logo" => Some(Modifiers::SUPER),
+            _ => None,
+        }
+    }
+
+    /* The modifier a keycode belongs to, if any */
+    pub fn from_keycode(keycode: u32) -> Option<Modifiers> {
+        let modifier_keys = [
+            (Key::LeftControl, Modifiers::CONTROL), (Key::RightControl, Modifiers::CONTROL),
+            (Key::LeftAlt, Modifiers::ALT), (Key::RightAlt, Modifiers::ALT),
+            (Key::LeftShift, Modifiers::SHIFT), (Key::RightShift, Modifiers::SHIFT),
+            (Key::LeftMeta, Modifiers::SUPER), (Key::RightMeta, Modifiers::SUPER),
+        ];
+
+        modifier_keys.iter().find(|(key, _)| *key as u32 == keycode).map(|(_, modifier)| *modifier)
+    }
+}
+
+/* Written as "super+ctrl", in the same order as they are parsed back */
+impl fmt::Display for Modifiers {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let names: Vec<&str> = Modifiers::NAMES.iter()
+            .filter(|(modifier, _)| self.contains(*modifier))
+            .map(|(_, name)| *name)
+            .collect();
+
+        write!(f, "{}", names.join("+"))
+    }
+}
+
+/* Tracks which modifier keys are held down across all keyboards */
+#[derive(Debug)]
+pub struct ModifierState
+{
+    pressed_keys: Vec<u32>,
+}
+
+impl ModifierState {
+    pub fn new() -> ModifierState {
+        ModifierState { pressed_keys: Vec::new() }
+    }
+
+    pub fn update(&mut self, keycode: u32, pressed: bool) {
+        if Modifiers::from_keycode(keycode).is_none() { return; }
+
+        if pressed {
+            self.pressed_keys.push(keycode);
+        } else if let Some(index) = self.pressed_keys.iter().position(|k| *k == keycode) {
+            self.pressed_keys.remove(index);
+        }
+    }
+
+    pub fn current(&self) -> Modifiers {
+        self.pressed_keys.iter()
+            .filter_map(|k| Modifiers::from_keycode(*k))
+            .fold(Modifiers::NONE, |acc, m| acc.union(m))
+    }
+}
+
+impl Default for ModifierState {
+    fn default() -> ModifierState {
+        ModifierState::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn maps_both_sides_of_each_modifier() {
+        assert_eq!(Modifiers::from_keycode(Key::LeftControl as u32), Some(Modifiers::CONTROL));
+        assert_eq!(Modifiers::from_keycode(Key::RightControl as u32), Some(Modifiers::CONTROL));
+        assert_eq!(Modifiers::from_keycode(Key::RightAlt as u32), Some(Modifiers::ALT));
+        assert_eq!(Modifiers::from_keycode(Key::LeftShift as u32), Some(Modifiers::SHIFT));
+        assert_eq!(Modifiers::from_keycode(Key::RightMeta as u32), Some(Modifiers::SUPER));
+        assert_eq!(Modifiers::from_keycode(Key::A as u32), None);
+    }
+
+    #[test]
+    fn tracks_held_modifiers() {
+        let mut state = ModifierState::new();
+        state.update(Key::LeftMeta as u32, true);
+        state.update(Key::A as u32, true);
+        state.update(Key::RightShift as u32, true);
+        assert_eq!(state.current(), Modifiers::SUPER.union(Modifiers::SHIFT));
+        assert_eq!(state.current().to_string(), "shift+super");
+
+        /* Either side keeps the modifier held until both are released */
+        state.update(Key::LeftShift as u32, true);
+        state.update(Key::RightShift as u32, false);
+        assert_eq!(state.current(), Modifiers::SUPER.union(Modifiers::SHIFT));
+        state.update(Key::LeftShift as u32, false);
+        state.update(Key::LeftMeta as u32, false);
+        assert_eq!(state.current(), Modifiers::NONE);
+
+        /* A release without a press, e.g. of a key held since before Kinesix started */
+        state.update(Key::LeftControl as u32, false);
+        assert!(state.current().is_empty());
+    }
+}
@@ -0,0 +1,259 @@
+/*
+ * Copyright © 2019 Romeo Calota
+ *
+ * This program is free software; you can redistribute it and/or
+ * modify it under the terms of the GNU Lesser General Public
+ * License as published by the Free Software Foundation; either
+ * version 2 of the licence, or (at your option) any later version.
+ *
+ * This software is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+ * Lesser General Public License for more details.
+ *
+ * You should have received a copy of the GNU Lesser General Public
+ * License along with this program; if not, see <http://www.gnu.org/licenses/>.
+ *
+ * Author: Romeo Calota
+ */
+
+use std::time::Duration;
+
+use virtualinput::codes::*;
+use virtualinput::{EvdevDevice, InputEvent};
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Touch
+{
+    pub tracking_id: i32,
+    pub x: i32,
+    pub y: i32,
+}
+
+/* Touchpad state at a SYN_REPORT */
+#[derive(Debug, PartialEq, Clone)]
+pub struct TouchFrame
+{
+    pub touches: Vec<Touch>,
+    /* Can be more than the tracked touches, pads report extra fingers through BTN_TOOL_*TAP only */
+    pub finger_count: u32,
+    /* Timestamp of the SYN_REPORT, see virtualinput::monotonic_time */
+    pub time: Duration,
+}
+
+impl TouchFrame {
+    pub fn centroid(&self) -> Option<(f64, f64)> {
+        if self.touches.is_empty() { return None; }
+
+        let count = self.touches.len() as f64;
+        let (sx, sy) = self.touches.iter().fold((0.0, 0.0), |(sx, sy), t| (sx + t.x as f64, sy + t.y as f64));
+        Some((sx / count, sy / count))
+    }
+
+    pub fn touch(&self, tracking_id: i32) -> Option<&Touch> {
+        self.touches.iter().find(|t| t.tracking_id == tracking_id)
+    }
+}
+
+/* The BTN_TOOL_* keys and the finger count each stands for */
+const TOOL_FINGER_COUNTS: [(u32, u32); 5] = [
+    (BTN_TOOL_FINGER, 1), (BTN_TOOL_DOUBLETAP, 2), (BTN_TOOL_TRIPLETAP, 3), (BTN_TOOL_QUADTAP, 4), (BTN_TOOL_QUINTTAP, 5),
+];
+
+#[derive(Debug, Clone, Copy)]
+struct Slot
+{
+    /* -1 while the slot is empty */
+    tracking_id: i32,
+    x: i32,
+    y: i32,
+}
+
+/* Follows ABS_MT_SLOT / ABS_MT_TRACKING_ID and the BTN_TOOL_* keys of a multitouch device */
+#[derive(Debug)]
+pub struct MultitouchState
+{
+    slots: Vec<Slot>,
+    current_slot: usize,
+    tool_finger_count: u32,
+    time: Duration,
+}
+
+impl MultitouchState {
+    pub fn new(slot_count: u32) -> MultitouchState {
+        MultitouchState {
+            slots: vec![Slot { tracking_id: -1, x: 0, y: 0 }; slot_count.max(1) as usize],
+            current_slot: 0,
+            tool_finger_count: 0,
+            time: Duration::from_secs(0),
+        }
+    }
+
+    /* Starts out with the touches already on the device */
+    pub fn from_device(device: &EvdevDevice) -> MultitouchState {
+        let mut state = MultitouchState::new(device.slot_count().unwrap_or(1));
+        state.resync(device);
+        state
+    }
+
+    /* Takes over the device state as libevdev knows it, e.g. after events were dropped */
+    pub fn resync(&mut self, device: &EvdevDevice) {
+        if device.slot_count().is_some() {
+            for (index, slot) in self.slots.iter_mut().enumerate() {
+                slot.tracking_id = device.slot_value(index as u32, ABS_MT_TRACKING_ID);
+                slot.x = device.slot_value(index as u32, ABS_MT_POSITION_X);
+                slot.y = device.slot_value(index as u32, ABS_MT_POSITION_Y);
+            }
+            self.current_slot = (device.current_slot() as usize).min(self.slots.len() - 1);
+        }
+
+        self.tool_finger_count = 0;
+        for (code, finger_count) in TOOL_FINGER_COUNTS.iter() {
+            if device.event_value(EV_KEY, *code) != 0 { self.tool_finger_count = *finger_count; }
+        }
+    }
+
+    /* Returns the new state on every SYN_REPORT */
+    pub fn update(&mut self, event: &InputEvent) -> Option<TouchFrame> {
+        self.time = event.time;
+        match event.event_type {
+            EV_ABS => {
+                let slot_count = self.slots.len();
+                let slot = &mut self.slots[self.current_slot];
+                match event.code {
+                    ABS_MT_SLOT if (event.value as usize) < slot_count => self.current_slot = event.value.max(0) as usize,
+                    ABS_MT_TRACKING_ID => slot.tracking_id = event.value,
+                    ABS_MT_POSITION_X => slot.x = event.value,
+                    ABS_MT_POSITION_Y => slot.y = event.value,
+                    _ => {},
+                }
+                None
+            },
+            EV_KEY => {
+                let finger_count = match TOOL_FINGER_COUNTS.iter().find(|(code, _)| *code == event.code) {
+                    Some((_, finger_count)) => *finger_count,
+                    None => return None,
+                };
+
+                if event.value != 0 {
+                    self.tool_finger_count = finger_count;
+                } else if self.tool_finger_count == finger_count {
+                    self.tool_finger_count = 0;
+                }
+                None
+            },
+            EV_SYN if event.code == SYN_REPORT => Some(self.frame()),
+            _ => None,
+        }
+    }
+
+    /* Events that bring a listener up to this state, from whatever it saw before, e.g. after a
+     * frame of them went missing */
+    pub fn state_events(&self) -> Vec<InputEvent> {
+        let mut events = Vec::new();
+        for (index, slot) in self.slots.iter().enumerate() {
+            events.push(InputEvent::new(EV_ABS, ABS_MT_SLOT, index as i32));
+            events.push(InputEvent::new(EV_ABS, ABS_MT_TRACKING_ID, slot.tracking_id));
+            if slot.tracking_id >= 0 {
+                events.push(InputEvent::new(EV_ABS, ABS_MT_POSITION_X, slot.x));
+                events.push(InputEvent::new(EV_ABS, ABS_MT_POSITION_Y, slot.y));
+            }
+        }
+        events.push(InputEvent::new(EV_ABS, ABS_MT_SLOT, self.current_slot as i32));
+
+        let touching = self.slots.iter().any(|s| s.tracking_id >= 0);
+        events.push(InputEvent::new(EV_KEY, BTN_TOUCH, touching as i32));
+        for (code, finger_count) in TOOL_FINGER_COUNTS.iter() {
+            events.push(InputEvent::new(EV_KEY, *code, (*finger_count == self.tool_finger_count) as i32));
+        }
+        events.push(InputEvent::new(EV_SYN, SYN_REPORT, 0));
+
+        events
+    }
+
+    /* Events that lift every finger, leaving the current slot as it was */
+    pub fn lift_events(&self) -> Vec<InputEvent> {
+        let mut events = Vec::new();
+        for index in 0..self.slots.len() {
+            events.push(InputEvent::new(EV_ABS, ABS_MT_SLOT, index as i32));
+            events.push(InputEvent::new(EV_ABS, ABS_MT_TRACKING_ID, -1));
+        }
+        events.push(InputEvent::new(EV_ABS, ABS_MT_SLOT, self.current_slot as i32));
+
+        for code in &[BTN_TOUCH, BTN_TOOL_FINGER, BTN_TOOL_DOUBLETAP, BTN_TOOL_TRIPLETAP, BTN_TOOL_QUADTAP, BTN_TOOL_QUINTTAP] {
+            events.push(InputEvent::new(EV_KEY, *code, 0));
+        }
+        events.push(InputEvent::new(EV_SYN, SYN_REPORT, 0));
+
+        events
+    }
+
+    pub fn frame(&self) -> TouchFrame {
+        let touches: Vec<Touch> = self.slots.iter()
+            .filter(|s| s.tracking_id >= 0)
+            .map(|s| Touch { tracking_id: s.tracking_id, x: s.x, y: s.y })
+            .collect();
+        let finger_count = self.tool_finger_count.max(touches.len() as u32);
+
+        TouchFrame { touches, finger_count, time: self.time }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn abs(code: u32, value: i32) -> InputEvent {
+        InputEvent::new(EV_ABS, code, value)
+    }
+
+    fn syn() -> InputEvent {
+        InputEvent { time: Duration::from_millis(1500), ..InputEvent::new(EV_SYN, SYN_REPORT, 0) }
+    }
+
+    #[test]
+    fn tracks_slots_and_tool_fingers() {
+        let mut state = MultitouchState::new(2);
+        let events = [
+            abs(ABS_MT_SLOT, 0), abs(ABS_MT_TRACKING_ID, 10), abs(ABS_MT_POSITION_X, 100), abs(ABS_MT_POSITION_Y, 200),
+            abs(ABS_MT_SLOT, 1), abs(ABS_MT_TRACKING_ID, 11), abs(ABS_MT_POSITION_X, 300), abs(ABS_MT_POSITION_Y, 200),
+            InputEvent::new(EV_KEY, BTN_TOOL_QUADTAP, 1),
+        ];
+        for event in &events {
+            assert!(state.update(event).is_none());
+        }
+
+        let frame = state.update(&syn()).unwrap();
+        assert_eq!(frame.touches.len(), 2);
+        assert_eq!(frame.finger_count, 4);
+        assert_eq!(frame.centroid(), Some((200.0, 200.0)));
+        assert_eq!(frame.time, Duration::from_millis(1500));
+
+        state.update(&InputEvent::new(EV_KEY, BTN_TOOL_QUADTAP, 0));
+        state.update(&abs(ABS_MT_TRACKING_ID, -1));
+        let frame = state.update(&syn()).unwrap();
+        assert_eq!(frame.finger_count, 1);
+        assert_eq!(frame.touch(10).map(|t| t.x), Some(100));
+    }
+
+    #[test]
+    fn replays_its_state() {
+        let mut state = MultitouchState::new(2);
+        for event in &[abs(ABS_MT_SLOT, 1), abs(ABS_MT_TRACKING_ID, 7), abs(ABS_MT_POSITION_X, 40), abs(ABS_MT_POSITION_Y, 50),
+                       InputEvent::new(EV_KEY, BTN_TOOL_FINGER, 1), syn()] {
+            state.update(event);
+        }
+
+        let events = state.state_events();
+        assert_eq!(&events[..6], &[
+            abs(ABS_MT_SLOT, 0), abs(ABS_MT_TRACKING_ID, -1),
+            abs(ABS_MT_SLOT, 1), abs(ABS_MT_TRACKING_ID, 7), abs(ABS_MT_POSITION_X, 40), abs(ABS_MT_POSITION_Y, 50),
+        ]);
+
+        /* Replaying them elsewhere ends up in the same state */
+        let mut replayed = MultitouchState::new(2);
+        let frame = events.iter().filter_map(|e| replayed.update(e)).last().unwrap();
+        assert_eq!((frame.touches, frame.finger_count), (state.frame().touches, 1));
+        assert!(events.contains(&InputEvent::new(EV_KEY, BTN_TOUCH, 1)));
+    }
+}
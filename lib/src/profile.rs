@@ -0,0 +1,150 @@
+/*
+ * Copyright © 2019 Romeo Calota
+ *
+ * This program is free software; you can redistribute it and/or
+ * modify it under the terms of the GNU Lesser General Public
+ * License as published by the Free Software Foundation; either
+ * version 2 of the licence, or (at your option) any later version.
+ *
+ * This software is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+ * Lesser General Public License for more details.
+ *
+ * You should have received a copy of the GNU Lesser General Public
+ * License along with this program; if not, see <http://www.gnu.org/licenses/>.
+ *
+ * Author: Romeo Calota
+ */
+
+use crate::action::Action;
+use crate::focused_window::{AppPattern, WindowInfo};
+use crate::gesture::Gesture;
+use crate::proportional::ProportionalAction;
+use crate::region::RegionTrigger;
+use crate::sequence::GestureSequence;
+use crate::stroke::Stroke;
+use crate::switches::SwitchTrigger;
+use crate::tap::Tap;
+use crate::tablet_pad::{PadBinding, PadTrigger};
+
+pub const DEFAULT_PROFILE: &str = "default";
+
+/* Gesture bindings that take precedence while a matching window has the focus */
+#[derive(Debug, Clone)]
+pub struct AppBindings
+{
+    pub pattern: AppPattern,
+    pub bindings: Vec<(Gesture, Action)>,
+}
+
+/* A named set of bindings; anything not bound in the active profile falls back to the default one */
+#[derive(Debug, Clone)]
+pub struct Profile
+{
+    pub name: String,
+
+    pub bindings: Vec<(Gesture, Action)>,
+    /* In configuration order, the first matching section binding a gesture wins */
+    pub app_bindings: Vec<AppBindings>,
+    pub sequences: Vec<(GestureSequence, Action)>,
+    pub pad_bindings: Vec<PadBinding>,
+    pub switch_bindings: Vec<(SwitchTrigger, Action)>,
+    pub stroke_bindings: Vec<(Stroke, Action)>,
+    /* Shapes matched against stroke templates, keyed by template name */
+    pub shape_bindings: Vec<(String, Action)>,
+    pub tap_bindings: Vec<(Tap, Action)>,
+    pub region_bindings: Vec<(RegionTrigger, Action)>,
+    /* Proportional actions keyed by finger count */
+    pub proportional_actions: Vec<(i32, ProportionalAction)>,
+}
+
+impl Profile {
+    pub fn new(name: &str) -> Profile {
+        Profile {
+            name: name.to_string(),
+            bindings: Vec::new(),
+            app_bindings: Vec::new(),
+            sequences: Vec::new(),
+            pad_bindings: Vec::new(),
+            switch_bindings: Vec::new(),
+            stroke_bindings: Vec::new(),
+            shape_bindings: Vec::new(),
+            tap_bindings: Vec::new(),
+            region_bindings: Vec::new(),
+            proportional_actions: Vec::new(),
+        }
+    }
+
+    pub fn gesture_action(&self, gesture: Gesture) -> Option<&Action> {
+        self.bindings.iter().find(|(g, _)| *g == gesture).map(|(_, action)| action)
+    }
+
+    /* The binding for window if there is one, the global one otherwise */
+    pub fn window_gesture_action(&self, window: Option<&WindowInfo>, gesture: Gesture) -> Option<&Action> {
+        window.and_then(|window| {
+            self.app_bindings.iter()
+                .filter(|app| app.pattern.matches(window))
+                .flat_map(|app| app.bindings.iter())
+                .find(|(g, _)| *g == gesture)
+                .map(|(_, action)| action)
+        }).or_else(|| self.gesture_action(gesture))
+    }
+
+    pub fn pad_action(&self, trigger: PadTrigger, group: u32, mode: u32) -> Option<&Action> {
+        self.pad_bindings.iter().find(|b| b.matches(trigger, group, mode)).map(|b| &b.action)
+    }
+
+    pub fn switch_action(&self, trigger: SwitchTrigger) -> Option<&Action> {
+        self.switch_bindings.iter().find(|(t, _)| *t == trigger).map(|(_, action)| action)
+    }
+
+    pub fn stroke_action(&self, stroke: &Stroke) -> Option<&Action> {
+        self.stroke_bindings.iter().find(|(s, _)| s == stroke).map(|(_, action)| action)
+    }
+
+    pub fn shape_action(&self, name: &str) -> Option<&Action> {
+        self.shape_bindings.iter().find(|(n, _)| n == name).map(|(_, action)| action)
+    }
+
+    pub fn tap_action(&self, tap: Tap) -> Option<&Action> {
+        self.tap_bindings.iter().find(|(t, _)| *t == tap).map(|(_, action)| action)
+    }
+
+    pub fn region_action(&self, trigger: &RegionTrigger) -> Option<&Action> {
+        self.region_bindings.iter().find(|(t, _)| t == trigger).map(|(_, action)| action)
+    }
+
+    /* Finger counts used by gesture bindings, sequences and proportional actions */
+    pub fn gesture_finger_counts(&self) -> Vec<i32> {
+        let mut finger_counts: Vec<i32> = self.bindings.iter().map(|(g, _)| g.finger_count)
+            .chain(self.app_bindings.iter().flat_map(|app| app.bindings.iter().map(|(g, _)| g.finger_count)))
+            .chain(self.sequences.iter().flat_map(|(s, _)| s.gestures.iter().map(|g| g.finger_count)))
+            .chain(self.proportional_actions.iter().map(|(fc, _)| *fc))
+            .collect();
+        finger_counts.sort();
+        finger_counts.dedup();
+        finger_counts
+    }
+
+    /* Every bound action, including those of proportional actions */
+    pub fn actions(&self) -> Vec<&Action> {
+        self.bindings.iter().map(|(_, action)| action)
+            .chain(self.app_bindings.iter().flat_map(|app| app.bindings.iter().map(|(_, action)| action)))
+            .chain(self.sequences.iter().map(|(_, action)| action))
+            .chain(self.pad_bindings.iter().map(|b| &b.action))
+            .chain(self.switch_bindings.iter().map(|(_, action)| action))
+            .chain(self.stroke_bindings.iter().map(|(_, action)| action))
+            .chain(self.shape_bindings.iter().map(|(_, action)| action))
+            .chain(self.tap_bindings.iter().map(|(_, action)| action))
+            .chain(self.region_bindings.iter().map(|(_, action)| action))
+            .chain(self.proportional_actions.iter().flat_map(|(_, p)| vec![&p.increase, &p.decrease]))
+            .collect()
+    }
+
+    pub fn proportional_action(&self, pinch: bool, finger_count: i32) -> Option<&ProportionalAction> {
+        self.proportional_actions.iter()
+            .find(|(fc, action)| *fc == finger_count && action.is_pinch() == pinch)
+            .map(|(_, action)| action)
+    }
+}
@@ -0,0 +1,178 @@
+/*
+ * Copyright © 2019 Romeo Calota
+ *
+ * This program is free software; you can redistribute it and/or
+ * modify it under the terms of the GNU Lesser General Public
+ * License as published by the Free Software Foundation; either
+ * version 2 of the licence, or (at your option) any later version.
+ *
+ * This software is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+ * Lesser General Public License for more details.
+ *
+ * You should have received a copy of the GNU Lesser General Public
+ * License along with this program; if not, see <http://www.gnu.org/licenses/>.
+ *
+ * Author: Romeo Calota
+ */
+
+use crate::action::Action;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum ProportionalAxis
+{
+    /* Swipe distance along X, moving right increases */
+    Horizontal,
+    /* Swipe distance along Y, moving up increases */
+    Vertical,
+    /* Pinch scale, spreading the fingers apart increases */
+    Scale,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct ProportionalAction
+{
+    pub axis: ProportionalAxis,
+    /* Distance needed for one step; in unaccelerated units for swipes
+     * and in natural log of the scale ratio for pinches */
+    pub step: f64,
+    /* How much faster movement adds extra steps, 0.0 disables acceleration */
+    pub acceleration: f64,
+    pub increase: Action,
+    pub decrease: Action,
+}
+
+impl ProportionalAction {
+    pub fn new(axis: ProportionalAxis, increase: Action, decrease: Action) -> ProportionalAction {
+        let step = match axis {
+            ProportionalAxis::Horizontal | ProportionalAxis::Vertical => 40.0,
+            ProportionalAxis::Scale => 0.1,
+        };
+
+        ProportionalAction { axis, step, acceleration: 0.0, increase, decrease }
+    }
+
+    pub fn is_pinch(&self) -> bool {
+        self.axis == ProportionalAxis::Scale
+    }
+}
+
+/* Accumulates gesture movement and converts it into a number of whole steps */
+#[derive(Debug)]
+pub struct ProportionalTracker
+{
+    accumulated: f64,
+    last_scale: f64,
+}
+
+impl ProportionalTracker {
+    pub fn new() -> ProportionalTracker {
+        ProportionalTracker { accumulated: 0.0, last_scale: 1.0 }
+    }
+
+    pub fn reset(&mut self) {
+        self.accumulated = 0.0;
+        self.last_scale = 1.0;
+    }
+
+    /* Returns the signed number of steps to fire for this update, positive meaning increase */
+    pub fn update(&mut self, action: &ProportionalAction, dx: f64, dy: f64, scale: f64) -> i32 {
+        if action.step <= 0.0 { return 0; }
+
+        let delta = match action.axis {
+            ProportionalAxis::Horizontal => dx,
+            ProportionalAxis::Vertical => -dy,
+            ProportionalAxis::Scale => {
+                if scale <= 0.0 { return 0; }
+                let delta = scale.ln() - self.last_scale.ln();
+                self.last_scale = scale;
+                delta
+            },
+        };
+
+        self.accumulated += delta * (1.0 + action.acceleration * delta.abs() / action.step);
+
+        let steps = (self.accumulated / action.step).trunc();
+        self.accumulated -= steps * action.step;
+
+        steps as i32
+    }
+}
+
+impl Default for ProportionalTracker {
+    fn default() -> ProportionalTracker {
+        ProportionalTracker::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn action(axis: ProportionalAxis) -> ProportionalAction {
+        ProportionalAction::new(axis, Action::Command(String::from("up")), Action::Command(String::from("down")))
+    }
+
+    #[test]
+    fn fires_a_step_once_the_threshold_is_crossed() {
+        let action = action(ProportionalAxis::Horizontal);
+        let mut tracker = ProportionalTracker::new();
+
+        assert_eq!(tracker.update(&action, 39.0, 0.0, 1.0), 0);
+        assert_eq!(tracker.update(&action, 2.0, 0.0, 1.0), 1);
+        assert_eq!(tracker.update(&action, 38.0, 0.0, 1.0), 0);
+        assert_eq!(tracker.update(&action, 1.0, 0.0, 1.0), 1);
+        assert_eq!(tracker.update(&action, 130.0, 0.0, 1.0), 3);
+
+        /* Moving up increases on the vertical axis */
+        let action = self::action(ProportionalAxis::Vertical);
+        tracker.reset();
+        assert_eq!(tracker.update(&action, 0.0, -85.0, 1.0), 2);
+        assert_eq!(tracker.update(&action, 0.0, 100.0, 1.0), -2);
+    }
+
+    #[test]
+    fn accumulates_back_and_forth_movement() {
+        let action = action(ProportionalAxis::Horizontal);
+        let mut tracker = ProportionalTracker::new();
+
+        assert_eq!(tracker.update(&action, 30.0, 0.0, 1.0), 0);
+        assert_eq!(tracker.update(&action, -20.0, 0.0, 1.0), 0);
+        assert_eq!(tracker.update(&action, 29.0, 0.0, 1.0), 0);
+        assert_eq!(tracker.update(&action, 2.0, 0.0, 1.0), 1);
+
+        /* Nothing carries over into the next gesture */
+        tracker.update(&action, 39.0, 0.0, 1.0);
+        tracker.reset();
+        assert_eq!(tracker.update(&action, 39.0, 0.0, 1.0), 0);
+    }
+
+    #[test]
+    fn steps_pinches_by_scale_ratio() {
+        let action = action(ProportionalAxis::Scale);
+        let mut tracker = ProportionalTracker::new();
+
+        /* Scale is absolute, a step is a ratio of about 1.105 */
+        assert_eq!(tracker.update(&action, 0.0, 0.0, 1.05), 0);
+        assert_eq!(tracker.update(&action, 0.0, 0.0, 1.12), 1);
+        assert_eq!(tracker.update(&action, 0.0, 0.0, 1.12 * 1.25), 2);
+        assert_eq!(tracker.update(&action, 0.0, 0.0, 0.0), 0);
+        assert_eq!(tracker.update(&action, 0.0, 0.0, 0.8), -5);
+    }
+
+    #[test]
+    fn accelerates_fast_movement() {
+        let mut action = action(ProportionalAxis::Horizontal);
+        let mut tracker = ProportionalTracker::new();
+        assert_eq!(tracker.update(&action, 80.0, 0.0, 1.0), 2);
+
+        action.acceleration = 0.5;
+        tracker.reset();
+        assert_eq!(tracker.update(&action, 80.0, 0.0, 1.0), 4);
+        assert_eq!(tracker.update(&action, 10.0, 0.0, 1.0), 0);
+
+        action.step = 0.0;
+        assert_eq!(tracker.update(&action, 1000.0, 0.0, 1.0), 0);
+    }
+}
@@ -0,0 +1,199 @@
+/*
+ * Copyright © 2019 Romeo Calota
+ *
+ * This program is free software; you can redistribute it and/or
+ * modify it under the terms of the GNU Lesser General Public
+ * License as published by the Free Software Foundation; either
+ * version 2 of the licence, or (at your option) any later version.
+ *
+ * This software is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+ * Lesser General Public License for more details.
+ *
+ * You should have received a copy of the GNU Lesser General Public
+ * License along with this program; if not, see <http://www.gnu.org/licenses/>.
+ *
+ * Author: Romeo Calota
+ */
+
+use crate::gesture::GESTURE_DELTA;
+use crate::multitouch::TouchFrame;
+
+/* libinput reports gesture motion normalized to a 1000dpi device */
+const UNITS_PER_MM: f64 = 1000.0 / 25.4;
+/* Change in finger spread that makes a touch a pinch rather than a swipe */
+const PINCH_SCALE_THRESHOLD: f64 = 0.2;
+
+/* Gestures recognized from raw touches, mirroring the libinput gesture events */
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum RawGestureEvent
+{
+    SwipeBegin(i32),
+    SwipeUpdate(f64, f64),
+    PinchBegin(i32),
+    /* Finger spread relative to the start of the pinch */
+    PinchUpdate(f64),
+    /* Carries the finger count the gesture began with */
+    End(i32),
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum Kind
+{
+    Undecided,
+    Swipe,
+    Pinch,
+}
+
+/* Turns the touches of a touchpad libinput no longer sees, because it is grabbed, into gestures */
+#[derive(Debug)]
+pub struct RawGestureTracker
+{
+    /* Device units per millimeter, per axis */
+    resolution: (f64, f64),
+
+    kind: Option<Kind>,
+    finger_count: i32,
+    tracking_ids: Vec<i32>,
+    last_centroid: (f64, f64),
+    start_spread: f64,
+    /* Motion while still deciding between swipe and pinch */
+    pending: (f64, f64),
+}
+
+impl RawGestureTracker {
+    pub fn new(resolution: (f64, f64)) -> RawGestureTracker {
+        RawGestureTracker {
+            resolution,
+            kind: None,
+            finger_count: 0,
+            tracking_ids: Vec::new(),
+            last_centroid: (0.0, 0.0),
+            start_spread: 0.0,
+            pending: (0.0, 0.0),
+        }
+    }
+
+    pub fn update(&mut self, frame: &TouchFrame) -> Vec<RawGestureEvent> {
+        if frame.finger_count == 0 || frame.touches.is_empty() {
+            let kind = self.kind.take();
+            return match kind {
+                Some(Kind::Swipe) | Some(Kind::Pinch) => vec![RawGestureEvent::End(self.finger_count)],
+                _ => Vec::new(),
+            };
+        }
+
+        let centroid = frame.centroid().unwrap();
+        let spread = self.spread(frame, centroid);
+        let tracking_ids: Vec<i32> = frame.touches.iter().map(|t| t.tracking_id).collect();
+
+        let kind = match self.kind {
+            Some(kind) if tracking_ids == self.tracking_ids => kind,
+            Some(kind) => {
+                /* Fingers came or went, start measuring from the new set without a jump */
+                let scale = self.scale_for(spread);
+                self.tracking_ids = tracking_ids;
+                self.last_centroid = centroid;
+                self.start_spread = spread / scale;
+                return if kind == Kind::Pinch { vec![RawGestureEvent::PinchUpdate(scale)] } else { Vec::new() };
+            },
+            None => {
+                self.kind = Some(Kind::Undecided);
+                self.tracking_ids = tracking_ids;
+                self.last_centroid = centroid;
+                self.start_spread = spread;
+                self.pending = (0.0, 0.0);
+                return Vec::new();
+            },
+        };
+
+        let dx = (centroid.0 - self.last_centroid.0) / self.resolution.0 * UNITS_PER_MM;
+        let dy = (centroid.1 - self.last_centroid.1) / self.resolution.1 * UNITS_PER_MM;
+        self.last_centroid = centroid;
+        let scale = self.scale_for(spread);
+
+        let finger_count = frame.finger_count as i32;
+        match kind {
+            Kind::Undecided => {
+                self.pending.0 += dx;
+                self.pending.1 += dy;
+
+                self.finger_count = finger_count;
+                if (scale - 1.0).abs() > PINCH_SCALE_THRESHOLD {
+                    self.kind = Some(Kind::Pinch);
+                    vec![RawGestureEvent::PinchBegin(finger_count), RawGestureEvent::PinchUpdate(scale)]
+                } else if self.pending.0.hypot(self.pending.1) > 3.0 * GESTURE_DELTA {
+                    self.kind = Some(Kind::Swipe);
+                    vec![RawGestureEvent::SwipeBegin(finger_count), RawGestureEvent::SwipeUpdate(self.pending.0, self.pending.1)]
+                } else {
+                    Vec::new()
+                }
+            },
+            Kind::Swipe => vec![RawGestureEvent::SwipeUpdate(dx, dy)],
+            Kind::Pinch => vec![RawGestureEvent::PinchUpdate(scale)],
+        }
+    }
+
+    /* Spread relative to the start of the gesture */
+    fn scale_for(&self, spread: f64) -> f64 {
+        if self.start_spread > 0.0 && spread > 0.0 { spread / self.start_spread } else { 1.0 }
+    }
+
+    /* Mean distance of the fingers from their centroid, in millimeters */
+    fn spread(&self, frame: &TouchFrame, centroid: (f64, f64)) -> f64 {
+        let total: f64 = frame.touches.iter()
+            .map(|t| ((t.x as f64 - centroid.0) / self.resolution.0).hypot((t.y as f64 - centroid.1) / self.resolution.1))
+            .sum();
+        total / frame.touches.len() as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::multitouch::Touch;
+    use std::time::Duration;
+
+    fn frame(positions: &[(i32, i32)]) -> TouchFrame {
+        let touches = positions.iter().enumerate().map(|(i, (x, y))| Touch { tracking_id: i as i32, x: *x, y: *y }).collect();
+        TouchFrame { touches, finger_count: positions.len() as u32, time: Duration::from_secs(0) }
+    }
+
+    #[test]
+    fn recognizes_three_finger_swipe() {
+        let mut tracker = RawGestureTracker::new((10.0, 10.0));
+        let mut events = Vec::new();
+        for step in 0..10 {
+            let x = 1000 + 20 * step;
+            events.extend(tracker.update(&frame(&[(x, 500), (x + 150, 500), (x + 300, 500)])));
+        }
+        events.extend(tracker.update(&frame(&[])));
+
+        assert_eq!(events[0], RawGestureEvent::SwipeBegin(3));
+        assert!(events.iter().all(|e| match e { RawGestureEvent::SwipeUpdate(dx, dy) => *dx > 0.0 && *dy == 0.0, _ => true }));
+        assert_eq!(events.last(), Some(&RawGestureEvent::End(3)));
+    }
+
+    #[test]
+    fn recognizes_pinch() {
+        let mut tracker = RawGestureTracker::new((10.0, 10.0));
+        let mut events = Vec::new();
+        for step in 0..5 {
+            let offset = 200 + 40 * step;
+            events.extend(tracker.update(&frame(&[(1000 - offset, 500), (1000 + offset, 500), (1000, 500 - offset), (1000, 500 + offset)])));
+        }
+
+        assert_eq!(events[0], RawGestureEvent::PinchBegin(4));
+        match events.last() {
+            Some(RawGestureEvent::PinchUpdate(scale)) => assert!(*scale > 1.5),
+            other => panic!("unexpected {:?}", other),
+        }
+
+        /* A touch that barely moves is no gesture at all */
+        tracker.update(&frame(&[]));
+        assert!(tracker.update(&frame(&[(0, 0), (100, 0), (200, 0)])).is_empty());
+        assert!(tracker.update(&frame(&[(2, 0), (102, 0), (202, 0)])).is_empty());
+        assert!(tracker.update(&frame(&[])).is_empty());
+    }
+}
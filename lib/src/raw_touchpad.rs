@@ -0,0 +1,290 @@
+/*
+ * Copyright © 2019 Romeo Calota
+ *
+ * This program is free software; you can redistribute it and/or
+ * modify it under the terms of the GNU Lesser General Public
+ * License as published by the Free Software Foundation; either
+ * version 2 of the licence, or (at your option) any later version.
+ *
+ * This software is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+ * Lesser General Public License for more details.
+ *
+ * You should have received a copy of the GNU Lesser General Public
+ * License along with this program; if not, see <http://www.gnu.org/licenses/>.
+ *
+ * Author: Romeo Calota
+ */
+
+use virtualinput::codes::*;
+use virtualinput::{monotonic_time, EvdevDevice, InputEvent, UinputClone};
+
+use crate::gesture::DirectionTransform;
+use crate::multitouch::{MultitouchState, TouchFrame};
+use crate::raw_gesture::{RawGestureEvent, RawGestureTracker};
+use crate::region::{Region, RegionTracker, RegionTrigger};
+use crate::tap::{Tap, TapRecognizer};
+use crate::two_finger_swipe::TwoFingerSwipeRecognizer;
+use crate::SwipeDirection;
+
+/* Fraction of the pad diagonal a finger may move during a tap */
+const TAP_MOVEMENT_RATIO: f64 = 0.03;
+/* Assumed pad width, for devices that don't report their resolution */
+const FALLBACK_WIDTH_MM: f64 = 100.0;
+
+/* What a grabbed touchpad keeps from the desktop */
+#[derive(Debug, Default)]
+pub struct TouchpadClaims
+{
+    /* Touches reaching one of these are turned into gestures */
+    pub finger_counts: Vec<u32>,
+    /* Single-finger touches starting in one of these regions, by name */
+    pub regions: Vec<String>,
+    /* Two-finger flicks, recognized with this transform, see take_flicks */
+    pub flick_transform: Option<DirectionTransform>,
+}
+
+/* The touchpad read directly through evdev, next to libinput, for what libinput doesn't report */
+pub struct RawTouchpad
+{
+    device: EvdevDevice,
+    state: MultitouchState,
+    taps: TapRecognizer,
+    two_finger_swipes: TwoFingerSwipeRecognizer,
+    regions: RegionTracker,
+
+    /* While grabbed, touches with a claimed finger count are turned into gestures here and
+     * everything else is re-emitted through the passthrough device, so the pad keeps working */
+    passthrough: Option<UinputClone>,
+    gestures: RawGestureTracker,
+    gesture_events: Vec<RawGestureEvent>,
+    claimed: bool,
+    /* Events of the frame being read */
+    frame_events: Vec<InputEvent>,
+    /* Two-finger touches held back while they may still be a flick, and flicks recognized since the last call */
+    held_events: Vec<InputEvent>,
+    flicks: Vec<SwipeDirection>,
+    /* Set once a flick or region touch was taken from the desktop, until the fingers lift */
+    withheld: bool,
+    last_finger_count: u32,
+    /* Set after a SYN_DROPPED until the next SYN_REPORT, the events in between are incomplete */
+    dropping: bool,
+
+    /* Range of the absolute axes, in device units */
+    pub x_range: (i32, i32),
+    pub y_range: (i32, i32),
+}
+
+impl RawTouchpad {
+    pub fn open(path: &str, regions: Vec<Region>) -> Result<RawTouchpad, String> {
+        let device = EvdevDevice::open(path)?;
+
+        let x_range = device.abs_range(ABS_MT_POSITION_X);
+        let y_range = device.abs_range(ABS_MT_POSITION_Y);
+        let (x_range, y_range) = match (x_range, y_range) {
+            (Some(x_range), Some(y_range)) => (x_range, y_range),
+            _ => return Err(format!("{} ({}) is not a multitouch device", path, device.name())),
+        };
+
+        let diagonal = ((x_range.1 - x_range.0) as f64).hypot((y_range.1 - y_range.0) as f64);
+
+        let fallback_resolution = (x_range.1 - x_range.0) as f64 / FALLBACK_WIDTH_MM;
+        let resolution = |code| match device.abs_resolution(code) {
+            0 => fallback_resolution,
+            resolution => resolution as f64,
+        };
+        let resolution = (resolution(ABS_MT_POSITION_X), resolution(ABS_MT_POSITION_Y));
+
+        Ok(RawTouchpad {
+            state: MultitouchState::from_device(&device),
+            taps: TapRecognizer::new(diagonal * TAP_MOVEMENT_RATIO),
+            two_finger_swipes: TwoFingerSwipeRecognizer::new((x_range.1 - x_range.0) as f64),
+            regions: RegionTracker::new(regions),
+            passthrough: None,
+            gestures: RawGestureTracker::new(resolution),
+            gesture_events: Vec::new(),
+            claimed: false,
+            frame_events: Vec::new(),
+            held_events: Vec::new(),
+            flicks: Vec::new(),
+            withheld: false,
+            last_finger_count: 0,
+            dropping: false,
+            device,
+            x_range,
+            y_range,
+        })
+    }
+
+    pub fn path(&self) -> &str {
+        self.device.path()
+    }
+
+    /* Take the pad away from everyone else, see passthrough */
+    pub fn set_grab(&mut self, grab: bool) -> Result<(), String> {
+        if grab == self.passthrough.is_some() { return Ok(()); }
+
+        if grab {
+            let passthrough = self.device.create_clone()?;
+            self.device.grab(true)?;
+            self.passthrough = Some(passthrough);
+        } else {
+            self.device.grab(false)?;
+            self.passthrough = None;
+            self.claimed = false;
+            self.withheld = false;
+            self.held_events.clear();
+        }
+
+        Ok(())
+    }
+
+    /* Reads everything pending on the device, returning the frames completed by it. When grabbed,
+     * the touches claimed are kept from the desktop until they lift. */
+    pub fn read_frames(&mut self, claims: &TouchpadClaims) -> Result<Vec<TouchFrame>, String> {
+        let mut frames = Vec::new();
+        while let Some(event) = self.device.next_event()? {
+            let frame = match (event.event_type, event.code) {
+                (EV_SYN, SYN_DROPPED) => {
+                    self.frame_events.clear();
+                    self.dropping = true;
+                    continue;
+                },
+                (EV_SYN, SYN_REPORT) if self.dropping => {
+                    /* Start over from the state libevdev resynchronized, and bring the desktop up to it */
+                    self.dropping = false;
+                    self.state.resync(&self.device);
+                    self.frame_events = self.state.state_events();
+                    self.frame_events.pop();
+                    self.frame_events.push(event);
+                    TouchFrame { time: event.time, ..self.state.frame() }
+                },
+                _ if self.dropping => continue,
+                _ => {
+                    self.frame_events.push(event);
+                    match self.state.update(&event) {
+                        Some(frame) => frame,
+                        None => continue,
+                    }
+                },
+            };
+
+            self.pass_through(&frame, claims)?;
+            self.last_finger_count = frame.finger_count;
+            frames.push(frame);
+        }
+
+        Ok(frames)
+    }
+
+    fn pass_through(&mut self, frame: &TouchFrame, claims: &TouchpadClaims) -> Result<(), String> {
+        let mut events = std::mem::take(&mut self.frame_events);
+        if self.passthrough.is_none() { return Ok(()); }
+
+        if self.last_finger_count == 0 && frame.finger_count == 1 && !claims.regions.is_empty() {
+            /* The desktop never sees a touch starting in a bound region */
+            let region = self.normalized_position(frame).and_then(|position| self.regions.region_at(position));
+            if region.map(|r| claims.regions.contains(&r.name)).unwrap_or(false) {
+                self.withheld = true;
+            }
+        }
+
+        /* The recognizer sees every frame, so it starts over once the fingers lift */
+        let flick = match claims.flick_transform.as_ref() {
+            Some(transform) => self.two_finger_swipes.update(frame, transform),
+            None => None,
+        };
+
+        let passthrough = match self.passthrough.as_mut() {
+            Some(passthrough) => passthrough,
+            None => return Ok(()),
+        };
+
+        if claims.flick_transform.is_some() && !self.claimed && !self.withheld {
+            /* The desktop only gets to scroll once the touch can't be a flick anymore */
+            if let Some(direction) = flick {
+                self.flicks.push(direction);
+                self.withheld = true;
+                let held = std::mem::take(&mut self.held_events);
+                passthrough.write(&self.state.lift_events())?;
+                write_buttons(passthrough, &held)?;
+            } else if self.two_finger_swipes.is_pending() {
+                self.held_events.extend(events);
+                return Ok(());
+            } else if !self.held_events.is_empty() {
+                self.held_events.extend(events);
+                events = std::mem::take(&mut self.held_events);
+            }
+        }
+
+        if self.withheld {
+            if frame.finger_count == 0 { self.withheld = false; }
+            return write_buttons(passthrough, &events);
+        }
+
+        if !self.claimed && claims.finger_counts.contains(&frame.finger_count) {
+            /* To the desktop it looks like the fingers lifted */
+            self.claimed = true;
+            passthrough.write(&self.state.lift_events())?;
+        }
+
+        if self.claimed {
+            self.gesture_events.extend(self.gestures.update(frame));
+            if frame.finger_count == 0 { self.claimed = false; }
+            return write_buttons(passthrough, &events);
+        }
+
+        passthrough.write(&events)
+    }
+
+    /* Gestures recognized from claimed touches since the last call */
+    pub fn take_gesture_events(&mut self) -> Vec<RawGestureEvent> {
+        std::mem::take(&mut self.gesture_events)
+    }
+
+    pub fn update_taps(&mut self, frame: &TouchFrame) -> Vec<Tap> {
+        self.taps.update(frame)
+    }
+
+    /* Two-finger flicks recognized since the last call, only while grabbed */
+    pub fn take_flicks(&mut self) -> Vec<SwipeDirection> {
+        std::mem::take(&mut self.flicks)
+    }
+
+    pub fn update_regions(&mut self, frame: &TouchFrame) -> Vec<RegionTrigger> {
+        let position = self.normalized_position(frame);
+        self.regions.update(frame.finger_count, position, frame.time)
+    }
+
+    /* Regions are fractions of the pad, from the top left corner, and only see single touches */
+    fn normalized_position(&self, frame: &TouchFrame) -> Option<(f64, f64)> {
+        match frame.touches.as_slice() {
+            [touch] => Some((
+                (touch.x - self.x_range.0) as f64 / (self.x_range.1 - self.x_range.0).max(1) as f64,
+                (touch.y - self.y_range.0) as f64 / (self.y_range.1 - self.y_range.0).max(1) as f64,
+            )),
+            _ => None,
+        }
+    }
+
+    pub fn set_regions(&mut self, regions: Vec<Region>) {
+        self.regions = RegionTracker::new(regions);
+    }
+
+    pub fn poll_taps(&mut self) -> Vec<Tap> {
+        self.taps.poll(monotonic_time())
+    }
+}
+
+/* Clickpad buttons still reach the desktop while the touch is kept from it, a click isn't a gesture */
+fn write_buttons(passthrough: &mut UinputClone, events: &[InputEvent]) -> Result<(), String> {
+    let mut buttons: Vec<InputEvent> = events.iter()
+        .filter(|e| e.event_type == EV_KEY && [BTN_LEFT, BTN_RIGHT, BTN_MIDDLE].contains(&e.code))
+        .cloned()
+        .collect();
+    if buttons.is_empty() { return Ok(()); }
+
+    buttons.push(InputEvent::new(EV_SYN, SYN_REPORT, 0));
+    passthrough.write(&buttons)
+}
@@ -0,0 +1,277 @@
+/*
+ * Copyright © 2019 Romeo Calota
+ *
+ * This program is free software; you can redistribute it and/or
+ * modify it under the terms of the GNU Lesser General Public
+ * License as published by the Free Software Foundation; either
+ * version 2 of the licence, or (at your option) any later version.
+ *
+ * This software is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+ * Lesser General Public License for more details.
+ *
+ * You should have received a copy of the GNU Lesser General Public
+ * License along with this program; if not, see <http://www.gnu.org/licenses/>.
+ *
+ * Author: Romeo Calota
+ */
+
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+
+/* Longest a touch may last to count as a tap */
+const MAX_TAP_DURATION: Duration = Duration::from_millis(250);
+/* Travel, as a fraction of the pad, after which a touch is a slide rather than a tap */
+const SLIDE_THRESHOLD: f64 = 0.03;
+/* Travel, as a fraction of the pad, for each step of a slide */
+const SLIDE_STEP: f64 = 0.05;
+
+/* An area of the touchpad, in fractions of its width and height from the top left corner */
+#[derive(Debug, PartialEq, Clone)]
+pub struct Region
+{
+    pub name: String,
+    pub left: f64,
+    pub top: f64,
+    pub right: f64,
+    pub bottom: f64,
+}
+
+impl Region {
+    /* area is "left top right bottom", e.g. "0.8 0.0 1.0 0.2" for the top right corner */
+    pub fn new(name: &str, area: &str) -> Result<Region, String> {
+        let values = area.split_whitespace()
+            .map(|v| v.parse::<f64>().map_err(|_| format!("Invalid number \"{}\"", v)))
+            .collect::<Result<Vec<f64>, String>>()?;
+
+        if values.len() != 4 {
+            return Err(format!("A region needs left, top, right and bottom, found \"{}\"", area));
+        }
+        if values.iter().any(|v| *v < 0.0 || *v > 1.0) || values[0] >= values[2] || values[1] >= values[3] {
+            return Err(format!("Region \"{}\" must lie within 0.0 and 1.0 and have a size", name));
+        }
+
+        Ok(Region { name: name.to_string(), left: values[0], top: values[1], right: values[2], bottom: values[3] })
+    }
+
+    pub fn contains(&self, x: f64, y: f64) -> bool {
+        x >= self.left && x <= self.right && y >= self.top && y <= self.bottom
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum SlideDirection
+{
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+/* Written as "region-tap:NAME" or "region-slide-up:NAME", also down, left and right */
+#[derive(Debug, PartialEq, Clone)]
+pub enum RegionTrigger
+{
+    Tap(String),
+    Slide(String, SlideDirection),
+}
+
+impl RegionTrigger {
+    pub fn region(&self) -> &str {
+        match self {
+            RegionTrigger::Tap(name) | RegionTrigger::Slide(name, _) => name,
+        }
+    }
+}
+
+impl fmt::Display for RegionTrigger {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RegionTrigger::Tap(name) => write!(f, "region-tap:{}", name),
+            RegionTrigger::Slide(name, direction) => {
+                let direction = match direction {
+                    SlideDirection::Up => "up",
+                    SlideDirection::Down => "down",
+                    SlideDirection::Left => "left",
+                    SlideDirection::Right => "right",
+                };
+                write!(f, "region-slide-{}:{}", direction, name)
+            },
+        }
+    }
+}
+
+impl FromStr for RegionTrigger {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<RegionTrigger, String> {
+        let mut parts = s.trim().splitn(2, ':');
+        let kind = parts.next().unwrap_or("");
+        let name = parts.next().map(|n| n.trim()).filter(|n| !n.is_empty())
+            .ok_or_else(|| format!("Missing region name in \"{}\"", s))?
+            .to_string();
+
+        match kind {
+            "region-tap" => Ok(RegionTrigger::Tap(name)),
+            "region-slide-up" => Ok(RegionTrigger::Slide(name, SlideDirection::Up)),
+            "region-slide-down" => Ok(RegionTrigger::Slide(name, SlideDirection::Down)),
+            "region-slide-left" => Ok(RegionTrigger::Slide(name, SlideDirection::Left)),
+            "region-slide-right" => Ok(RegionTrigger::Slide(name, SlideDirection::Right)),
+            _ => Err(format!("Unknown region trigger \"{}\"", kind)),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct RegionTouch
+{
+    /* Index of the region the touch started in, None if it started elsewhere */
+    region: Option<usize>,
+    start_time: Duration,
+    start: (f64, f64),
+    last: (f64, f64),
+    /* Travel not yet turned into slide steps */
+    accumulated: (f64, f64),
+    sliding: bool,
+}
+
+/* Turns single-finger touches starting inside a region into taps and slide steps */
+#[derive(Debug)]
+pub struct RegionTracker
+{
+    regions: Vec<Region>,
+    touch: Option<RegionTouch>,
+}
+
+impl RegionTracker {
+    pub fn new(regions: Vec<Region>) -> RegionTracker {
+        RegionTracker { regions, touch: None }
+    }
+
+    /* The region a touch at position would start in */
+    pub fn region_at(&self, position: (f64, f64)) -> Option<&Region> {
+        self.regions.iter().find(|r| r.contains(position.0, position.1))
+    }
+
+    /* position is normalized to the pad and only set while exactly one finger is down. now is the event
+     * timestamp, so how events were batched when read doesn't change how long a touch lasted. */
+    pub fn update(&mut self, finger_count: u32, position: Option<(f64, f64)>, now: Duration) -> Vec<RegionTrigger> {
+        if finger_count == 0 {
+            return match self.touch.take() {
+                Some(RegionTouch { region: Some(index), start_time, sliding: false, .. }) if now.saturating_sub(start_time) <= MAX_TAP_DURATION => {
+                    vec![RegionTrigger::Tap(self.regions[index].name.clone())]
+                },
+                _ => Vec::new(),
+            };
+        }
+
+        let (x, y) = match position {
+            Some(position) if finger_count == 1 => position,
+            _ => {
+                /* More fingers make it something else, ignore the touch until they all lift */
+                if let Some(touch) = self.touch.as_mut() { touch.region = None; }
+                if self.touch.is_none() {
+                    self.touch = Some(RegionTouch { region: None, start_time: now, start: (0.0, 0.0), last: (0.0, 0.0), accumulated: (0.0, 0.0), sliding: false });
+                }
+                return Vec::new();
+            }
+        };
+
+        let touch = match self.touch.as_mut() {
+            Some(touch) => touch,
+            None => {
+                /* Overlapping regions go to the first one, as in region_at */
+                let region = self.regions.iter().position(|r| r.contains(x, y));
+                self.touch = Some(RegionTouch { region, start_time: now, start: (x, y), last: (x, y), accumulated: (0.0, 0.0), sliding: false });
+                return Vec::new();
+            }
+        };
+
+        let index = match touch.region {
+            Some(index) => index,
+            None => return Vec::new(),
+        };
+
+        touch.accumulated.0 += x - touch.last.0;
+        touch.accumulated.1 += y - touch.last.1;
+        touch.last = (x, y);
+
+        if !touch.sliding {
+            if (x - touch.start.0).hypot(y - touch.start.1) < SLIDE_THRESHOLD { return Vec::new(); }
+            touch.sliding = true;
+        }
+
+        let name = &self.regions[index].name;
+        let mut triggers = Vec::new();
+        let steps = [
+            (&mut touch.accumulated.0, SlideDirection::Right, SlideDirection::Left),
+            (&mut touch.accumulated.1, SlideDirection::Down, SlideDirection::Up),
+        ];
+        for (accumulated, increasing, decreasing) in steps {
+            let count = (*accumulated / SLIDE_STEP).trunc();
+            *accumulated -= count * SLIDE_STEP;
+
+            let direction = if count > 0.0 { increasing } else { decreasing };
+            for _ in 0..(count.abs() as usize) {
+                triggers.push(RegionTrigger::Slide(name.clone(), direction));
+            }
+        }
+
+        triggers
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tracker() -> RegionTracker {
+        RegionTracker::new(vec![
+            Region::new("top-right", "0.8 0.0 1.0 0.2").unwrap(),
+            Region::new("right-edge", "0.9 0.2 1.0 1.0").unwrap(),
+        ])
+    }
+
+    #[test]
+    fn taps_inside_region_only() {
+        let mut tracker = tracker();
+        let start = Duration::from_secs(1);
+
+        assert!(tracker.update(1, Some((0.9, 0.1)), start).is_empty());
+        assert_eq!(tracker.update(0, None, start + Duration::from_millis(100)), vec![RegionTrigger::Tap(String::from("top-right"))]);
+
+        assert!(tracker.update(1, Some((0.5, 0.5)), start).is_empty());
+        assert!(tracker.update(0, None, start + Duration::from_millis(100)).is_empty());
+
+        /* A second finger turns it into something else */
+        tracker.update(1, Some((0.9, 0.1)), start);
+        tracker.update(2, None, start);
+        assert!(tracker.update(0, None, start + Duration::from_millis(100)).is_empty());
+
+        assert_eq!(tracker.region_at((0.95, 0.1)).map(|r| r.name.as_str()), Some("top-right"));
+        assert_eq!(tracker.region_at((0.5, 0.1)), None);
+        assert_eq!(RegionTrigger::Slide(String::from("edge"), SlideDirection::Up).region(), "edge");
+    }
+
+    #[test]
+    fn slides_in_steps() {
+        let mut tracker = tracker();
+        let start = Duration::from_secs(1);
+
+        tracker.update(1, Some((0.95, 0.8)), start);
+        assert!(tracker.update(1, Some((0.95, 0.78)), start).is_empty());
+        let triggers = tracker.update(1, Some((0.95, 0.68)), start);
+        assert_eq!(triggers, vec![RegionTrigger::Slide(String::from("right-edge"), SlideDirection::Up); 2]);
+        assert!(tracker.update(0, None, start + Duration::from_millis(100)).is_empty());
+    }
+
+    #[test]
+    fn parses_triggers_and_regions() {
+        assert_eq!("region-slide-down:right-edge".parse::<RegionTrigger>().unwrap().to_string(), "region-slide-down:right-edge");
+        assert!("region-tap:".parse::<RegionTrigger>().is_err());
+        assert!(Region::new("bad", "0.5 0.0 0.4 1.0").is_err());
+        assert!(Region::new("bad", "0.5 0.0 1.0").is_err());
+    }
+}
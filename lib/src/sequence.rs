@@ -0,0 +1,326 @@
+/*
+ * Copyright © 2019 Romeo Calota
+ *
+ * This program is free software; you can redistribute it and/or
+ * modify it under the terms of the GNU Lesser General Public
+ * License as published by the Free Software Foundation; either
+ * version 2 of the licence, or (at your option) any later version.
+ *
+ * This software is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+ * Lesser General Public License for more details.
+ *
+ * You should have received a copy of the GNU Lesser General Public
+ * License along with this program; if not, see <http://www.gnu.org/licenses/>.
+ *
+ * Author: Romeo Calota
+ */
+
+use std::fmt;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+use crate::gesture::Gesture;
+
+const DEFAULT_SEQUENCE_TIMEOUT: Duration = Duration::from_millis(800);
+
+/* A multi-stroke combo, written as "swipe-up:3 then swipe-left:3 within 800ms" */
+#[derive(Debug, PartialEq, Clone)]
+pub struct GestureSequence
+{
+    pub gestures: Vec<Gesture>,
+    /* Maximum time allowed between two consecutive gestures of the sequence */
+    pub timeout: Duration,
+}
+
+impl GestureSequence {
+    fn starts_with(&self, gestures: &[Gesture]) -> bool {
+        gestures.len() <= self.gestures.len() && self.gestures[..gestures.len()] == *gestures
+    }
+}
+
+impl fmt::Display for GestureSequence {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let gestures: Vec<String> = self.gestures.iter().map(|g| g.to_string()).collect();
+        write!(f, "{} within {}ms", gestures.join(" then "), self.timeout.as_millis())
+    }
+}
+
+impl FromStr for GestureSequence {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<GestureSequence, String> {
+        let mut gestures = Vec::new();
+        let mut timeout = DEFAULT_SEQUENCE_TIMEOUT;
+
+        let mut tokens = s.split_whitespace();
+        while let Some(token) = tokens.next() {
+            match token {
+                "then" => {},
+                "within" => {
+                    let value = tokens.next().ok_or_else(|| String::from("Missing timeout after \"within\""))?;
+                    timeout = parse_duration(value)?;
+                },
+                _ => gestures.push(token.parse::<Gesture>()?),
+            }
+        }
+
+        if gestures.len() < 2 {
+            return Err(format!("A sequence needs at least two gestures: \"{}\"", s));
+        }
+
+        Ok(GestureSequence { gestures, timeout })
+    }
+}
+
+/* Parses "800ms" or "1s" style durations */
+pub fn parse_duration(value: &str) -> Result<Duration, String> {
+    let result = if let Some(ms) = value.strip_suffix("ms") {
+        ms.parse::<u64>().map(Duration::from_millis)
+    } else if let Some(secs) = value.strip_suffix('s') {
+        secs.parse::<u64>().map(Duration::from_secs)
+    } else {
+        value.parse::<u64>().map(Duration::from_millis)
+    };
+
+    result.map_err(|_| format!("Invalid duration \"{}\"", value))
+}
+
+/* What to do with a single gesture that could also be the start of a sequence */
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum AmbiguityPolicy
+{
+    /* Fire every single gesture right away, and the sequence as well once it completes */
+    FireImmediately,
+    /* Hold back gestures that may start a sequence until it completes or times out */
+    WaitForSequence,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum SequenceEvent
+{
+    Single(Gesture),
+    /* Index of the completed sequence, in the order they were added */
+    Sequence(usize),
+}
+
+#[derive(Debug)]
+pub struct SequenceMatcher
+{
+    sequences: Vec<GestureSequence>,
+    pub policy: AmbiguityPolicy,
+
+    pending: Vec<Gesture>,
+    last_gesture_time: Option<Instant>,
+}
+
+impl SequenceMatcher {
+    pub fn new(policy: AmbiguityPolicy) -> SequenceMatcher {
+        SequenceMatcher {
+            sequences: Vec::new(),
+            policy,
+            pending: Vec::new(),
+            last_gesture_time: None,
+        }
+    }
+
+    pub fn add_sequence(&mut self, sequence: GestureSequence) -> usize {
+        self.sequences.push(sequence);
+        self.sequences.len() - 1
+    }
+
+    pub fn clear(&mut self) {
+        self.sequences.clear();
+        self.pending.clear();
+        self.last_gesture_time = None;
+    }
+
+    /* Feed a recognized gesture, returns what should fire as a result */
+    pub fn push(&mut self, gesture: Gesture, now: Instant) -> Vec<SequenceEvent> {
+        let mut events = self.poll(now);
+
+        self.pending.push(gesture);
+        if !self.is_pending_prefix() {
+            /* The combo broke off, what was pending may already be a sequence held back because it could
+             * be extended, and the new gesture may still start another one */
+            self.pending.pop();
+            events.append(&mut self.resolve());
+
+            self.pending.push(gesture);
+            if !self.is_pending_prefix() {
+                self.pending.clear();
+                events.push(SequenceEvent::Single(gesture));
+                return events;
+            }
+        }
+        self.last_gesture_time = Some(now);
+
+        if self.policy == AmbiguityPolicy::FireImmediately { events.push(SequenceEvent::Single(gesture)); }
+
+        if let Some(index) = self.complete_match() {
+            let extendable = self.sequences.iter().any(|s| s.gestures.len() > self.pending.len() && s.starts_with(&self.pending));
+            if !extendable {
+                self.pending.clear();
+                self.last_gesture_time = None;
+                events.push(SequenceEvent::Sequence(index));
+            }
+        }
+
+        events
+    }
+
+    /* Resolve pending gestures whose sequence timed out, call this periodically */
+    pub fn poll(&mut self, now: Instant) -> Vec<SequenceEvent> {
+        let last_gesture_time = match self.last_gesture_time {
+            Some(time) => time,
+            None => return Vec::new(),
+        };
+
+        let timeout = self.sequences.iter()
+            .filter(|s| s.starts_with(&self.pending))
+            .map(|s| s.timeout)
+            .max()
+            .unwrap_or_default();
+
+        if now.duration_since(last_gesture_time) <= timeout { return Vec::new(); }
+
+        self.resolve()
+    }
+
+    fn is_pending_prefix(&self) -> bool {
+        self.sequences.iter().any(|s| s.starts_with(&self.pending))
+    }
+
+    fn complete_match(&self) -> Option<usize> {
+        self.sequences.iter().position(|s| s.gestures == self.pending)
+    }
+
+    /* Fires the sequence the pending gestures complete, if any, or flushes them */
+    fn resolve(&mut self) -> Vec<SequenceEvent> {
+        match self.complete_match() {
+            Some(index) => {
+                self.pending.clear();
+                self.last_gesture_time = None;
+                vec![SequenceEvent::Sequence(index)]
+            },
+            None => self.flush(),
+        }
+    }
+
+    /* Drops the pending gestures, returning them as singles if they were held back */
+    fn flush(&mut self) -> Vec<SequenceEvent> {
+        let events = match self.policy {
+            AmbiguityPolicy::WaitForSequence => self.pending.iter().map(|g| SequenceEvent::Single(*g)).collect(),
+            AmbiguityPolicy::FireImmediately => Vec::new(),
+        };
+
+        self.pending.clear();
+        self.last_gesture_time = None;
+
+        events
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gesture(s: &str) -> Gesture {
+        s.parse().unwrap()
+    }
+
+    fn matcher(policy: AmbiguityPolicy) -> SequenceMatcher {
+        let mut matcher = SequenceMatcher::new(policy);
+        matcher.add_sequence("swipe-up:3 then swipe-left:3 within 800ms".parse().unwrap());
+        matcher
+    }
+
+    #[test]
+    fn parses_sequence() {
+        let sequence: GestureSequence = "swipe-up:3 then swipe-left:3 within 500ms".parse().unwrap();
+        assert_eq!(sequence.gestures, vec![gesture("swipe-up:3"), gesture("swipe-left:3")]);
+        assert_eq!(sequence.timeout, Duration::from_millis(500));
+        assert_eq!(sequence.to_string(), "swipe-up:3 then swipe-left:3 within 500ms");
+
+        let sequence: GestureSequence = "pinch-in:2 then pinch-out:2".parse().unwrap();
+        assert_eq!(sequence.timeout, DEFAULT_SEQUENCE_TIMEOUT);
+
+        assert!("swipe-up:3".parse::<GestureSequence>().is_err());
+        assert!("swipe-up:3 then swipe-left:3 within soon".parse::<GestureSequence>().is_err());
+    }
+
+    #[test]
+    fn wait_policy_fires_only_the_sequence() {
+        let mut matcher = matcher(AmbiguityPolicy::WaitForSequence);
+        let start = Instant::now();
+
+        assert!(matcher.push(gesture("swipe-up:3"), start).is_empty());
+        assert_eq!(matcher.push(gesture("swipe-left:3"), start + Duration::from_millis(300)),
+                   vec![SequenceEvent::Sequence(0)]);
+    }
+
+    #[test]
+    fn wait_policy_flushes_on_timeout() {
+        let mut matcher = matcher(AmbiguityPolicy::WaitForSequence);
+        let start = Instant::now();
+
+        assert!(matcher.push(gesture("swipe-up:3"), start).is_empty());
+        assert!(matcher.poll(start + Duration::from_millis(500)).is_empty());
+        assert_eq!(matcher.poll(start + Duration::from_millis(900)),
+                   vec![SequenceEvent::Single(gesture("swipe-up:3"))]);
+
+        /* A late second stroke fires on its own */
+        assert_eq!(matcher.push(gesture("swipe-left:3"), start + Duration::from_millis(1000)),
+                   vec![SequenceEvent::Single(gesture("swipe-left:3"))]);
+    }
+
+    #[test]
+    fn wait_policy_flushes_on_mismatch() {
+        let mut matcher = matcher(AmbiguityPolicy::WaitForSequence);
+        let start = Instant::now();
+
+        assert!(matcher.push(gesture("swipe-up:3"), start).is_empty());
+        assert!(matcher.push(gesture("swipe-up:3"), start + Duration::from_millis(100)).len() == 1);
+        assert_eq!(matcher.push(gesture("swipe-left:3"), start + Duration::from_millis(200)),
+                   vec![SequenceEvent::Sequence(0)]);
+    }
+
+    #[test]
+    fn immediate_policy_fires_singles_and_sequence() {
+        let mut matcher = matcher(AmbiguityPolicy::FireImmediately);
+        let start = Instant::now();
+
+        assert_eq!(matcher.push(gesture("swipe-up:3"), start),
+                   vec![SequenceEvent::Single(gesture("swipe-up:3"))]);
+        assert_eq!(matcher.push(gesture("swipe-left:3"), start + Duration::from_millis(300)),
+                   vec![SequenceEvent::Single(gesture("swipe-left:3")), SequenceEvent::Sequence(0)]);
+        assert!(matcher.poll(start + Duration::from_secs(5)).is_empty());
+    }
+
+    #[test]
+    fn longer_sequence_waits_for_timeout() {
+        let mut matcher = matcher(AmbiguityPolicy::WaitForSequence);
+        matcher.add_sequence("swipe-up:3 then swipe-left:3 then swipe-down:3".parse().unwrap());
+        let start = Instant::now();
+
+        assert!(matcher.push(gesture("swipe-up:3"), start).is_empty());
+        assert!(matcher.push(gesture("swipe-left:3"), start + Duration::from_millis(100)).is_empty());
+        assert_eq!(matcher.poll(start + Duration::from_millis(1000)), vec![SequenceEvent::Sequence(0)]);
+    }
+
+    #[test]
+    fn broken_off_sequence_fires_what_it_completed() {
+        for &policy in &[AmbiguityPolicy::WaitForSequence, AmbiguityPolicy::FireImmediately] {
+            let mut matcher = matcher(policy);
+            matcher.add_sequence("swipe-up:3 then swipe-left:3 then swipe-down:3".parse().unwrap());
+            let start = Instant::now();
+
+            matcher.push(gesture("swipe-up:3"), start);
+            matcher.push(gesture("swipe-left:3"), start + Duration::from_millis(100));
+            assert_eq!(matcher.push(gesture("pinch-in:2"), start + Duration::from_millis(200)),
+                       vec![SequenceEvent::Sequence(0), SequenceEvent::Single(gesture("pinch-in:2"))]);
+            assert!(matcher.poll(start + Duration::from_millis(2000)).is_empty());
+        }
+    }
+}
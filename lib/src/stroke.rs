@@ -0,0 +1,293 @@
+/*
+ * Copyright © 2019 Romeo Calota
+ *
+ * This program is free software; you can redistribute it and/or
+ * modify it under the terms of the GNU Lesser General Public
+ * License as published by the Free Software Foundation; either
+ * version 2 of the licence, or (at your option) any later version.
+ *
+ * This software is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+ * Lesser General Public License for more details.
+ *
+ * You should have received a copy of the GNU Lesser General Public
+ * License along with this program; if not, see <http://www.gnu.org/licenses/>.
+ *
+ * Author: Romeo Calota
+ */
+
+use std::fmt;
+use std::str::FromStr;
+
+/* Pointer travel, in pixels, before holding the trigger button counts as a stroke instead of a click */
+const STROKE_THRESHOLD: f64 = 20.0;
+/* Pointer travel needed to register a direction within a stroke */
+const SEGMENT_LENGTH: f64 = 30.0;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum StrokeDirection
+{
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+/* Direction sequence of a mouse stroke, written as "DR" for down then right */
+#[derive(Debug, PartialEq, Clone)]
+pub struct Stroke
+{
+    pub directions: Vec<StrokeDirection>,
+}
+
+impl fmt::Display for Stroke {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for direction in &self.directions {
+            let letter = match direction {
+                StrokeDirection::Up => 'U',
+                StrokeDirection::Down => 'D',
+                StrokeDirection::Left => 'L',
+                StrokeDirection::Right => 'R',
+            };
+            write!(f, "{}", letter)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl FromStr for Stroke {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Stroke, String> {
+        let mut directions: Vec<StrokeDirection> = Vec::new();
+
+        for letter in s.trim().chars() {
+            let direction = match letter.to_ascii_uppercase() {
+                'U' => StrokeDirection::Up,
+                'D' => StrokeDirection::Down,
+                'L' => StrokeDirection::Left,
+                'R' => StrokeDirection::Right,
+                _ => return Err(format!("Unknown stroke direction '{}' in \"{}\"", letter, s)),
+            };
+
+            if directions.last() == Some(&direction) {
+                return Err(format!("Repeated stroke direction in \"{}\"", s));
+            }
+            directions.push(direction);
+        }
+
+        if directions.is_empty() {
+            return Err(String::from("Empty stroke"));
+        }
+
+        Ok(Stroke { directions })
+    }
+}
+
+/* Collects pointer motion while the trigger button is held */
+#[derive(Debug)]
+pub struct StrokeRecorder
+{
+    /* Pointer positions relative to where the button was pressed, in screen orientation */
+    points: Vec<(f64, f64)>,
+    x: f64,
+    y: f64,
+}
+
+impl StrokeRecorder {
+    pub fn new() -> StrokeRecorder {
+        StrokeRecorder { points: vec![(0.0, 0.0)], x: 0.0, y: 0.0 }
+    }
+
+    pub fn reset(&mut self) {
+        self.points.clear();
+        self.points.push((0.0, 0.0));
+        self.x = 0.0;
+        self.y = 0.0;
+    }
+
+    pub fn update(&mut self, dx: f64, dy: f64) {
+        self.x += dx;
+        self.y += dy;
+        self.points.push((self.x, self.y));
+    }
+
+    pub fn points(&self) -> &[(f64, f64)] {
+        &self.points
+    }
+
+    /* False if the pointer barely moved, in which case the button press was meant as a click */
+    pub fn is_stroke(&self) -> bool {
+        self.points.iter().any(|(x, y)| x.hypot(*y) >= STROKE_THRESHOLD)
+    }
+
+    /* Splits the recorded path into straight segments along the dominant axis */
+    pub fn directions(&self) -> Stroke {
+        let mut directions: Vec<StrokeDirection> = Vec::new();
+        let (mut anchor_x, mut anchor_y) = self.points[0];
+
+        for (x, y) in &self.points[1..] {
+            let (dx, dy) = (x - anchor_x, y - anchor_y);
+            if dx.hypot(dy) < SEGMENT_LENGTH { continue; }
+
+            let direction = if dx.abs() > dy.abs() {
+                if dx > 0.0 { StrokeDirection::Right } else { StrokeDirection::Left }
+            } else if dy > 0.0 {
+                StrokeDirection::Down
+            } else {
+                StrokeDirection::Up
+            };
+
+            if directions.last() != Some(&direction) {
+                directions.push(direction);
+            }
+
+            anchor_x = *x;
+            anchor_y = *y;
+        }
+
+        Stroke { directions }
+    }
+}
+
+impl Default for StrokeRecorder {
+    fn default() -> StrokeRecorder {
+        StrokeRecorder::new()
+    }
+}
+
+/* Follows strokes drawn on a touchscreen with a set number of fingers, the first one down leads */
+#[derive(Debug)]
+pub struct TouchStrokeTracker
+{
+    pub finger_count: u32,
+    /* Slots currently down, the lead first */
+    slots: Vec<i32>,
+    lead_position: (f64, f64),
+    active: bool,
+    /* Set once more fingers than finger_count touched, until all are lifted */
+    cancelled: bool,
+}
+
+impl TouchStrokeTracker {
+    pub fn new(finger_count: u32) -> TouchStrokeTracker {
+        TouchStrokeTracker { finger_count, slots: Vec::new(), lead_position: (0.0, 0.0), active: false, cancelled: false }
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.active
+    }
+
+    /* True when the stroke begins, i.e. the last of its fingers went down */
+    pub fn touch_down(&mut self, slot: i32, position: (f64, f64)) -> bool {
+        if self.slots.is_empty() { self.lead_position = position; }
+        self.slots.push(slot);
+
+        if self.slots.len() as u32 > self.finger_count {
+            self.active = false;
+            self.cancelled = true;
+        }
+        if self.active || self.cancelled || self.slots.len() as u32 != self.finger_count { return false; }
+
+        self.active = true;
+        true
+    }
+
+    /* Motion of the lead finger while the stroke is drawn */
+    pub fn touch_motion(&mut self, slot: i32, position: (f64, f64)) -> Option<(f64, f64)> {
+        if self.slots.first() != Some(&slot) { return None; }
+
+        let delta = (position.0 - self.lead_position.0, position.1 - self.lead_position.1);
+        self.lead_position = position;
+        if self.active { Some(delta) } else { None }
+    }
+
+    /* True when the stroke is complete, i.e. every finger was lifted */
+    pub fn touch_up(&mut self, slot: i32) -> bool {
+        self.slots.retain(|s| *s != slot);
+        if !self.slots.is_empty() { return false; }
+
+        let finished = self.active;
+        self.active = false;
+        self.cancelled = false;
+        finished
+    }
+
+    pub fn cancel(&mut self) {
+        self.slots.clear();
+        self.active = false;
+        self.cancelled = false;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(moves: &[(f64, f64)]) -> StrokeRecorder {
+        let mut recorder = StrokeRecorder::new();
+        for (dx, dy) in moves {
+            recorder.update(*dx, *dy);
+        }
+        recorder
+    }
+
+    #[test]
+    fn recognizes_l_shape() {
+        let mut moves = vec![(1.0, 8.0); 20];
+        moves.extend(vec![(9.0, -1.0); 20]);
+
+        let recorder = record(&moves);
+        assert!(recorder.is_stroke());
+        assert_eq!(recorder.directions(), "DR".parse().unwrap());
+        assert_eq!(recorder.directions().to_string(), "DR");
+    }
+
+    #[test]
+    fn small_movement_is_a_click() {
+        let recorder = record(&[(2.0, 1.0), (3.0, -2.0)]);
+        assert!(!recorder.is_stroke());
+        assert!(recorder.directions().directions.is_empty());
+    }
+
+    #[test]
+    fn rejects_invalid_strokes() {
+        assert!("".parse::<Stroke>().is_err());
+        assert!("UX".parse::<Stroke>().is_err());
+        assert!("UUL".parse::<Stroke>().is_err());
+        assert_eq!("ul".parse::<Stroke>().unwrap().directions, vec![StrokeDirection::Up, StrokeDirection::Left]);
+    }
+
+    #[test]
+    fn follows_the_lead_touch() {
+        let mut tracker = TouchStrokeTracker::new(2);
+        assert!(!tracker.touch_down(3, (10.0, 10.0)));
+        assert_eq!(tracker.touch_motion(3, (12.0, 10.0)), None);
+        assert!(tracker.touch_down(4, (30.0, 10.0)));
+
+        assert_eq!(tracker.touch_motion(4, (30.0, 20.0)), None);
+        assert_eq!(tracker.touch_motion(3, (12.0, 15.0)), Some((0.0, 5.0)));
+
+        assert!(!tracker.touch_up(3));
+        assert!(tracker.is_active());
+        assert!(tracker.touch_up(4));
+        assert!(!tracker.is_active());
+    }
+
+    #[test]
+    fn extra_fingers_cancel_the_stroke() {
+        let mut tracker = TouchStrokeTracker::new(1);
+        assert!(tracker.touch_down(0, (0.0, 0.0)));
+        assert!(!tracker.touch_down(1, (5.0, 5.0)));
+        assert_eq!(tracker.touch_motion(0, (5.0, 0.0)), None);
+
+        /* Lifting back to the finger count doesn't resume it */
+        assert!(!tracker.touch_up(1));
+        assert!(!tracker.is_active());
+        assert!(!tracker.touch_up(0));
+
+        assert!(tracker.touch_down(0, (0.0, 0.0)));
+    }
+}
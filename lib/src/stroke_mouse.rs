@@ -0,0 +1,218 @@
+/*
+ * Copyright © 2019 Romeo Calota
+ *
+ * This program is free software; you can redistribute it and/or
+ * modify it under the terms of the GNU Lesser General Public
+ * License as published by the Free Software Foundation; either
+ * version 2 of the licence, or (at your option) any later version.
+ *
+ * This software is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+ * Lesser General Public License for more details.
+ *
+ * You should have received a copy of the GNU Lesser General Public
+ * License along with this program; if not, see <http://www.gnu.org/licenses/>.
+ *
+ * Author: Romeo Calota
+ */
+
+
+use virtualinput::codes::*;
+use virtualinput::{Button, EvdevDevice, InputEvent, UinputClone};
+
+/* What a stroke mouse reports to the backend */
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum StrokeMouseEvent
+{
+    /* The trigger button went down */
+    Begin,
+    /* Relative motion while it is held, kept from the desktop */
+    Motion(f64, f64),
+    /* The trigger button came back up */
+    End,
+}
+
+/* Splits evdev frames of a mouse into stroke events and what is passed through unchanged */
+#[derive(Debug)]
+pub struct StrokeFilter
+{
+    button: Button,
+    holding: bool,
+    /* State of the frame being read */
+    began: bool,
+    ended: bool,
+    motion: Option<(f64, f64)>,
+    passthrough: Vec<InputEvent>,
+}
+
+impl StrokeFilter {
+    pub fn new(button: Button) -> StrokeFilter {
+        StrokeFilter { button, holding: false, began: false, ended: false, motion: None, passthrough: Vec::new() }
+    }
+
+    /* Returns the events to pass through and the stroke events once a frame is complete */
+    pub fn push(&mut self, event: InputEvent) -> Option<(Vec<InputEvent>, Vec<StrokeMouseEvent>)> {
+        match (event.event_type, event.code) {
+            (EV_SYN, SYN_DROPPED) => {
+                /* What was read of this frame is incomplete, libevdev follows up with the current state */
+                self.passthrough.clear();
+                self.motion = None;
+            },
+            (EV_SYN, SYN_REPORT) => return Some(self.finish_frame()),
+            (EV_KEY, code) if code == self.button as u32 => {
+                /* Key repeat, value 2, is dropped as well */
+                if event.value == 1 && !self.holding {
+                    self.holding = true;
+                    self.began = true;
+                } else if event.value == 0 && self.holding {
+                    self.holding = false;
+                    self.ended = true;
+                }
+            },
+            (EV_REL, REL_X) | (EV_REL, REL_Y) if self.holding || self.ended => {
+                let (dx, dy) = self.motion.unwrap_or((0.0, 0.0));
+                self.motion = Some(if event.code == REL_X { (dx + event.value as f64, dy) } else { (dx, dy + event.value as f64) });
+            },
+            _ => self.passthrough.push(event),
+        }
+
+        None
+    }
+
+    fn finish_frame(&mut self) -> (Vec<InputEvent>, Vec<StrokeMouseEvent>) {
+        let mut events = Vec::new();
+        if self.began { events.push(StrokeMouseEvent::Begin); }
+        if let Some((dx, dy)) = self.motion.take() { events.push(StrokeMouseEvent::Motion(dx, dy)); }
+        if self.ended { events.push(StrokeMouseEvent::End); }
+        self.began = false;
+        self.ended = false;
+
+        let mut passthrough = std::mem::take(&mut self.passthrough);
+        if !passthrough.is_empty() {
+            passthrough.push(InputEvent::new(EV_SYN, SYN_REPORT, 0));
+        }
+
+        (passthrough, events)
+    }
+}
+
+/*
+ * A mouse grabbed so the stroke button, and the motion while it is held, can be kept from the
+ * desktop. Everything else is re-emitted unchanged through a uinput copy of the mouse, so side
+ * buttons, high resolution scrolling and the like keep working.
+ */
+pub struct StrokeMouse
+{
+    device: EvdevDevice,
+    passthrough: UinputClone,
+    filter: StrokeFilter,
+    button: Button,
+}
+
+impl StrokeMouse {
+    /* None for devices that aren't mice with the button, or that can't be read */
+    pub fn open(path: &str, button: Button) -> Result<Option<StrokeMouse>, String> {
+        let mut device = match EvdevDevice::open(path) {
+            Ok(device) => device,
+            Err(_) => return Ok(None),
+        };
+        if !device.has_event_code(EV_REL, REL_X) || !device.has_event_code(EV_KEY, button as u32) {
+            return Ok(None);
+        }
+
+        let passthrough = device.create_clone()?;
+        device.grab(true)?;
+
+        Ok(Some(StrokeMouse { device, passthrough, filter: StrokeFilter::new(button), button }))
+    }
+
+    pub fn path(&self) -> &str {
+        self.device.path()
+    }
+
+    /* Where the copy shows up, it must not be taken for a mouse itself */
+    pub fn passthrough_devnode(&self) -> Option<String> {
+        self.passthrough.devnode()
+    }
+
+    /* Reads everything pending, passing through what isn't part of a stroke */
+    pub fn read_events(&mut self) -> Result<Vec<StrokeMouseEvent>, String> {
+        let mut stroke_events = Vec::new();
+        while let Some(event) = self.device.next_event()? {
+            if let Some((passthrough, events)) = self.filter.push(event) {
+                if !passthrough.is_empty() {
+                    self.passthrough.write(&passthrough)?;
+                }
+                stroke_events.extend(events);
+            }
+        }
+
+        Ok(stroke_events)
+    }
+
+    /* The trigger button was only clicked, replay it since the original never made it past the grab */
+    pub fn replay_click(&mut self) -> Result<(), String> {
+        let code = self.button as u32;
+        self.passthrough.write(&[
+            InputEvent::new(EV_KEY, code, 1),
+            InputEvent::new(EV_SYN, SYN_REPORT, 0),
+            InputEvent::new(EV_KEY, code, 0),
+            InputEvent::new(EV_SYN, SYN_REPORT, 0),
+        ])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(filter: &mut StrokeFilter, events: &[(u32, u32, i32)]) -> (Vec<InputEvent>, Vec<StrokeMouseEvent>) {
+        for (event_type, code, value) in events {
+            assert_eq!(filter.push(InputEvent::new(*event_type, *code, *value)), None);
+        }
+        filter.push(InputEvent::new(EV_SYN, SYN_REPORT, 0)).unwrap()
+    }
+
+    #[test]
+    fn keeps_the_stroke_from_the_desktop() {
+        let mut filter = StrokeFilter::new(Button::Right);
+        let syn = InputEvent::new(EV_SYN, SYN_REPORT, 0);
+
+        /* Everything passes through until the trigger button goes down */
+        let (passthrough, events) = frame(&mut filter, &[(EV_REL, REL_X, 3), (EV_KEY, Button::Side as u32, 1)]);
+        assert_eq!(passthrough, vec![InputEvent::new(EV_REL, REL_X, 3), InputEvent::new(EV_KEY, Button::Side as u32, 1), syn]);
+        assert!(events.is_empty());
+
+        let (passthrough, events) = frame(&mut filter, &[(EV_KEY, Button::Right as u32, 1), (EV_REL, REL_Y, 5)]);
+        assert!(passthrough.is_empty());
+        assert_eq!(events, vec![StrokeMouseEvent::Begin, StrokeMouseEvent::Motion(0.0, 5.0)]);
+
+        /* Other input while drawing still reaches the desktop */
+        let (passthrough, events) = frame(&mut filter, &[(EV_REL, REL_X, -4), (EV_REL, REL_Y, 2), (EV_KEY, Button::Right as u32, 2), (EV_REL, 11, 120)]);
+        assert_eq!(passthrough, vec![InputEvent::new(EV_REL, 11, 120), syn]);
+        assert_eq!(events, vec![StrokeMouseEvent::Motion(-4.0, 2.0)]);
+
+        let (passthrough, events) = frame(&mut filter, &[(EV_REL, REL_X, 1), (EV_KEY, Button::Right as u32, 0)]);
+        assert!(passthrough.is_empty());
+        assert_eq!(events, vec![StrokeMouseEvent::Motion(1.0, 0.0), StrokeMouseEvent::End]);
+
+        let (passthrough, events) = frame(&mut filter, &[(EV_REL, REL_X, 1)]);
+        assert_eq!(passthrough, vec![InputEvent::new(EV_REL, REL_X, 1), syn]);
+        assert!(events.is_empty());
+    }
+
+    #[test]
+    fn discards_frames_cut_short_by_dropped_events() {
+        let mut filter = StrokeFilter::new(Button::Middle);
+        frame(&mut filter, &[(EV_KEY, Button::Middle as u32, 1)]);
+
+        /* libevdev reports the release it resynchronized after the drop */
+        assert_eq!(filter.push(InputEvent::new(EV_REL, REL_X, 7)), None);
+        assert_eq!(filter.push(InputEvent::new(EV_KEY, Button::Left as u32, 1)), None);
+        assert_eq!(filter.push(InputEvent::new(EV_SYN, SYN_DROPPED, 0)), None);
+        let (passthrough, events) = frame(&mut filter, &[(EV_KEY, Button::Middle as u32, 0)]);
+        assert!(passthrough.is_empty());
+        assert_eq!(events, vec![StrokeMouseEvent::End]);
+    }
+}
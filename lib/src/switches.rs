@@ -0,0 +1,79 @@
+/*
+ * Copyright © 2019 Romeo Calota
+ *
+ * This program is free software; you can redistribute it and/or
+ * modify it under the terms of the GNU Lesser General Public
+ * License as published by the Free Software Foundation; either
+ * version 2 of the licence, or (at your option) any later version.
+ *
+ * This software is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+ * Lesser General Public License for more details.
+ *
+ * You should have received a copy of the GNU Lesser General Public
+ * License along with this program; if not, see <http://www.gnu.org/licenses/>.
+ *
+ * Author: Romeo Calota
+ */
+
+use std::fmt;
+use std::str::FromStr;
+
+/* Values match enum libinput_switch */
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Switch
+{
+    Lid = 1,
+    TabletMode = 2,
+}
+
+/* A switch transition, written as "lid-close", "lid-open", "tablet-mode-enter" or "tablet-mode-exit" */
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct SwitchTrigger
+{
+    pub switch: Switch,
+    /* True when the lid closes or tablet mode is entered */
+    pub on: bool,
+}
+
+impl SwitchTrigger {
+    pub fn from_libinput(switch: u32, state: u32) -> Option<SwitchTrigger> {
+        let switch = match switch {
+            1 => Switch::Lid,
+            2 => Switch::TabletMode,
+            _ => return None,
+        };
+
+        Some(SwitchTrigger { switch, on: state != 0 })
+    }
+}
+
+impl fmt::Display for SwitchTrigger {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match (self.switch, self.on) {
+            (Switch::Lid, true) => "lid-close",
+            (Switch::Lid, false) => "lid-open",
+            (Switch::TabletMode, true) => "tablet-mode-enter",
+            (Switch::TabletMode, false) => "tablet-mode-exit",
+        };
+
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for SwitchTrigger {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<SwitchTrigger, String> {
+        let (switch, on) = match s.trim() {
+            "lid-close" => (Switch::Lid, true),
+            "lid-open" => (Switch::Lid, false),
+            "tablet-mode-enter" => (Switch::TabletMode, true),
+            "tablet-mode-exit" => (Switch::TabletMode, false),
+            _ => return Err(format!("Unknown switch transition \"{}\"", s)),
+        };
+
+        Ok(SwitchTrigger { switch, on })
+    }
+}
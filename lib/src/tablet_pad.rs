@@ -0,0 +1,202 @@
+/*
+ * Copyright © 2019 Romeo Calota
+ *
+ * This program is free software; you can redistribute it and/or
+ * modify it under the terms of the GNU Lesser General Public
+ * License as published by the Free Software Foundation; either
+ * version 2 of the licence, or (at your option) any later version.
+ *
+ * This software is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+ * Lesser General Public License for more details.
+ *
+ * You should have received a copy of the GNU Lesser General Public
+ * License along with this program; if not, see <http://www.gnu.org/licenses/>.
+ *
+ * Author: Romeo Calota
+ */
+
+use std::fmt;
+use std::str::FromStr;
+
+use crate::action::Action;
+
+/* Degrees of ring rotation needed for one step */
+const RING_STEP: f64 = 15.0;
+/* Fraction of the strip length needed for one step */
+const STRIP_STEP: f64 = 0.1;
+
+/* A control on a tablet pad, written as "pad-button:2", "pad-ring-cw:0" or "pad-strip-down:1" */
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum PadTrigger
+{
+    Button(u32),
+    RingClockwise(u32),
+    RingCounterClockwise(u32),
+    StripUp(u32),
+    StripDown(u32),
+}
+
+impl fmt::Display for PadTrigger {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PadTrigger::Button(number) => write!(f, "pad-button:{}", number),
+            PadTrigger::RingClockwise(number) => write!(f, "pad-ring-cw:{}", number),
+            PadTrigger::RingCounterClockwise(number) => write!(f, "pad-ring-ccw:{}", number),
+            PadTrigger::StripUp(number) => write!(f, "pad-strip-up:{}", number),
+            PadTrigger::StripDown(number) => write!(f, "pad-strip-down:{}", number),
+        }
+    }
+}
+
+impl FromStr for PadTrigger {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<PadTrigger, String> {
+        let mut parts = s.trim().splitn(2, ':');
+        let name = parts.next().unwrap_or("");
+        let number = parts.next()
+            .and_then(|number| number.parse::<u32>().ok())
+            .ok_or_else(|| format!("Missing or invalid number in pad control \"{}\"", s))?;
+
+        match name {
+            "pad-button" => Ok(PadTrigger::Button(number)),
+            "pad-ring-cw" => Ok(PadTrigger::RingClockwise(number)),
+            "pad-ring-ccw" => Ok(PadTrigger::RingCounterClockwise(number)),
+            "pad-strip-up" => Ok(PadTrigger::StripUp(number)),
+            "pad-strip-down" => Ok(PadTrigger::StripDown(number)),
+            _ => Err(format!("Unknown pad control \"{}\"", name)),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct PadBinding
+{
+    pub trigger: PadTrigger,
+    /* Index of the mode group the control has to belong to, None for any group */
+    pub group: Option<u32>,
+    /* The mode of the control's mode group this binding is active in, None for all modes */
+    pub mode: Option<u32>,
+    pub action: Action,
+}
+
+impl PadBinding {
+    /* group and mode are those of the mode group the control belongs to */
+    pub fn matches(&self, trigger: PadTrigger, group: u32, mode: u32) -> bool {
+        self.trigger == trigger && (self.group.is_none() || self.group == Some(group))
+            && (self.mode.is_none() || self.mode == Some(mode))
+    }
+}
+
+/* Converts absolute ring and strip positions into steps in either direction */
+#[derive(Debug)]
+pub struct PadTracker
+{
+    /* Last known position and accumulated movement, per ring and strip number */
+    rings: Vec<(u32, f64, f64)>,
+    strips: Vec<(u32, f64, f64)>,
+}
+
+impl PadTracker {
+    pub fn new() -> PadTracker {
+        PadTracker { rings: Vec::new(), strips: Vec::new() }
+    }
+
+    /* position is in degrees, clockwise from the top, or -1 when the finger is lifted */
+    pub fn ring_update(&mut self, number: u32, position: f64) -> Vec<PadTrigger> {
+        let steps = PadTracker::update(&mut self.rings, number, position, RING_STEP, |delta| {
+            /* Take the short way around when crossing the top of the ring */
+            if delta > 180.0 { delta - 360.0 } else if delta < -180.0 { delta + 360.0 } else { delta }
+        });
+
+        let trigger = if steps > 0 { PadTrigger::RingClockwise(number) } else { PadTrigger::RingCounterClockwise(number) };
+        vec![trigger; steps.unsigned_abs() as usize]
+    }
+
+    /* position is normalized from 0.0 at the top to 1.0 at the bottom, or -1 when the finger is lifted */
+    pub fn strip_update(&mut self, number: u32, position: f64) -> Vec<PadTrigger> {
+        let steps = PadTracker::update(&mut self.strips, number, position, STRIP_STEP, |delta| delta);
+
+        let trigger = if steps > 0 { PadTrigger::StripDown(number) } else { PadTrigger::StripUp(number) };
+        vec![trigger; steps.unsigned_abs() as usize]
+    }
+
+    fn update<F: Fn(f64) -> f64>(axes: &mut Vec<(u32, f64, f64)>, number: u32, position: f64, step: f64, normalize: F) -> i32 {
+        let index = match axes.iter().position(|(n, _, _)| *n == number) {
+            Some(index) => index,
+            None => {
+                axes.push((number, -1.0, 0.0));
+                axes.len() - 1
+            }
+        };
+
+        let (_, last_position, accumulated) = &mut axes[index];
+
+        if position < 0.0 {
+            *last_position = -1.0;
+            *accumulated = 0.0;
+            return 0;
+        }
+
+        if *last_position < 0.0 {
+            *last_position = position;
+            return 0;
+        }
+
+        *accumulated += normalize(position - *last_position);
+        *last_position = position;
+
+        let steps = (*accumulated / step).trunc();
+        *accumulated -= steps * step;
+
+        steps as i32
+    }
+}
+
+impl Default for PadTracker {
+    fn default() -> PadTracker {
+        PadTracker::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ring_wraps_around_the_top() {
+        let mut tracker = PadTracker::new();
+        assert!(tracker.ring_update(0, 350.0).is_empty());
+        assert_eq!(tracker.ring_update(0, 20.0), vec![PadTrigger::RingClockwise(0), PadTrigger::RingClockwise(0)]);
+        assert_eq!(tracker.ring_update(0, 335.0), vec![PadTrigger::RingCounterClockwise(0); 3]);
+
+        /* Lifting the finger forgets the position */
+        assert!(tracker.ring_update(0, -1.0).is_empty());
+        assert!(tracker.ring_update(0, 90.0).is_empty());
+    }
+
+    #[test]
+    fn strip_reports_direction() {
+        let mut tracker = PadTracker::new();
+        assert!(tracker.strip_update(1, 0.5).is_empty());
+        assert_eq!(tracker.strip_update(1, 0.25), vec![PadTrigger::StripUp(1), PadTrigger::StripUp(1)]);
+        assert!(tracker.strip_update(1, 0.3).is_empty());
+        assert_eq!(tracker.strip_update(1, 0.4), vec![PadTrigger::StripDown(1)]);
+    }
+
+    #[test]
+    fn binding_respects_mode() {
+        let mut binding = PadBinding { trigger: "pad-button:3".parse().unwrap(), group: None, mode: Some(1), action: Action::Command(String::from("true")) };
+        assert!(binding.matches(PadTrigger::Button(3), 0, 1));
+        assert!(binding.matches(PadTrigger::Button(3), 1, 1));
+        assert!(!binding.matches(PadTrigger::Button(3), 0, 0));
+        assert!(!binding.matches(PadTrigger::Button(2), 0, 1));
+
+        /* Pads with several mode groups switch their modes separately */
+        binding.group = Some(1);
+        assert!(binding.matches(PadTrigger::Button(3), 1, 1));
+        assert!(!binding.matches(PadTrigger::Button(3), 0, 1));
+    }
+}
@@ -0,0 +1,208 @@
+/*
+ * Copyright © 2019 Romeo Calota
+ *
+ * This program is free software; you can redistribute it and/or
+ * modify it under the terms of the GNU Lesser General Public
+ * License as published by the Free Software Foundation; either
+ * version 2 of the licence, or (at your option) any later version.
+ *
+ * This software is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+ * Lesser General Public License for more details.
+ *
+ * You should have received a copy of the GNU Lesser General Public
+ * License along with this program; if not, see <http://www.gnu.org/licenses/>.
+ *
+ * Author: Romeo Calota
+ */
+
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+
+use crate::multitouch::TouchFrame;
+
+/* Longest a touch may last to count as a tap */
+const MAX_TAP_DURATION: Duration = Duration::from_millis(250);
+/* Longest time between the end of a tap and the end of the next one for a double tap */
+const DOUBLE_TAP_INTERVAL: Duration = Duration::from_millis(300);
+
+/* Written as "tap:4" or "double-tap:3" */
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Tap
+{
+    pub finger_count: u32,
+    pub double: bool,
+}
+
+impl fmt::Display for Tap {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}", if self.double { "double-tap" } else { "tap" }, self.finger_count)
+    }
+}
+
+impl FromStr for Tap {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Tap, String> {
+        let mut parts = s.trim().splitn(2, ':');
+        let double = match parts.next().unwrap_or("") {
+            "tap" => false,
+            "double-tap" => true,
+            name => return Err(format!("Unknown tap \"{}\"", name)),
+        };
+
+        let finger_count = parts.next()
+            .and_then(|count| count.parse::<u32>().ok())
+            .filter(|count| (1..=5).contains(count))
+            .ok_or_else(|| format!("Tap needs a finger count from 1 to 5: \"{}\"", s))?;
+
+        Ok(Tap { finger_count, double })
+    }
+}
+
+/* Recognizes short touches that stay in place, reporting single taps once a double tap can be ruled out.
+ * Times are event timestamps, so a late read of the device doesn't stretch a tap. */
+#[derive(Debug)]
+pub struct TapRecognizer
+{
+    /* How far, in device units, a finger may move during a tap */
+    max_movement: f64,
+
+    touch_start: Option<Duration>,
+    max_finger_count: u32,
+    start_positions: Vec<(i32, i32, i32)>,
+    moved: bool,
+
+    /* Finger count and end time of a tap that may still become a double tap */
+    pending: Option<(u32, Duration)>,
+}
+
+impl TapRecognizer {
+    pub fn new(max_movement: f64) -> TapRecognizer {
+        TapRecognizer {
+            max_movement,
+            touch_start: None,
+            max_finger_count: 0,
+            start_positions: Vec::new(),
+            moved: false,
+            pending: None,
+        }
+    }
+
+    pub fn update(&mut self, frame: &TouchFrame) -> Vec<Tap> {
+        let now = frame.time;
+        let mut taps = self.poll(now);
+
+        if frame.finger_count > 0 {
+            if self.touch_start.is_none() {
+                self.touch_start = Some(now);
+                self.max_finger_count = 0;
+                self.start_positions.clear();
+                self.moved = false;
+            }
+
+            self.max_finger_count = self.max_finger_count.max(frame.finger_count);
+            for touch in &frame.touches {
+                match self.start_positions.iter().find(|(id, _, _)| *id == touch.tracking_id) {
+                    Some((_, x, y)) => {
+                        let distance = ((touch.x - x) as f64).hypot((touch.y - y) as f64);
+                        if distance > self.max_movement { self.moved = true; }
+                    },
+                    None => self.start_positions.push((touch.tracking_id, touch.x, touch.y)),
+                }
+            }
+
+            return taps;
+        }
+
+        let touch_start = match self.touch_start.take() {
+            Some(start) => start,
+            None => return taps,
+        };
+
+        let is_tap = !self.moved && now.saturating_sub(touch_start) <= MAX_TAP_DURATION;
+        match (is_tap, self.pending) {
+            (true, Some((finger_count, _))) if finger_count == self.max_finger_count => {
+                self.pending = None;
+                taps.push(Tap { finger_count, double: true });
+            },
+            (true, _) => {
+                taps.extend(self.flush());
+                self.pending = Some((self.max_finger_count, now));
+            },
+            (false, _) => taps.extend(self.flush()),
+        }
+
+        taps
+    }
+
+    /* Report a tap once its double tap interval passed, call this periodically with monotonic_time() */
+    pub fn poll(&mut self, now: Duration) -> Vec<Tap> {
+        match self.pending {
+            Some((_, end)) if now.saturating_sub(end) > DOUBLE_TAP_INTERVAL => self.flush(),
+            _ => Vec::new(),
+        }
+    }
+
+    fn flush(&mut self) -> Vec<Tap> {
+        match self.pending.take() {
+            Some((finger_count, _)) => vec![Tap { finger_count, double: false }],
+            None => Vec::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::multitouch::Touch;
+
+    fn frame(finger_count: u32, x: i32, time: Duration) -> TouchFrame {
+        let touches = (0..finger_count.min(2)).map(|i| Touch { tracking_id: i as i32, x: x + 100 * i as i32, y: 100 }).collect();
+        TouchFrame { touches, finger_count, time }
+    }
+
+    fn tap(recognizer: &mut TapRecognizer, finger_count: u32, start: Duration, duration_ms: u64) -> Vec<Tap> {
+        let mut taps = recognizer.update(&frame(finger_count, 100, start));
+        taps.extend(recognizer.update(&frame(0, 0, start + Duration::from_millis(duration_ms))));
+        taps
+    }
+
+    #[test]
+    fn reports_four_finger_tap_after_interval() {
+        let mut recognizer = TapRecognizer::new(50.0);
+        let start = Duration::from_secs(100);
+
+        assert!(tap(&mut recognizer, 4, start, 100).is_empty());
+        assert!(recognizer.poll(start + Duration::from_millis(300)).is_empty());
+        assert_eq!(recognizer.poll(start + Duration::from_millis(500)), vec![Tap { finger_count: 4, double: false }]);
+    }
+
+    #[test]
+    fn reports_double_tap() {
+        let mut recognizer = TapRecognizer::new(50.0);
+        let start = Duration::from_secs(100);
+
+        assert!(tap(&mut recognizer, 3, start, 100).is_empty());
+        assert_eq!(tap(&mut recognizer, 3, start + Duration::from_millis(250), 80), vec!["double-tap:3".parse().unwrap()]);
+        assert!(recognizer.poll(start + Duration::from_secs(2)).is_empty());
+    }
+
+    #[test]
+    fn ignores_long_or_moving_touches() {
+        let mut recognizer = TapRecognizer::new(50.0);
+        let start = Duration::from_secs(100);
+
+        assert!(tap(&mut recognizer, 4, start, 400).is_empty());
+
+        recognizer.update(&frame(4, 100, start + Duration::from_secs(1)));
+        recognizer.update(&frame(4, 300, start + Duration::from_millis(1050)));
+        recognizer.update(&frame(0, 0, start + Duration::from_millis(1100)));
+        assert!(recognizer.poll(start + Duration::from_secs(3)).is_empty());
+
+        assert!("tap:6".parse::<Tap>().is_err());
+        assert_eq!("tap:5".parse::<Tap>().unwrap().to_string(), "tap:5");
+    }
+}
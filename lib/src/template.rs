@@ -0,0 +1,350 @@
+/*
+ * Copyright © 2019 Romeo Calota
+ *
+ * This program is free software; you can redistribute it and/or
+ * modify it under the terms of the GNU Lesser General Public
+ * License as published by the Free Software Foundation; either
+ * version 2 of the licence, or (at your option) any later version.
+ *
+ * This software is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+ * Lesser General Public License for more details.
+ *
+ * You should have received a copy of the GNU Lesser General Public
+ * License along with this program; if not, see <http://www.gnu.org/licenses/>.
+ *
+ * Author: Romeo Calota
+ */
+
+/*
+ * Template matching of drawn shapes, following the "$1 unistroke recognizer" by Wobbrock et al.
+ * Strokes are resampled, rotated so their first point lies at angle zero from the centroid, scaled
+ * to a square and centered, then compared point by point against each template.
+ */
+
+use std::f64::consts::PI;
+
+const SAMPLE_COUNT: usize = 64;
+const SQUARE_SIZE: f64 = 250.0;
+/* Range and precision of the search for the best matching rotation */
+const ANGLE_RANGE: f64 = PI / 4.0;
+const ANGLE_PRECISION: f64 = PI / 90.0;
+
+pub const DEFAULT_THRESHOLD: f64 = 0.8;
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct StrokeTemplate
+{
+    pub name: String,
+    /* Normalized points */
+    pub points: Vec<(f64, f64)>,
+}
+
+impl StrokeTemplate {
+    pub fn new(name: &str, points: &[(f64, f64)]) -> Result<StrokeTemplate, String> {
+        if !all_finite(points) {
+            return Err(format!("Shape \"{}\" has a point that isn't a finite number", name));
+        }
+        if path_length(points) <= 0.0 {
+            return Err(format!("Shape \"{}\" needs at least two distinct points", name));
+        }
+
+        Ok(StrokeTemplate { name: name.to_string(), points: normalize(points) })
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct TemplateMatch
+{
+    pub name: String,
+    /* From 0.0 for no resemblance to 1.0 for a perfect match */
+    pub score: f64,
+}
+
+#[derive(Debug)]
+pub struct TemplateRecognizer
+{
+    templates: Vec<StrokeTemplate>,
+    /* Matches scoring below this are rejected */
+    pub threshold: f64,
+}
+
+impl TemplateRecognizer {
+    /* Starts out with the built-in templates */
+    pub fn new() -> TemplateRecognizer {
+        TemplateRecognizer { templates: builtin_templates(), threshold: DEFAULT_THRESHOLD }
+    }
+
+    /* Replaces a template of the same name, so recorded shapes can override the built-in ones */
+    pub fn add_template(&mut self, template: StrokeTemplate) {
+        self.templates.retain(|t| t.name != template.name);
+        self.templates.push(template);
+    }
+
+    pub fn templates(&self) -> &[StrokeTemplate] {
+        &self.templates
+    }
+
+    /* The closest template, however poor the match */
+    pub fn best_match(&self, points: &[(f64, f64)]) -> Option<TemplateMatch> {
+        if !all_finite(points) || path_length(points) <= 0.0 { return None; }
+
+        let candidate = normalize(points);
+        let half_diagonal = 0.5 * (2.0 * SQUARE_SIZE * SQUARE_SIZE).sqrt();
+
+        self.templates.iter()
+            .map(|template| {
+                let distance = distance_at_best_angle(&candidate, &template.points);
+                TemplateMatch { name: template.name.clone(), score: 1.0 - distance / half_diagonal }
+            })
+            .filter(|m| !m.score.is_nan())
+            .max_by(|a, b| a.score.partial_cmp(&b.score).unwrap())
+    }
+
+    /* The closest template, if it scores at least the threshold */
+    pub fn recognize(&self, points: &[(f64, f64)]) -> Option<TemplateMatch> {
+        self.best_match(points).filter(|m| m.score >= self.threshold)
+    }
+}
+
+impl Default for TemplateRecognizer {
+    fn default() -> TemplateRecognizer {
+        TemplateRecognizer::new()
+    }
+}
+
+/* Written as "x,y x,y ..." in the configuration file */
+pub fn format_points(points: &[(f64, f64)]) -> String {
+    let points: Vec<String> = points.iter().map(|(x, y)| format!("{:.1},{:.1}", x, y)).collect();
+    points.join(" ")
+}
+
+pub fn parse_points(s: &str) -> Result<Vec<(f64, f64)>, String> {
+    s.split_whitespace()
+        .map(|point| {
+            let mut coordinates = point.splitn(2, ',').map(|c| c.parse::<f64>());
+            match (coordinates.next(), coordinates.next()) {
+                (Some(Ok(x)), Some(Ok(y))) if x.is_finite() && y.is_finite() => Ok((x, y)),
+                _ => Err(format!("Invalid point \"{}\"", point)),
+            }
+        })
+        .collect()
+}
+
+fn all_finite(points: &[(f64, f64)]) -> bool {
+    points.iter().all(|(x, y)| x.is_finite() && y.is_finite())
+}
+
+fn builtin_templates() -> Vec<StrokeTemplate> {
+    /* Screen coordinates, y grows downwards; the circle is drawn clockwise from the top */
+    let circle: Vec<(f64, f64)> = (0..=32)
+        .map(|i| {
+            let angle = -PI / 2.0 + 2.0 * PI * (i as f64) / 32.0;
+            (50.0 * angle.cos(), 50.0 * angle.sin())
+        })
+        .collect();
+
+    let shapes: Vec<(&str, Vec<(f64, f64)>)> = vec![
+        ("circle", circle),
+        ("checkmark", vec![(0.0, 60.0), (30.0, 90.0), (90.0, 0.0)]),
+        ("caret", vec![(0.0, 90.0), (45.0, 0.0), (90.0, 90.0)]),
+        ("triangle", vec![(45.0, 0.0), (0.0, 90.0), (90.0, 90.0), (45.0, 0.0)]),
+        ("rectangle", vec![(0.0, 0.0), (0.0, 60.0), (90.0, 60.0), (90.0, 0.0), (0.0, 0.0)]),
+        ("zigzag", vec![(0.0, 0.0), (30.0, 60.0), (60.0, 0.0), (90.0, 60.0)]),
+    ];
+
+    shapes.iter()
+        .map(|(name, corners)| StrokeTemplate::new(name, &densify(corners)).unwrap())
+        .collect()
+}
+
+/* Fills in straight lines between corners, so resampling sees the same shape a drawn stroke would */
+fn densify(corners: &[(f64, f64)]) -> Vec<(f64, f64)> {
+    let mut points = vec![corners[0]];
+    for pair in corners.windows(2) {
+        let ((x0, y0), (x1, y1)) = (pair[0], pair[1]);
+        for step in 1..=10 {
+            let t = step as f64 / 10.0;
+            points.push((x0 + (x1 - x0) * t, y0 + (y1 - y0) * t));
+        }
+    }
+    points
+}
+
+fn normalize(points: &[(f64, f64)]) -> Vec<(f64, f64)> {
+    let points = resample(points, SAMPLE_COUNT);
+
+    let (cx, cy) = centroid(&points);
+    let angle = (cy - points[0].1).atan2(cx - points[0].0);
+    let points = rotate_by(&points, -angle);
+
+    let points = scale_to_square(&points);
+    translate_to_origin(&points)
+}
+
+fn path_length(points: &[(f64, f64)]) -> f64 {
+    points.windows(2).map(|pair| distance(pair[0], pair[1])).sum()
+}
+
+fn distance(a: (f64, f64), b: (f64, f64)) -> f64 {
+    (b.0 - a.0).hypot(b.1 - a.1)
+}
+
+fn resample(points: &[(f64, f64)], count: usize) -> Vec<(f64, f64)> {
+    let interval = path_length(points) / (count - 1) as f64;
+    let mut accumulated = 0.0;
+    let mut resampled = vec![points[0]];
+
+    let mut previous = points[0];
+    let mut index = 1;
+    while index < points.len() {
+        let current = points[index];
+        let d = distance(previous, current);
+
+        if d > 0.0 && accumulated + d >= interval {
+            let t = (interval - accumulated) / d;
+            let point = (previous.0 + t * (current.0 - previous.0), previous.1 + t * (current.1 - previous.1));
+            resampled.push(point);
+
+            /* The new point starts the next interval */
+            previous = point;
+            accumulated = 0.0;
+        } else {
+            accumulated += d;
+            previous = current;
+            index += 1;
+        }
+    }
+
+    /* Rounding can leave the last point out */
+    while resampled.len() < count {
+        resampled.push(points[points.len() - 1]);
+    }
+    resampled.truncate(count);
+
+    resampled
+}
+
+fn centroid(points: &[(f64, f64)]) -> (f64, f64) {
+    let count = points.len() as f64;
+    let (sx, sy) = points.iter().fold((0.0, 0.0), |(sx, sy), (x, y)| (sx + x, sy + y));
+    (sx / count, sy / count)
+}
+
+fn rotate_by(points: &[(f64, f64)], angle: f64) -> Vec<(f64, f64)> {
+    let (cx, cy) = centroid(points);
+    let (sin, cos) = angle.sin_cos();
+
+    points.iter()
+        .map(|(x, y)| ((x - cx) * cos - (y - cy) * sin + cx, (x - cx) * sin + (y - cy) * cos + cy))
+        .collect()
+}
+
+fn scale_to_square(points: &[(f64, f64)]) -> Vec<(f64, f64)> {
+    let min_x = points.iter().map(|p| p.0).fold(f64::INFINITY, f64::min);
+    let max_x = points.iter().map(|p| p.0).fold(f64::NEG_INFINITY, f64::max);
+    let min_y = points.iter().map(|p| p.1).fold(f64::INFINITY, f64::min);
+    let max_y = points.iter().map(|p| p.1).fold(f64::NEG_INFINITY, f64::max);
+
+    /* Straight lines have no extent along one axis, leave that axis alone */
+    let width = (max_x - min_x).max(1.0);
+    let height = (max_y - min_y).max(1.0);
+
+    points.iter()
+        .map(|(x, y)| (x * SQUARE_SIZE / width, y * SQUARE_SIZE / height))
+        .collect()
+}
+
+fn translate_to_origin(points: &[(f64, f64)]) -> Vec<(f64, f64)> {
+    let (cx, cy) = centroid(points);
+    points.iter().map(|(x, y)| (x - cx, y - cy)).collect()
+}
+
+fn path_distance(a: &[(f64, f64)], b: &[(f64, f64)]) -> f64 {
+    let total: f64 = a.iter().zip(b.iter()).map(|(p, q)| distance(*p, *q)).sum();
+    total / a.len() as f64
+}
+
+/* Golden section search for the rotation of the candidate closest to the template */
+fn distance_at_best_angle(candidate: &[(f64, f64)], template: &[(f64, f64)]) -> f64 {
+    let phi = 0.5 * (-1.0 + 5.0_f64.sqrt());
+    let distance_at = |angle: f64| path_distance(&rotate_by(candidate, angle), template);
+
+    let (mut a, mut b) = (-ANGLE_RANGE, ANGLE_RANGE);
+    let mut x1 = phi * a + (1.0 - phi) * b;
+    let mut f1 = distance_at(x1);
+    let mut x2 = (1.0 - phi) * a + phi * b;
+    let mut f2 = distance_at(x2);
+
+    while (b - a).abs() > ANGLE_PRECISION {
+        if f1 < f2 {
+            b = x2;
+            x2 = x1;
+            f2 = f1;
+            x1 = phi * a + (1.0 - phi) * b;
+            f1 = distance_at(x1);
+        } else {
+            a = x1;
+            x1 = x2;
+            f1 = f2;
+            x2 = (1.0 - phi) * a + phi * b;
+            f2 = distance_at(x2);
+        }
+    }
+
+    f1.min(f2)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recognizes_builtin_shapes() {
+        let recognizer = TemplateRecognizer::new();
+
+        /* A slightly squashed, larger circle drawn clockwise from the top */
+        let circle: Vec<(f64, f64)> = (0..=40)
+            .map(|i| {
+                let angle = -PI / 2.0 + 2.0 * PI * (i as f64) / 40.0;
+                (300.0 + 120.0 * angle.cos(), 200.0 + 100.0 * angle.sin())
+            })
+            .collect();
+        let result = recognizer.recognize(&circle).unwrap();
+        assert_eq!(result.name, "circle");
+        assert!(result.score > 0.9);
+
+        let checkmark = densify(&[(100.0, 150.0), (140.0, 190.0), (230.0, 60.0)]);
+        assert_eq!(recognizer.recognize(&checkmark).unwrap().name, "checkmark");
+    }
+
+    #[test]
+    fn rejects_below_threshold() {
+        let mut recognizer = TemplateRecognizer::new();
+        let scribble = densify(&[(0.0, 0.0), (50.0, 10.0), (10.0, 40.0), (60.0, 60.0), (0.0, 90.0), (40.0, 20.0)]);
+
+        let best = recognizer.best_match(&scribble).unwrap();
+        recognizer.threshold = best.score + 0.01;
+        assert!(recognizer.recognize(&scribble).is_none());
+
+        assert!(recognizer.best_match(&[(1.0, 1.0)]).is_none());
+    }
+
+    #[test]
+    fn recorded_template_round_trips() {
+        let mut recognizer = TemplateRecognizer::new();
+        let drawn = densify(&[(0.0, 0.0), (80.0, 0.0), (0.0, 80.0), (80.0, 80.0)]);
+
+        let template = StrokeTemplate::new("z", &drawn).unwrap();
+        let stored = StrokeTemplate::new("z", &parse_points(&format_points(&template.points)).unwrap()).unwrap();
+        recognizer.add_template(stored);
+
+        assert_eq!(recognizer.recognize(&drawn).unwrap().name, "z");
+        assert!(parse_points("1,2 3").is_err());
+        assert!(parse_points("1,2 nan,3").is_err());
+        assert!(parse_points("1,2 3,inf").is_err());
+        assert!(StrokeTemplate::new("z", &[(0.0, 0.0), (f64::NAN, 1.0), (2.0, 2.0)]).is_err());
+        assert!(recognizer.best_match(&[(0.0, 0.0), (f64::NAN, 1.0), (2.0, 2.0)]).is_none());
+    }
+}
@@ -0,0 +1,166 @@
+/*
+ * Copyright © 2019 Romeo Calota
+ *
+ * This program is free software; you can redistribute it and/or
+ * modify it under the terms of the GNU Lesser General Public
+ * License as published by the Free Software Foundation; either
+ * version 2 of the licence, or (at your option) any later version.
+ *
+ * This software is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+ * Lesser General Public License for more details.
+ *
+ * You should have received a copy of the GNU Lesser General Public
+ * License along with this program; if not, see <http://www.gnu.org/licenses/>.
+ *
+ * Author: Romeo Calota
+ */
+
+use std::time::Duration;
+
+use crate::gesture::DirectionTransform;
+use crate::multitouch::TouchFrame;
+use crate::SwipeDirection;
+
+/*
+ * libinput turns two-finger movement into scrolling, so quick horizontal flicks are picked out of
+ * the raw touches instead. To leave scrolling alone, the flick has to cover a good part of the pad
+ * within a short time, stay mostly horizontal and have both fingers move the same way. The touchpad
+ * holds two-finger touches back from the desktop while is_pending, see RawTouchpad::read_frames.
+ */
+const MAX_DURATION: Duration = Duration::from_millis(300);
+/* Fraction of the pad width the fingers have to travel */
+const MIN_DISTANCE_RATIO: f64 = 0.2;
+/* Largest vertical travel allowed, relative to the horizontal travel */
+const MAX_SLOPE: f64 = 0.4;
+
+#[derive(Debug)]
+pub struct TwoFingerSwipeRecognizer
+{
+    min_distance: f64,
+
+    start_time: Option<Duration>,
+    /* Tracking id and starting position of both fingers */
+    start_positions: Vec<(i32, i32, i32)>,
+    /* Set once the touch has fired or can no longer be a swipe, until all fingers lift */
+    done: bool,
+}
+
+impl TwoFingerSwipeRecognizer {
+    /* pad_width is in device units */
+    pub fn new(pad_width: f64) -> TwoFingerSwipeRecognizer {
+        TwoFingerSwipeRecognizer {
+            min_distance: pad_width * MIN_DISTANCE_RATIO,
+            start_time: None,
+            start_positions: Vec::new(),
+            done: false,
+        }
+    }
+
+    /* True while two fingers are down and may still turn out to be a swipe */
+    pub fn is_pending(&self) -> bool {
+        self.start_time.is_some() && !self.done
+    }
+
+    /* Returns the direction once a swipe is recognized, at most once per touch */
+    pub fn update(&mut self, frame: &TouchFrame, transform: &DirectionTransform) -> Option<SwipeDirection> {
+        let now = frame.time;
+        if frame.finger_count == 0 {
+            self.start_time = None;
+            self.start_positions.clear();
+            self.done = false;
+            return None;
+        }
+
+        if self.done { return None; }
+
+        if frame.finger_count != 2 || frame.touches.len() != 2 {
+            /* Starting with one finger and adding the second is fine, anything else is not a swipe */
+            if frame.finger_count > 2 || self.start_time.is_some() { self.done = true; }
+            return None;
+        }
+
+        let start_time = match self.start_time {
+            Some(start_time) => start_time,
+            None => {
+                self.start_time = Some(now);
+                self.start_positions = frame.touches.iter().map(|t| (t.tracking_id, t.x, t.y)).collect();
+                return None;
+            }
+        };
+
+        if now.saturating_sub(start_time) > MAX_DURATION {
+            self.done = true;
+            return None;
+        }
+
+        let mut deltas = Vec::new();
+        for (tracking_id, x, y) in &self.start_positions {
+            match frame.touch(*tracking_id) {
+                Some(touch) => deltas.push(transform.apply((touch.x - x) as f64, (touch.y - y) as f64)),
+                None => {
+                    self.done = true;
+                    return None;
+                }
+            }
+        }
+
+        let (dx, dy) = ((deltas[0].0 + deltas[1].0) / 2.0, (deltas[0].1 + deltas[1].1) / 2.0);
+        if dx.abs() < self.min_distance { return None; }
+
+        let same_direction = deltas.iter().all(|(finger_dx, _)| finger_dx.signum() == dx.signum() && finger_dx.abs() >= self.min_distance / 2.0);
+        if dy.abs() > dx.abs() * MAX_SLOPE || !same_direction {
+            self.done = true;
+            return None;
+        }
+
+        self.done = true;
+        Some(if dx > 0.0 { SwipeDirection::SwipeRight } else { SwipeDirection::SwipeLeft })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::multitouch::Touch;
+
+    fn frame(positions: &[(i32, i32)], time: Duration) -> TouchFrame {
+        let touches = positions.iter().enumerate().map(|(i, (x, y))| Touch { tracking_id: i as i32, x: *x, y: *y }).collect();
+        TouchFrame { touches, finger_count: positions.len() as u32, time }
+    }
+
+    fn swipe(recognizer: &mut TwoFingerSwipeRecognizer, moves: &[(i32, i32)], step: Duration) -> Option<SwipeDirection> {
+        let start = Duration::from_secs(100);
+        let mut result = None;
+        for (index, (dx, dy)) in moves.iter().enumerate() {
+            let now = start + step * index as u32;
+            let recognized = recognizer.update(&frame(&[(1000 + dx, 1000 + dy), (1500 + dx, 1000 + dy)], now), &DirectionTransform::identity());
+            if index == 0 { assert!(recognizer.is_pending()); }
+            result = result.or(recognized);
+        }
+        recognizer.update(&frame(&[], start + Duration::from_secs(1)), &DirectionTransform::identity());
+        assert!(!recognizer.is_pending());
+        result
+    }
+
+    #[test]
+    fn recognizes_quick_horizontal_flick() {
+        let mut recognizer = TwoFingerSwipeRecognizer::new(4000.0);
+        let moves: Vec<(i32, i32)> = (0..8).map(|i| (-150 * i, 10 * i)).collect();
+        assert_eq!(swipe(&mut recognizer, &moves, Duration::from_millis(20)), Some(SwipeDirection::SwipeLeft));
+    }
+
+    #[test]
+    fn leaves_scrolling_alone() {
+        let mut recognizer = TwoFingerSwipeRecognizer::new(4000.0);
+
+        /* Slow horizontal scroll */
+        let moves: Vec<(i32, i32)> = (0..30).map(|i| (50 * i, 0)).collect();
+        assert_eq!(swipe(&mut recognizer, &moves, Duration::from_millis(20)), None);
+
+        /* Fast diagonal scroll */
+        let moves: Vec<(i32, i32)> = (0..8).map(|i| (150 * i, 100 * i)).collect();
+        assert_eq!(swipe(&mut recognizer, &moves, Duration::from_millis(20)), None);
+    }
+}
@@ -0,0 +1,114 @@
+/*
+ * Copyright © 2019 Romeo Calota
+ *
+ * This program is free software; you can redistribute it and/or
+ * modify it under the terms of the GNU Lesser General Public
+ * License as published by the Free Software Foundation; either
+ * version 2 of the licence, or (at your option) any later version.
+ *
+ * This software is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+ * Lesser General Public License for more details.
+ *
+ * You should have received a copy of the GNU Lesser General Public
+ * License along with this program; if not, see <http://www.gnu.org/licenses/>.
+ *
+ * Author: Romeo Calota
+ */
+
+/*
+ * End to end gesture recognition over a virtual touchpad. These need write access to /dev/uinput
+ * and read access to /dev/input, and are skipped with a note when either is missing.
+ */
+
+use std::cell::RefCell;
+use std::fs::OpenOptions;
+use std::rc::Rc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use kinesix::{KinesixBackend, PinchType, SwipeDirection};
+use virtualinput::VirtualTouchpad;
+
+type Recorded<T> = Rc<RefCell<Vec<(T, i32)>>>;
+
+/* How long udev and libinput get to catch up before a test gives up */
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+/* A virtual touchpad, or None when this environment can't create one */
+fn create_touchpad() -> Option<VirtualTouchpad> {
+    if let Err(e) = OpenOptions::new().write(true).open("/dev/uinput") {
+        eprintln!("Skipping, /dev/uinput is not writable: {}", e);
+        return None;
+    }
+
+    Some(VirtualTouchpad::new("Kinesix test touchpad").expect("Could not create the virtual touchpad"))
+}
+
+/* Keeps dispatching until done says so, or fails the test once the timeout is up */
+fn wait_for<Done: FnMut() -> bool>(backend: &mut KinesixBackend, what: &str, mut done: Done) {
+    let deadline = Instant::now() + TIMEOUT;
+    loop {
+        backend.dispatch_pending_events();
+        if done() { return; }
+
+        assert!(Instant::now() < deadline, "Timed out waiting for {}", what);
+        thread::sleep(Duration::from_millis(10));
+    }
+}
+
+fn backend_for(touchpad: &VirtualTouchpad) -> Option<(Box<KinesixBackend>, Recorded<SwipeDirection>, Recorded<PinchType>)> {
+    let swipes: Recorded<SwipeDirection> = Rc::new(RefCell::new(Vec::new()));
+    let pinches: Recorded<PinchType> = Rc::new(RefCell::new(Vec::new()));
+
+    let (swipes_, pinches_) = (swipes.clone(), pinches.clone());
+    let mut backend = Box::new(KinesixBackend::new(
+        move |direction, finger_count| swipes_.borrow_mut().push((direction, finger_count)),
+        move |pinch_type, finger_count| pinches_.borrow_mut().push((pinch_type, finger_count)),
+    ));
+
+    /* The node only becomes readable once udev has set its permissions */
+    let devnode = touchpad.devnode().expect("The virtual touchpad has no device node");
+    let deadline = Instant::now() + TIMEOUT;
+    let device = loop {
+        if let Some(device) = backend.get_valid_device_list().into_iter().find(|d| d.path == devnode) {
+            break device;
+        }
+        if Instant::now() >= deadline {
+            eprintln!("Skipping, {} did not show up as a gesture device", devnode);
+            return None;
+        }
+        thread::sleep(Duration::from_millis(50));
+    };
+    backend.set_active_device(&device);
+    backend.dispatch_pending_events();
+
+    Some((backend, swipes, pinches))
+}
+
+#[test]
+fn three_finger_swipe_fires_swipe_delegate() {
+    let mut touchpad = match create_touchpad() { Some(touchpad) => touchpad, None => return };
+    let (mut backend, swipes, pinches) = match backend_for(&touchpad) { Some(backend) => backend, None => return };
+
+    let (width, height) = touchpad.size();
+    touchpad.swipe(3, (width / 4, height / 2), (width / 2, 0), 20).unwrap();
+
+    wait_for(&mut backend, "the swipe", || !swipes.borrow().is_empty());
+    assert_eq!(*swipes.borrow(), vec![(SwipeDirection::SwipeRight, 3)]);
+    assert!(pinches.borrow().is_empty());
+}
+
+#[test]
+fn four_finger_pinch_fires_pinch_delegate() {
+    let mut touchpad = match create_touchpad() { Some(touchpad) => touchpad, None => return };
+    let (mut backend, swipes, pinches) = match backend_for(&touchpad) { Some(backend) => backend, None => return };
+
+    let (width, height) = touchpad.size();
+    touchpad.pinch(4, (width / 2, height / 2), height / 3, height / 10, 20).unwrap();
+
+    wait_for(&mut backend, "the pinch", || !pinches.borrow().is_empty());
+    assert_eq!(*pinches.borrow(), vec![(PinchType::PinchIn, 4)]);
+    assert!(swipes.borrow().is_empty());
+}
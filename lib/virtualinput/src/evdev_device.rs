@@ -0,0 +1,265 @@
+/*
+ * Copyright © 2019 Romeo Calota
+ *
+ * This program is free software; you can redistribute it and/or
+ * modify it under the terms of the GNU Lesser General Public
+ * License as published by the Free Software Foundation; either
+ * version 2 of the licence, or (at your option) any later version.
+ *
+ * This software is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+ * Lesser General Public License for more details.
+ *
+ * You should have received a copy of the GNU Lesser General Public
+ * License along with this program; if not, see <http://www.gnu.org/licenses/>.
+ *
+ * Author: Romeo Calota
+ */
+
+use crate::errno::*;
+use crate::evdev_uinput::*;
+use ::std::os::raw::*;
+use std::ffi::{CStr, CString};
+use std::time::Duration;
+
+const LIBEVDEV_UINPUT_OPEN_MANAGED: i32 = -2;
+
+const O_RDONLY: c_int = 0o0;
+const O_NONBLOCK: c_int = 0o4000;
+const EAGAIN: c_int = 11;
+const CLOCK_MONOTONIC: c_int = 1;
+
+extern "C"
+{
+    #[no_mangle]
+    fn open(path: *const c_char, flags: c_int, ...) -> c_int;
+
+    #[no_mangle]
+    fn close(fd: c_int) -> c_int;
+
+    #[no_mangle]
+    fn clock_gettime(clock_id: c_int, tp: *mut timespec) -> c_int;
+}
+
+/* The clock event timestamps are taken from */
+pub fn monotonic_time() -> Duration {
+    let mut now = timespec { tv_sec: 0, tv_nsec: 0 };
+    unsafe { clock_gettime(CLOCK_MONOTONIC, &mut now as *mut timespec); }
+
+    Duration::new(now.tv_sec as u64, now.tv_nsec as u32)
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct InputEvent
+{
+    pub event_type: u32,
+    pub code: u32,
+    pub value: i32,
+    /* When the kernel saw it, on the monotonic clock. Zero for events made up to be written */
+    pub time: Duration,
+}
+
+impl InputEvent {
+    pub fn new(event_type: u32, code: u32, value: i32) -> InputEvent {
+        InputEvent { event_type, code, value, time: Duration::from_secs(0) }
+    }
+}
+
+/* An evdev node read directly through libevdev, bypassing libinput */
+pub struct EvdevDevice
+{
+    fd: c_int,
+    evdev_dev: *mut libevdev,
+    path: String,
+    /* Set after a SYN_DROPPED, until the events describing the current state have been read */
+    syncing: bool,
+}
+
+impl EvdevDevice {
+    pub fn open(path: &str) -> Result<EvdevDevice, String> {
+        let path_cstr = CString::new(path).map_err(|e| e.to_string())?;
+
+        unsafe {
+            let fd = open(path_cstr.as_ptr(), O_RDONLY | O_NONBLOCK);
+            if fd < 0 {
+                return Err(format!("{}: {}", path, strerror(errno())));
+            }
+
+            let mut evdev_dev = 0 as *mut libevdev;
+            let err = libevdev_new_from_fd(fd, &mut evdev_dev as *mut *mut libevdev);
+            if err != 0 {
+                close(fd);
+                return Err(format!("{}: {}", path, strerror(-err)));
+            }
+
+            /* Event times are compared against monotonic_time() */
+            let err = libevdev_set_clock_id(evdev_dev, CLOCK_MONOTONIC);
+            if err != 0 {
+                libevdev_free(evdev_dev);
+                close(fd);
+                return Err(format!("{}: {}", path, strerror(-err)));
+            }
+
+            Ok(EvdevDevice { fd, evdev_dev, path: path.to_string(), syncing: false })
+        }
+    }
+
+    /* For polling, the device is opened non-blocking */
+    pub fn fd(&self) -> i32 {
+        self.fd
+    }
+
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    pub fn name(&self) -> String {
+        unsafe {
+            let name = libevdev_get_name(self.evdev_dev);
+            if name.is_null() { return String::new(); }
+
+            CStr::from_ptr(name).to_string_lossy().into_owned()
+        }
+    }
+
+    pub fn has_event_code(&self, event_type: u32, code: u32) -> bool {
+        unsafe { libevdev_has_event_code(self.evdev_dev, event_type, code) != 0 }
+    }
+
+    /* Minimum and maximum of an absolute axis, None if the device doesn't have it */
+    pub fn abs_range(&self, code: u32) -> Option<(i32, i32)> {
+        if !self.has_event_code(EV_ABS, code) { return None; }
+
+        unsafe {
+            Some((libevdev_get_abs_minimum(self.evdev_dev, code), libevdev_get_abs_maximum(self.evdev_dev, code)))
+        }
+    }
+
+    /* Units per millimeter of an absolute axis, 0 if the device doesn't say */
+    pub fn abs_resolution(&self, code: u32) -> i32 {
+        if !self.has_event_code(EV_ABS, code) { return 0; }
+
+        unsafe { libevdev_get_abs_resolution(self.evdev_dev, code) }
+    }
+
+    /* Number of multitouch slots, None for devices without ABS_MT_SLOT */
+    pub fn slot_count(&self) -> Option<u32> {
+        let count = unsafe { libevdev_get_num_slots(self.evdev_dev) };
+        if count < 0 { None } else { Some(count as u32) }
+    }
+
+    pub fn slot_value(&self, slot: u32, code: u32) -> i32 {
+        unsafe { libevdev_get_slot_value(self.evdev_dev, slot, code) }
+    }
+
+    pub fn current_slot(&self) -> u32 {
+        unsafe { libevdev_get_current_slot(self.evdev_dev).max(0) as u32 }
+    }
+
+    /* Current state of a key or axis, as far as read so far */
+    pub fn event_value(&self, event_type: u32, code: u32) -> i32 {
+        unsafe { libevdev_get_event_value(self.evdev_dev, event_type, code) }
+    }
+
+    /* The next pending event, None once there are no more to read */
+    pub fn next_event(&mut self) -> Result<Option<InputEvent>, String> {
+        let mut event = input_event {
+            time: timeval { tv_sec: 0, tv_usec: 0 },
+            type_: 0,
+            code: 0,
+            value: 0,
+        };
+
+        let flags = if self.syncing { libevdev_read_flag_LIBEVDEV_READ_FLAG_SYNC } else { libevdev_read_flag_LIBEVDEV_READ_FLAG_NORMAL };
+        let status = unsafe { libevdev_next_event(self.evdev_dev, flags, &mut event as *mut input_event) };
+
+        if status == -EAGAIN {
+            /* Done catching up after dropped events, carry on normally */
+            if self.syncing {
+                self.syncing = false;
+                return self.next_event();
+            }
+            return Ok(None);
+        }
+        if status < 0 {
+            return Err(format!("{}: {}", self.path, strerror(-status)));
+        }
+
+        if status as u32 == libevdev_read_status_LIBEVDEV_READ_STATUS_SYNC {
+            self.syncing = true;
+        }
+
+        let time = Duration::new(event.time.tv_sec as u64, event.time.tv_usec as u32 * 1000);
+        Ok(Some(InputEvent { time, ..InputEvent::new(event.type_ as u32, event.code as u32, event.value) }))
+    }
+
+    /* Take the device exclusively, nobody else receives its events while grabbed */
+    pub fn grab(&mut self, grab: bool) -> Result<(), String> {
+        let mode = if grab { libevdev_grab_mode_LIBEVDEV_GRAB } else { libevdev_grab_mode_LIBEVDEV_UNGRAB };
+        let err = unsafe { libevdev_grab(self.evdev_dev, mode) };
+        if err != 0 {
+            return Err(format!("{}: {}", self.path, strerror(-err)));
+        }
+
+        Ok(())
+    }
+
+    /* A uinput device with the same name, ids and capabilities, to re-emit events through */
+    pub fn create_clone(&self) -> Result<UinputClone, String> {
+        let mut uinput_dev = 0 as *mut libevdev_uinput;
+        let err = unsafe {
+            libevdev_uinput_create_from_device(self.evdev_dev, LIBEVDEV_UINPUT_OPEN_MANAGED, &mut uinput_dev as *mut *mut libevdev_uinput)
+        };
+        if err != 0 {
+            return Err(format!("{}: {}", self.path, strerror(-err)));
+        }
+
+        Ok(UinputClone { uinput_dev })
+    }
+}
+
+pub struct UinputClone
+{
+    uinput_dev: *mut libevdev_uinput,
+}
+
+impl UinputClone {
+    /* Events are written as given, include a SYN_REPORT to complete a frame */
+    pub fn write(&mut self, events: &[InputEvent]) -> Result<(), String> {
+        for event in events {
+            let err = unsafe { libevdev_uinput_write_event(self.uinput_dev, event.event_type, event.code, event.value) };
+            if err != 0 {
+                return Err(strerror(-err));
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn devnode(&self) -> Option<String> {
+        unsafe {
+            let devnode = libevdev_uinput_get_devnode(self.uinput_dev);
+            if devnode.is_null() { return None; }
+
+            Some(CStr::from_ptr(devnode).to_string_lossy().into_owned())
+        }
+    }
+}
+
+impl Drop for UinputClone {
+    fn drop(&mut self) {
+        unsafe {
+            libevdev_uinput_destroy(self.uinput_dev);
+        }
+    }
+}
+
+impl Drop for EvdevDevice {
+    fn drop(&mut self) {
+        unsafe {
+            libevdev_free(self.evdev_dev);
+            close(self.fd);
+        }
+    }
+}
@@ -0,0 +1,197 @@
+/*
+ * Copyright © 2019 Romeo Calota
+ *
+ * This program is free software; you can redistribute it and/or
+ * modify it under the terms of the GNU Lesser General Public
+ * License as published by the Free Software Foundation; either
+ * version 2 of the licence, or (at your option) any later version.
+ *
+ * This software is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+ * Lesser General Public License for more details.
+ *
+ * You should have received a copy of the GNU Lesser General Public
+ * License along with this program; if not, see <http://www.gnu.org/licenses/>.
+ *
+ * Author: Romeo Calota
+ */
+
+use crate::evdev_device::InputEvent;
+use crate::evdev_uinput::*;
+use crate::errno::*;
+use crate::uinput_sink::EventSink;
+use std::sync::{Arc, Mutex, MutexGuard, TryLockError, Weak};
+
+/* Every live VirtualInput, so their keys can still be released from a panic hook or signal handler */
+static HELD_INPUT: Mutex<Vec<Weak<Mutex<HeldInput>>>> = Mutex::new(Vec::new());
+
+/* Keys and buttons currently pressed on a uinput device */
+pub(crate) struct HeldInput
+{
+    /* Null once the device is gone */
+    pub(crate) uinput_dev: *mut libevdev_uinput,
+    pressed: Vec<u32>,
+}
+
+/* The device is only written to with the lock held, and cleared before it is destroyed */
+unsafe impl Send for HeldInput {}
+
+/* A panic while writing must not keep the keys from being released afterwards */
+pub(crate) fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/* None if the lock is taken, possibly by the thread that is panicking while holding it */
+fn try_lock<T>(mutex: &Mutex<T>) -> Option<MutexGuard<'_, T>> {
+    match mutex.try_lock() {
+        Ok(guard) => Some(guard),
+        Err(TryLockError::Poisoned(e)) => Some(e.into_inner()),
+        Err(TryLockError::WouldBlock) => None,
+    }
+}
+
+impl HeldInput {
+    pub(crate) fn new() -> HeldInput {
+        HeldInput { uinput_dev: 0 as *mut libevdev_uinput, pressed: Vec::new() }
+    }
+
+    fn write(&self, event: &InputEvent) -> Result<(), String> {
+        let err = unsafe { libevdev_uinput_write_event(self.uinput_dev, event.event_type, event.code, event.value) };
+        if err != 0 {
+            return Err(strerror(-err));
+        }
+
+        Ok(())
+    }
+
+    /* Writes events as given, keeping track of the keys and buttons among them */
+    pub(crate) fn write_events(&mut self, events: &[InputEvent]) -> Result<(), String> {
+        if self.uinput_dev.is_null() {
+            return Err(String::from("The virtual device is gone"));
+        }
+
+        /* Counted as pressed before writing, a failed press might still have reached the kernel */
+        self.track_presses(events);
+        for event in events {
+            self.write(event)?;
+        }
+        self.track_releases(events);
+
+        Ok(())
+    }
+
+    fn track_presses(&mut self, events: &[InputEvent]) {
+        for event in events.iter().filter(|e| e.event_type == EV_KEY && e.value != 0) {
+            if !self.pressed.contains(&event.code) {
+                self.pressed.push(event.code);
+            }
+        }
+    }
+
+    fn track_releases(&mut self, events: &[InputEvent]) {
+        for event in events.iter().filter(|e| e.event_type == EV_KEY && e.value == 0) {
+            self.pressed.retain(|c| *c != event.code);
+        }
+    }
+
+    /* Presses or releases a key or button in a frame of its own */
+    pub(crate) fn write_key(&mut self, code: u32, pressed: bool) -> Result<(), String> {
+        self.write_events(&[InputEvent::new(EV_KEY, code, pressed as i32), InputEvent::new(EV_SYN, SYN_REPORT, 0)])
+    }
+
+    /* Releases in the reverse order of pressing, so modifiers go last */
+    fn release_events(&mut self) -> Vec<InputEvent> {
+        let mut events: Vec<InputEvent> = self.pressed.drain(..).rev()
+            .map(|code| InputEvent::new(EV_KEY, code, 0))
+            .collect();
+        if !events.is_empty() {
+            events.push(InputEvent::new(EV_SYN, SYN_REPORT, 0));
+        }
+
+        events
+    }
+
+    /* Best effort, a failed write should not keep the rest pressed */
+    pub(crate) fn release(&mut self) {
+        let events = self.release_events();
+        if self.uinput_dev.is_null() { return; }
+
+        for event in &events {
+            let _ = self.write(event);
+        }
+    }
+}
+
+/* Lets a virtual device built on an EventSink write through the shared state, so what it presses is released too */
+pub(crate) struct HeldSink(pub(crate) Arc<Mutex<HeldInput>>);
+
+impl EventSink for HeldSink {
+    fn write(&mut self, events: &[InputEvent]) -> Result<(), String> {
+        lock(&self.0).write_events(events)
+    }
+}
+
+pub(crate) fn register(held: &Arc<Mutex<HeldInput>>) {
+    let mut devices = lock(&HELD_INPUT);
+    devices.retain(|device| device.strong_count() > 0);
+    devices.push(Arc::downgrade(held));
+}
+
+/* Releases every key and button held on any VirtualInput, for cleaning up when the process is going down;
+ * devices locked at the time are skipped rather than waited on, which could deadlock a panic hook */
+pub fn release_all_devices() {
+    let devices = match try_lock(&HELD_INPUT) {
+        Some(devices) => devices.clone(),
+        None => return,
+    };
+    for device in devices.iter().filter_map(|device| device.upgrade()) {
+        if let Some(mut held) = try_lock(&device) {
+            held.release();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn releases_in_reverse_order() {
+        let mut held = HeldInput::new();
+        held.pressed = vec![KEY_LEFTCTRL, KEY_LEFTSHIFT, BTN_LEFT];
+
+        assert_eq!(held.release_events(), vec![
+            InputEvent::new(EV_KEY, BTN_LEFT, 0),
+            InputEvent::new(EV_KEY, KEY_LEFTSHIFT, 0),
+            InputEvent::new(EV_KEY, KEY_LEFTCTRL, 0),
+            InputEvent::new(EV_SYN, SYN_REPORT, 0),
+        ]);
+        assert!(held.release_events().is_empty());
+    }
+
+    #[test]
+    fn releases_only_what_is_held() {
+        let mut held = HeldInput::new();
+        let key = |code, value| InputEvent::new(EV_KEY, code, value);
+        let syn = InputEvent::new(EV_SYN, SYN_REPORT, 0);
+
+        held.track_presses(&[key(KEY_LEFTMETA, 1), key(KEY_A, 1), syn]);
+        held.track_releases(&[key(KEY_LEFTMETA, 1), key(KEY_A, 1), syn]);
+        held.track_presses(&[key(KEY_A, 0), syn]);
+        held.track_releases(&[key(KEY_A, 0), syn]);
+        held.track_presses(&[InputEvent::new(EV_REL, REL_X, 1), syn]);
+
+        assert_eq!(held.release_events(), vec![key(KEY_LEFTMETA, 0), syn]);
+    }
+
+    #[test]
+    fn release_all_skips_locked_devices() {
+        let held = Arc::new(Mutex::new(HeldInput::new()));
+        register(&held);
+
+        let guard = lock(&held);
+        release_all_devices();
+        drop(guard);
+    }
+}
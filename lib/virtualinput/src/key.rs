@@ -0,0 +1,1086 @@
+/*
+ * Copyright © 2019 Romeo Calota
+ *
+ * This program is free software; you can redistribute it and/or
+ * modify it under the terms of the GNU Lesser General Public
+ * License as published by the Free Software Foundation; either
+ * version 2 of the licence, or (at your option) any later version.
+ *
+ * This software is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+ * Lesser General Public License for more details.
+ *
+ * You should have received a copy of the GNU Lesser General Public
+ * License along with this program; if not, see <http://www.gnu.org/licenses/>.
+ *
+ * Author: Romeo Calota
+ */
+
+use crate::evdev_uinput::*;
+use std::fmt;
+use std::str::FromStr;
+
+/* Every key of the Linux input event codes, named after the kernel's KEY_* constants */
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Key
+{
+    Escape = KEY_ESC as isize,
+    One = KEY_1 as isize,
+    Two = KEY_2 as isize,
+    Three = KEY_3 as isize,
+    Four = KEY_4 as isize,
+    Five = KEY_5 as isize,
+    Six = KEY_6 as isize,
+    Seven = KEY_7 as isize,
+    Eight = KEY_8 as isize,
+    Nine = KEY_9 as isize,
+    Zero = KEY_0 as isize,
+    Minus = KEY_MINUS as isize,
+    Equals = KEY_EQUAL as isize,
+    Backspace = KEY_BACKSPACE as isize,
+    Tab = KEY_TAB as isize,
+    Q = KEY_Q as isize,
+    W = KEY_W as isize,
+    E = KEY_E as isize,
+    R = KEY_R as isize,
+    T = KEY_T as isize,
+    Y = KEY_Y as isize,
+    U = KEY_U as isize,
+    I = KEY_I as isize,
+    O = KEY_O as isize,
+    P = KEY_P as isize,
+    LeftBrace = KEY_LEFTBRACE as isize,
+    RightBrace = KEY_RIGHTBRACE as isize,
+    Enter = KEY_ENTER as isize,
+    LeftControl = KEY_LEFTCTRL as isize,
+    A = KEY_A as isize,
+    S = KEY_S as isize,
+    D = KEY_D as isize,
+    F = KEY_F as isize,
+    G = KEY_G as isize,
+    H = KEY_H as isize,
+    J = KEY_J as isize,
+    K = KEY_K as isize,
+    L = KEY_L as isize,
+    Semicolon = KEY_SEMICOLON as isize,
+    Apostrophe = KEY_APOSTROPHE as isize,
+    Backquote = KEY_GRAVE as isize,
+    LeftShift = KEY_LEFTSHIFT as isize,
+    Backslash = KEY_BACKSLASH as isize,
+    Z = KEY_Z as isize,
+    X = KEY_X as isize,
+    C = KEY_C as isize,
+    V = KEY_V as isize,
+    B = KEY_B as isize,
+    N = KEY_N as isize,
+    M = KEY_M as isize,
+    Comma = KEY_COMMA as isize,
+    Period = KEY_DOT as isize,
+    Slash = KEY_SLASH as isize,
+    RightShift = KEY_RIGHTSHIFT as isize,
+    KpAsterisk = KEY_KPASTERISK as isize,
+    LeftAlt = KEY_LEFTALT as isize,
+    Space = KEY_SPACE as isize,
+    CapsLock = KEY_CAPSLOCK as isize,
+    F1 = KEY_F1 as isize,
+    F2 = KEY_F2 as isize,
+    F3 = KEY_F3 as isize,
+    F4 = KEY_F4 as isize,
+    F5 = KEY_F5 as isize,
+    F6 = KEY_F6 as isize,
+    F7 = KEY_F7 as isize,
+    F8 = KEY_F8 as isize,
+    F9 = KEY_F9 as isize,
+    F10 = KEY_F10 as isize,
+    NumLock = KEY_NUMLOCK as isize,
+    ScrollLock = KEY_SCROLLLOCK as isize,
+    Kp7 = KEY_KP7 as isize,
+    Kp8 = KEY_KP8 as isize,
+    Kp9 = KEY_KP9 as isize,
+    KpMinus = KEY_KPMINUS as isize,
+    Kp4 = KEY_KP4 as isize,
+    Kp5 = KEY_KP5 as isize,
+    Kp6 = KEY_KP6 as isize,
+    KpPlus = KEY_KPPLUS as isize,
+    Kp1 = KEY_KP1 as isize,
+    Kp2 = KEY_KP2 as isize,
+    Kp3 = KEY_KP3 as isize,
+    Kp0 = KEY_KP0 as isize,
+    KpDot = KEY_KPDOT as isize,
+    ZenkakuHankaku = KEY_ZENKAKUHANKAKU as isize,
+    Key102nd = KEY_102ND as isize,
+    F11 = KEY_F11 as isize,
+    F12 = KEY_F12 as isize,
+    Ro = KEY_RO as isize,
+    Katakana = KEY_KATAKANA as isize,
+    Hiragana = KEY_HIRAGANA as isize,
+    Henkan = KEY_HENKAN as isize,
+    KatakanaHiragana = KEY_KATAKANAHIRAGANA as isize,
+    Muhenkan = KEY_MUHENKAN as isize,
+    KpJpComma = KEY_KPJPCOMMA as isize,
+    KpEnter = KEY_KPENTER as isize,
+    RightControl = KEY_RIGHTCTRL as isize,
+    KpSlash = KEY_KPSLASH as isize,
+    SysRq = KEY_SYSRQ as isize,
+    RightAlt = KEY_RIGHTALT as isize,
+    LineFeed = KEY_LINEFEED as isize,
+    Home = KEY_HOME as isize,
+    UpArrow = KEY_UP as isize,
+    PageUp = KEY_PAGEUP as isize,
+    LeftArrow = KEY_LEFT as isize,
+    RightArrow = KEY_RIGHT as isize,
+    End = KEY_END as isize,
+    DownArrow = KEY_DOWN as isize,
+    PageDown = KEY_PAGEDOWN as isize,
+    Insert = KEY_INSERT as isize,
+    Delete = KEY_DELETE as isize,
+    Macro = KEY_MACRO as isize,
+    Mute = KEY_MUTE as isize,
+    VolumeDown = KEY_VOLUMEDOWN as isize,
+    VolumeUp = KEY_VOLUMEUP as isize,
+    Power = KEY_POWER as isize,
+    KpEqual = KEY_KPEQUAL as isize,
+    KpPlusMinus = KEY_KPPLUSMINUS as isize,
+    Pause = KEY_PAUSE as isize,
+    Scale = KEY_SCALE as isize,
+    KpComma = KEY_KPCOMMA as isize,
+    Hangeul = KEY_HANGEUL as isize,
+    Hanja = KEY_HANJA as isize,
+    Yen = KEY_YEN as isize,
+    LeftMeta = KEY_LEFTMETA as isize,
+    RightMeta = KEY_RIGHTMETA as isize,
+    Compose = KEY_COMPOSE as isize,
+    Stop = KEY_STOP as isize,
+    Again = KEY_AGAIN as isize,
+    Props = KEY_PROPS as isize,
+    Undo = KEY_UNDO as isize,
+    Front = KEY_FRONT as isize,
+    Copy = KEY_COPY as isize,
+    Open = KEY_OPEN as isize,
+    Paste = KEY_PASTE as isize,
+    Find = KEY_FIND as isize,
+    Cut = KEY_CUT as isize,
+    Help = KEY_HELP as isize,
+    Menu = KEY_MENU as isize,
+    Calc = KEY_CALC as isize,
+    Setup = KEY_SETUP as isize,
+    Sleep = KEY_SLEEP as isize,
+    WakeUp = KEY_WAKEUP as isize,
+    File = KEY_FILE as isize,
+    SendFile = KEY_SENDFILE as isize,
+    DeleteFile = KEY_DELETEFILE as isize,
+    Xfer = KEY_XFER as isize,
+    Prog1 = KEY_PROG1 as isize,
+    Prog2 = KEY_PROG2 as isize,
+    Www = KEY_WWW as isize,
+    Msdos = KEY_MSDOS as isize,
+    ScreenLock = KEY_SCREENLOCK as isize,
+    RotateDisplay = KEY_ROTATE_DISPLAY as isize,
+    CycleWindows = KEY_CYCLEWINDOWS as isize,
+    Mail = KEY_MAIL as isize,
+    Bookmarks = KEY_BOOKMARKS as isize,
+    Computer = KEY_COMPUTER as isize,
+    Back = KEY_BACK as isize,
+    Forward = KEY_FORWARD as isize,
+    CloseCd = KEY_CLOSECD as isize,
+    EjectCd = KEY_EJECTCD as isize,
+    EjectCloseCd = KEY_EJECTCLOSECD as isize,
+    NextSong = KEY_NEXTSONG as isize,
+    PlayPause = KEY_PLAYPAUSE as isize,
+    PreviousSong = KEY_PREVIOUSSONG as isize,
+    StopCd = KEY_STOPCD as isize,
+    Record = KEY_RECORD as isize,
+    Rewind = KEY_REWIND as isize,
+    Phone = KEY_PHONE as isize,
+    Iso = KEY_ISO as isize,
+    Config = KEY_CONFIG as isize,
+    HomePage = KEY_HOMEPAGE as isize,
+    Refresh = KEY_REFRESH as isize,
+    Exit = KEY_EXIT as isize,
+    Move = KEY_MOVE as isize,
+    Edit = KEY_EDIT as isize,
+    ScrollUp = KEY_SCROLLUP as isize,
+    ScrollDown = KEY_SCROLLDOWN as isize,
+    KpLeftParen = KEY_KPLEFTPAREN as isize,
+    KpRightParen = KEY_KPRIGHTPAREN as isize,
+    New = KEY_NEW as isize,
+    Redo = KEY_REDO as isize,
+    F13 = KEY_F13 as isize,
+    F14 = KEY_F14 as isize,
+    F15 = KEY_F15 as isize,
+    F16 = KEY_F16 as isize,
+    F17 = KEY_F17 as isize,
+    F18 = KEY_F18 as isize,
+    F19 = KEY_F19 as isize,
+    F20 = KEY_F20 as isize,
+    F21 = KEY_F21 as isize,
+    F22 = KEY_F22 as isize,
+    F23 = KEY_F23 as isize,
+    F24 = KEY_F24 as isize,
+    PlayCd = KEY_PLAYCD as isize,
+    PauseCd = KEY_PAUSECD as isize,
+    Prog3 = KEY_PROG3 as isize,
+    Prog4 = KEY_PROG4 as isize,
+    Dashboard = KEY_DASHBOARD as isize,
+    Suspend = KEY_SUSPEND as isize,
+    Close = KEY_CLOSE as isize,
+    Play = KEY_PLAY as isize,
+    FastForward = KEY_FASTFORWARD as isize,
+    BassBoost = KEY_BASSBOOST as isize,
+    Print = KEY_PRINT as isize,
+    Hp = KEY_HP as isize,
+    Camera = KEY_CAMERA as isize,
+    Sound = KEY_SOUND as isize,
+    Question = KEY_QUESTION as isize,
+    Email = KEY_EMAIL as isize,
+    Chat = KEY_CHAT as isize,
+    Search = KEY_SEARCH as isize,
+    Connect = KEY_CONNECT as isize,
+    Finance = KEY_FINANCE as isize,
+    Sport = KEY_SPORT as isize,
+    Shop = KEY_SHOP as isize,
+    AltErase = KEY_ALTERASE as isize,
+    Cancel = KEY_CANCEL as isize,
+    BrightnessDown = KEY_BRIGHTNESSDOWN as isize,
+    BrightnessUp = KEY_BRIGHTNESSUP as isize,
+    Media = KEY_MEDIA as isize,
+    SwitchVideoMode = KEY_SWITCHVIDEOMODE as isize,
+    KbdIllumToggle = KEY_KBDILLUMTOGGLE as isize,
+    KbdIllumDown = KEY_KBDILLUMDOWN as isize,
+    KbdIllumUp = KEY_KBDILLUMUP as isize,
+    Send = KEY_SEND as isize,
+    Reply = KEY_REPLY as isize,
+    ForwardMail = KEY_FORWARDMAIL as isize,
+    Save = KEY_SAVE as isize,
+    Documents = KEY_DOCUMENTS as isize,
+    Battery = KEY_BATTERY as isize,
+    Bluetooth = KEY_BLUETOOTH as isize,
+    Wlan = KEY_WLAN as isize,
+    Uwb = KEY_UWB as isize,
+    Unknown = KEY_UNKNOWN as isize,
+    VideoNext = KEY_VIDEO_NEXT as isize,
+    VideoPrev = KEY_VIDEO_PREV as isize,
+    BrightnessCycle = KEY_BRIGHTNESS_CYCLE as isize,
+    BrightnessAuto = KEY_BRIGHTNESS_AUTO as isize,
+    DisplayOff = KEY_DISPLAY_OFF as isize,
+    Wwan = KEY_WWAN as isize,
+    Rfkill = KEY_RFKILL as isize,
+    MicMute = KEY_MICMUTE as isize,
+    Ok = KEY_OK as isize,
+    Select = KEY_SELECT as isize,
+    Goto = KEY_GOTO as isize,
+    Clear = KEY_CLEAR as isize,
+    Power2 = KEY_POWER2 as isize,
+    Option = KEY_OPTION as isize,
+    Info = KEY_INFO as isize,
+    Time = KEY_TIME as isize,
+    Vendor = KEY_VENDOR as isize,
+    Archive = KEY_ARCHIVE as isize,
+    Program = KEY_PROGRAM as isize,
+    Channel = KEY_CHANNEL as isize,
+    Favorites = KEY_FAVORITES as isize,
+    Epg = KEY_EPG as isize,
+    Pvr = KEY_PVR as isize,
+    Mhp = KEY_MHP as isize,
+    Language = KEY_LANGUAGE as isize,
+    Title = KEY_TITLE as isize,
+    Subtitle = KEY_SUBTITLE as isize,
+    Angle = KEY_ANGLE as isize,
+    Zoom = KEY_ZOOM as isize,
+    Mode = KEY_MODE as isize,
+    Keyboard = KEY_KEYBOARD as isize,
+    Screen = KEY_SCREEN as isize,
+    Pc = KEY_PC as isize,
+    Tv = KEY_TV as isize,
+    Tv2 = KEY_TV2 as isize,
+    Vcr = KEY_VCR as isize,
+    Vcr2 = KEY_VCR2 as isize,
+    Sat = KEY_SAT as isize,
+    Sat2 = KEY_SAT2 as isize,
+    Cd = KEY_CD as isize,
+    Tape = KEY_TAPE as isize,
+    Radio = KEY_RADIO as isize,
+    Tuner = KEY_TUNER as isize,
+    Player = KEY_PLAYER as isize,
+    Text = KEY_TEXT as isize,
+    Dvd = KEY_DVD as isize,
+    Aux = KEY_AUX as isize,
+    Mp3 = KEY_MP3 as isize,
+    Audio = KEY_AUDIO as isize,
+    Video = KEY_VIDEO as isize,
+    Directory = KEY_DIRECTORY as isize,
+    List = KEY_LIST as isize,
+    Memo = KEY_MEMO as isize,
+    Calendar = KEY_CALENDAR as isize,
+    Red = KEY_RED as isize,
+    Green = KEY_GREEN as isize,
+    Yellow = KEY_YELLOW as isize,
+    Blue = KEY_BLUE as isize,
+    ChannelUp = KEY_CHANNELUP as isize,
+    ChannelDown = KEY_CHANNELDOWN as isize,
+    First = KEY_FIRST as isize,
+    Last = KEY_LAST as isize,
+    Ab = KEY_AB as isize,
+    Next = KEY_NEXT as isize,
+    Restart = KEY_RESTART as isize,
+    Slow = KEY_SLOW as isize,
+    Shuffle = KEY_SHUFFLE as isize,
+    Break = KEY_BREAK as isize,
+    Previous = KEY_PREVIOUS as isize,
+    Digits = KEY_DIGITS as isize,
+    Teen = KEY_TEEN as isize,
+    Twen = KEY_TWEN as isize,
+    VideoPhone = KEY_VIDEOPHONE as isize,
+    Games = KEY_GAMES as isize,
+    ZoomIn = KEY_ZOOMIN as isize,
+    ZoomOut = KEY_ZOOMOUT as isize,
+    ZoomReset = KEY_ZOOMRESET as isize,
+    WordProcessor = KEY_WORDPROCESSOR as isize,
+    Editor = KEY_EDITOR as isize,
+    Spreadsheet = KEY_SPREADSHEET as isize,
+    GraphicsEditor = KEY_GRAPHICSEDITOR as isize,
+    Presentation = KEY_PRESENTATION as isize,
+    Database = KEY_DATABASE as isize,
+    News = KEY_NEWS as isize,
+    VoiceMail = KEY_VOICEMAIL as isize,
+    AddressBook = KEY_ADDRESSBOOK as isize,
+    Messenger = KEY_MESSENGER as isize,
+    DisplayToggle = KEY_DISPLAYTOGGLE as isize,
+    SpellCheck = KEY_SPELLCHECK as isize,
+    LogOff = KEY_LOGOFF as isize,
+    Dollar = KEY_DOLLAR as isize,
+    Euro = KEY_EURO as isize,
+    FrameBack = KEY_FRAMEBACK as isize,
+    FrameForward = KEY_FRAMEFORWARD as isize,
+    ContextMenu = KEY_CONTEXT_MENU as isize,
+    MediaRepeat = KEY_MEDIA_REPEAT as isize,
+    TenChannelsUp = KEY_10CHANNELSUP as isize,
+    TenChannelsDown = KEY_10CHANNELSDOWN as isize,
+    Images = KEY_IMAGES as isize,
+    DelEol = KEY_DEL_EOL as isize,
+    DelEos = KEY_DEL_EOS as isize,
+    InsLine = KEY_INS_LINE as isize,
+    DelLine = KEY_DEL_LINE as isize,
+    Fn = KEY_FN as isize,
+    FnEsc = KEY_FN_ESC as isize,
+    FnF1 = KEY_FN_F1 as isize,
+    FnF2 = KEY_FN_F2 as isize,
+    FnF3 = KEY_FN_F3 as isize,
+    FnF4 = KEY_FN_F4 as isize,
+    FnF5 = KEY_FN_F5 as isize,
+    FnF6 = KEY_FN_F6 as isize,
+    FnF7 = KEY_FN_F7 as isize,
+    FnF8 = KEY_FN_F8 as isize,
+    FnF9 = KEY_FN_F9 as isize,
+    FnF10 = KEY_FN_F10 as isize,
+    FnF11 = KEY_FN_F11 as isize,
+    FnF12 = KEY_FN_F12 as isize,
+    Fn1 = KEY_FN_1 as isize,
+    Fn2 = KEY_FN_2 as isize,
+    FnD = KEY_FN_D as isize,
+    FnE = KEY_FN_E as isize,
+    FnF = KEY_FN_F as isize,
+    FnS = KEY_FN_S as isize,
+    FnB = KEY_FN_B as isize,
+    BrlDot1 = KEY_BRL_DOT1 as isize,
+    BrlDot2 = KEY_BRL_DOT2 as isize,
+    BrlDot3 = KEY_BRL_DOT3 as isize,
+    BrlDot4 = KEY_BRL_DOT4 as isize,
+    BrlDot5 = KEY_BRL_DOT5 as isize,
+    BrlDot6 = KEY_BRL_DOT6 as isize,
+    BrlDot7 = KEY_BRL_DOT7 as isize,
+    BrlDot8 = KEY_BRL_DOT8 as isize,
+    BrlDot9 = KEY_BRL_DOT9 as isize,
+    BrlDot10 = KEY_BRL_DOT10 as isize,
+    Numeric0 = KEY_NUMERIC_0 as isize,
+    Numeric1 = KEY_NUMERIC_1 as isize,
+    Numeric2 = KEY_NUMERIC_2 as isize,
+    Numeric3 = KEY_NUMERIC_3 as isize,
+    Numeric4 = KEY_NUMERIC_4 as isize,
+    Numeric5 = KEY_NUMERIC_5 as isize,
+    Numeric6 = KEY_NUMERIC_6 as isize,
+    Numeric7 = KEY_NUMERIC_7 as isize,
+    Numeric8 = KEY_NUMERIC_8 as isize,
+    Numeric9 = KEY_NUMERIC_9 as isize,
+    NumericStar = KEY_NUMERIC_STAR as isize,
+    NumericPound = KEY_NUMERIC_POUND as isize,
+    NumericA = KEY_NUMERIC_A as isize,
+    NumericB = KEY_NUMERIC_B as isize,
+    NumericC = KEY_NUMERIC_C as isize,
+    NumericD = KEY_NUMERIC_D as isize,
+    CameraFocus = KEY_CAMERA_FOCUS as isize,
+    WpsButton = KEY_WPS_BUTTON as isize,
+    TouchpadToggle = KEY_TOUCHPAD_TOGGLE as isize,
+    TouchpadOn = KEY_TOUCHPAD_ON as isize,
+    TouchpadOff = KEY_TOUCHPAD_OFF as isize,
+    CameraZoomIn = KEY_CAMERA_ZOOMIN as isize,
+    CameraZoomOut = KEY_CAMERA_ZOOMOUT as isize,
+    CameraUp = KEY_CAMERA_UP as isize,
+    CameraDown = KEY_CAMERA_DOWN as isize,
+    CameraLeft = KEY_CAMERA_LEFT as isize,
+    CameraRight = KEY_CAMERA_RIGHT as isize,
+    AttendantOn = KEY_ATTENDANT_ON as isize,
+    AttendantOff = KEY_ATTENDANT_OFF as isize,
+    AttendantToggle = KEY_ATTENDANT_TOGGLE as isize,
+    LightsToggle = KEY_LIGHTS_TOGGLE as isize,
+    AlsToggle = KEY_ALS_TOGGLE as isize,
+    ButtonConfig = KEY_BUTTONCONFIG as isize,
+    TaskManager = KEY_TASKMANAGER as isize,
+    Journal = KEY_JOURNAL as isize,
+    ControlPanel = KEY_CONTROLPANEL as isize,
+    AppSelect = KEY_APPSELECT as isize,
+    ScreenSaver = KEY_SCREENSAVER as isize,
+    VoiceCommand = KEY_VOICECOMMAND as isize,
+    Assistant = KEY_ASSISTANT as isize,
+    BrightnessMin = KEY_BRIGHTNESS_MIN as isize,
+    BrightnessMax = KEY_BRIGHTNESS_MAX as isize,
+    KbdInputAssistPrev = KEY_KBDINPUTASSIST_PREV as isize,
+    KbdInputAssistNext = KEY_KBDINPUTASSIST_NEXT as isize,
+    KbdInputAssistPrevGroup = KEY_KBDINPUTASSIST_PREVGROUP as isize,
+    KbdInputAssistNextGroup = KEY_KBDINPUTASSIST_NEXTGROUP as isize,
+    KbdInputAssistAccept = KEY_KBDINPUTASSIST_ACCEPT as isize,
+    KbdInputAssistCancel = KEY_KBDINPUTASSIST_CANCEL as isize,
+    RightUp = KEY_RIGHT_UP as isize,
+    RightDown = KEY_RIGHT_DOWN as isize,
+    LeftUp = KEY_LEFT_UP as isize,
+    LeftDown = KEY_LEFT_DOWN as isize,
+    RootMenu = KEY_ROOT_MENU as isize,
+    MediaTopMenu = KEY_MEDIA_TOP_MENU as isize,
+    Numeric11 = KEY_NUMERIC_11 as isize,
+    Numeric12 = KEY_NUMERIC_12 as isize,
+    AudioDesc = KEY_AUDIO_DESC as isize,
+    Mode3D = KEY_3D_MODE as isize,
+    NextFavorite = KEY_NEXT_FAVORITE as isize,
+    StopRecord = KEY_STOP_RECORD as isize,
+    PauseRecord = KEY_PAUSE_RECORD as isize,
+    Vod = KEY_VOD as isize,
+    Unmute = KEY_UNMUTE as isize,
+    FastReverse = KEY_FASTREVERSE as isize,
+    SlowReverse = KEY_SLOWREVERSE as isize,
+    Data = KEY_DATA as isize,
+    OnscreenKeyboard = KEY_ONSCREEN_KEYBOARD as isize,
+}
+
+/* Canonical name and kernel name, without the KEY_ prefix, of every key */
+const KEY_NAMES: &[(Key, &str, &str)] = &[
+    (Key::Escape, "Escape", "ESC"),
+    (Key::One, "One", "1"),
+    (Key::Two, "Two", "2"),
+    (Key::Three, "Three", "3"),
+    (Key::Four, "Four", "4"),
+    (Key::Five, "Five", "5"),
+    (Key::Six, "Six", "6"),
+    (Key::Seven, "Seven", "7"),
+    (Key::Eight, "Eight", "8"),
+    (Key::Nine, "Nine", "9"),
+    (Key::Zero, "Zero", "0"),
+    (Key::Minus, "Minus", "MINUS"),
+    (Key::Equals, "Equals", "EQUAL"),
+    (Key::Backspace, "Backspace", "BACKSPACE"),
+    (Key::Tab, "Tab", "TAB"),
+    (Key::Q, "Q", "Q"),
+    (Key::W, "W", "W"),
+    (Key::E, "E", "E"),
+    (Key::R, "R", "R"),
+    (Key::T, "T", "T"),
+    (Key::Y, "Y", "Y"),
+    (Key::U, "U", "U"),
+    (Key::I, "I", "I"),
+    (Key::O, "O", "O"),
+    (Key::P, "P", "P"),
+    (Key::LeftBrace, "LeftBrace", "LEFTBRACE"),
+    (Key::RightBrace, "RightBrace", "RIGHTBRACE"),
+    (Key::Enter, "Enter", "ENTER"),
+    (Key::LeftControl, "LeftControl", "LEFTCTRL"),
+    (Key::A, "A", "A"),
+    (Key::S, "S", "S"),
+    (Key::D, "D", "D"),
+    (Key::F, "F", "F"),
+    (Key::G, "G", "G"),
+    (Key::H, "H", "H"),
+    (Key::J, "J", "J"),
+    (Key::K, "K", "K"),
+    (Key::L, "L", "L"),
+    (Key::Semicolon, "Semicolon", "SEMICOLON"),
+    (Key::Apostrophe, "Apostrophe", "APOSTROPHE"),
+    (Key::Backquote, "Backquote", "GRAVE"),
+    (Key::LeftShift, "LeftShift", "LEFTSHIFT"),
+    (Key::Backslash, "Backslash", "BACKSLASH"),
+    (Key::Z, "Z", "Z"),
+    (Key::X, "X", "X"),
+    (Key::C, "C", "C"),
+    (Key::V, "V", "V"),
+    (Key::B, "B", "B"),
+    (Key::N, "N", "N"),
+    (Key::M, "M", "M"),
+    (Key::Comma, "Comma", "COMMA"),
+    (Key::Period, "Period", "DOT"),
+    (Key::Slash, "Slash", "SLASH"),
+    (Key::RightShift, "RightShift", "RIGHTSHIFT"),
+    (Key::KpAsterisk, "KpAsterisk", "KPASTERISK"),
+    (Key::LeftAlt, "LeftAlt", "LEFTALT"),
+    (Key::Space, "Space", "SPACE"),
+    (Key::CapsLock, "CapsLock", "CAPSLOCK"),
+    (Key::F1, "F1", "F1"),
+    (Key::F2, "F2", "F2"),
+    (Key::F3, "F3", "F3"),
+    (Key::F4, "F4", "F4"),
+    (Key::F5, "F5", "F5"),
+    (Key::F6, "F6", "F6"),
+    (Key::F7, "F7", "F7"),
+    (Key::F8, "F8", "F8"),
+    (Key::F9, "F9", "F9"),
+    (Key::F10, "F10", "F10"),
+    (Key::NumLock, "NumLock", "NUMLOCK"),
+    (Key::ScrollLock, "ScrollLock", "SCROLLLOCK"),
+    (Key::Kp7, "Kp7", "KP7"),
+    (Key::Kp8, "Kp8", "KP8"),
+    (Key::Kp9, "Kp9", "KP9"),
+    (Key::KpMinus, "KpMinus", "KPMINUS"),
+    (Key::Kp4, "Kp4", "KP4"),
+    (Key::Kp5, "Kp5", "KP5"),
+    (Key::Kp6, "Kp6", "KP6"),
+    (Key::KpPlus, "KpPlus", "KPPLUS"),
+    (Key::Kp1, "Kp1", "KP1"),
+    (Key::Kp2, "Kp2", "KP2"),
+    (Key::Kp3, "Kp3", "KP3"),
+    (Key::Kp0, "Kp0", "KP0"),
+    (Key::KpDot, "KpDot", "KPDOT"),
+    (Key::ZenkakuHankaku, "ZenkakuHankaku", "ZENKAKUHANKAKU"),
+    (Key::Key102nd, "Key102nd", "102ND"),
+    (Key::F11, "F11", "F11"),
+    (Key::F12, "F12", "F12"),
+    (Key::Ro, "Ro", "RO"),
+    (Key::Katakana, "Katakana", "KATAKANA"),
+    (Key::Hiragana, "Hiragana", "HIRAGANA"),
+    (Key::Henkan, "Henkan", "HENKAN"),
+    (Key::KatakanaHiragana, "KatakanaHiragana", "KATAKANAHIRAGANA"),
+    (Key::Muhenkan, "Muhenkan", "MUHENKAN"),
+    (Key::KpJpComma, "KpJpComma", "KPJPCOMMA"),
+    (Key::KpEnter, "KpEnter", "KPENTER"),
+    (Key::RightControl, "RightControl", "RIGHTCTRL"),
+    (Key::KpSlash, "KpSlash", "KPSLASH"),
+    (Key::SysRq, "SysRq", "SYSRQ"),
+    (Key::RightAlt, "RightAlt", "RIGHTALT"),
+    (Key::LineFeed, "LineFeed", "LINEFEED"),
+    (Key::Home, "Home", "HOME"),
+    (Key::UpArrow, "UpArrow", "UP"),
+    (Key::PageUp, "PageUp", "PAGEUP"),
+    (Key::LeftArrow, "LeftArrow", "LEFT"),
+    (Key::RightArrow, "RightArrow", "RIGHT"),
+    (Key::End, "End", "END"),
+    (Key::DownArrow, "DownArrow", "DOWN"),
+    (Key::PageDown, "PageDown", "PAGEDOWN"),
+    (Key::Insert, "Insert", "INSERT"),
+    (Key::Delete, "Delete", "DELETE"),
+    (Key::Macro, "Macro", "MACRO"),
+    (Key::Mute, "Mute", "MUTE"),
+    (Key::VolumeDown, "VolumeDown", "VOLUMEDOWN"),
+    (Key::VolumeUp, "VolumeUp", "VOLUMEUP"),
+    (Key::Power, "Power", "POWER"),
+    (Key::KpEqual, "KpEqual", "KPEQUAL"),
+    (Key::KpPlusMinus, "KpPlusMinus", "KPPLUSMINUS"),
+    (Key::Pause, "Pause", "PAUSE"),
+    (Key::Scale, "Scale", "SCALE"),
+    (Key::KpComma, "KpComma", "KPCOMMA"),
+    (Key::Hangeul, "Hangeul", "HANGEUL"),
+    (Key::Hanja, "Hanja", "HANJA"),
+    (Key::Yen, "Yen", "YEN"),
+    (Key::LeftMeta, "LeftMeta", "LEFTMETA"),
+    (Key::RightMeta, "RightMeta", "RIGHTMETA"),
+    (Key::Compose, "Compose", "COMPOSE"),
+    (Key::Stop, "Stop", "STOP"),
+    (Key::Again, "Again", "AGAIN"),
+    (Key::Props, "Props", "PROPS"),
+    (Key::Undo, "Undo", "UNDO"),
+    (Key::Front, "Front", "FRONT"),
+    (Key::Copy, "Copy", "COPY"),
+    (Key::Open, "Open", "OPEN"),
+    (Key::Paste, "Paste", "PASTE"),
+    (Key::Find, "Find", "FIND"),
+    (Key::Cut, "Cut", "CUT"),
+    (Key::Help, "Help", "HELP"),
+    (Key::Menu, "Menu", "MENU"),
+    (Key::Calc, "Calc", "CALC"),
+    (Key::Setup, "Setup", "SETUP"),
+    (Key::Sleep, "Sleep", "SLEEP"),
+    (Key::WakeUp, "WakeUp", "WAKEUP"),
+    (Key::File, "File", "FILE"),
+    (Key::SendFile, "SendFile", "SENDFILE"),
+    (Key::DeleteFile, "DeleteFile", "DELETEFILE"),
+    (Key::Xfer, "Xfer", "XFER"),
+    (Key::Prog1, "Prog1", "PROG1"),
+    (Key::Prog2, "Prog2", "PROG2"),
+    (Key::Www, "Www", "WWW"),
+    (Key::Msdos, "Msdos", "MSDOS"),
+    (Key::ScreenLock, "ScreenLock", "SCREENLOCK"),
+    (Key::RotateDisplay, "RotateDisplay", "ROTATE_DISPLAY"),
+    (Key::CycleWindows, "CycleWindows", "CYCLEWINDOWS"),
+    (Key::Mail, "Mail", "MAIL"),
+    (Key::Bookmarks, "Bookmarks", "BOOKMARKS"),
+    (Key::Computer, "Computer", "COMPUTER"),
+    (Key::Back, "Back", "BACK"),
+    (Key::Forward, "Forward", "FORWARD"),
+    (Key::CloseCd, "CloseCd", "CLOSECD"),
+    (Key::EjectCd, "EjectCd", "EJECTCD"),
+    (Key::EjectCloseCd, "EjectCloseCd", "EJECTCLOSECD"),
+    (Key::NextSong, "NextSong", "NEXTSONG"),
+    (Key::PlayPause, "PlayPause", "PLAYPAUSE"),
+    (Key::PreviousSong, "PreviousSong", "PREVIOUSSONG"),
+    (Key::StopCd, "StopCd", "STOPCD"),
+    (Key::Record, "Record", "RECORD"),
+    (Key::Rewind, "Rewind", "REWIND"),
+    (Key::Phone, "Phone", "PHONE"),
+    (Key::Iso, "Iso", "ISO"),
+    (Key::Config, "Config", "CONFIG"),
+    (Key::HomePage, "HomePage", "HOMEPAGE"),
+    (Key::Refresh, "Refresh", "REFRESH"),
+    (Key::Exit, "Exit", "EXIT"),
+    (Key::Move, "Move", "MOVE"),
+    (Key::Edit, "Edit", "EDIT"),
+    (Key::ScrollUp, "ScrollUp", "SCROLLUP"),
+    (Key::ScrollDown, "ScrollDown", "SCROLLDOWN"),
+    (Key::KpLeftParen, "KpLeftParen", "KPLEFTPAREN"),
+    (Key::KpRightParen, "KpRightParen", "KPRIGHTPAREN"),
+    (Key::New, "New", "NEW"),
+    (Key::Redo, "Redo", "REDO"),
+    (Key::F13, "F13", "F13"),
+    (Key::F14, "F14", "F14"),
+    (Key::F15, "F15", "F15"),
+    (Key::F16, "F16", "F16"),
+    (Key::F17, "F17", "F17"),
+    (Key::F18, "F18", "F18"),
+    (Key::F19, "F19", "F19"),
+    (Key::F20, "F20", "F20"),
+    (Key::F21, "F21", "F21"),
+    (Key::F22, "F22", "F22"),
+    (Key::F23, "F23", "F23"),
+    (Key::F24, "F24", "F24"),
+    (Key::PlayCd, "PlayCd", "PLAYCD"),
+    (Key::PauseCd, "PauseCd", "PAUSECD"),
+    (Key::Prog3, "Prog3", "PROG3"),
+    (Key::Prog4, "Prog4", "PROG4"),
+    (Key::Dashboard, "Dashboard", "DASHBOARD"),
+    (Key::Suspend, "Suspend", "SUSPEND"),
+    (Key::Close, "Close", "CLOSE"),
+    (Key::Play, "Play", "PLAY"),
+    (Key::FastForward, "FastForward", "FASTFORWARD"),
+    (Key::BassBoost, "BassBoost", "BASSBOOST"),
+    (Key::Print, "Print", "PRINT"),
+    (Key::Hp, "Hp", "HP"),
+    (Key::Camera, "Camera", "CAMERA"),
+    (Key::Sound, "Sound", "SOUND"),
+    (Key::Question, "Question", "QUESTION"),
+    (Key::Email, "Email", "EMAIL"),
+    (Key::Chat, "Chat", "CHAT"),
+    (Key::Search, "Search", "SEARCH"),
+    (Key::Connect, "Connect", "CONNECT"),
+    (Key::Finance, "Finance", "FINANCE"),
+    (Key::Sport, "Sport", "SPORT"),
+    (Key::Shop, "Shop", "SHOP"),
+    (Key::AltErase, "AltErase", "ALTERASE"),
+    (Key::Cancel, "Cancel", "CANCEL"),
+    (Key::BrightnessDown, "BrightnessDown", "BRIGHTNESSDOWN"),
+    (Key::BrightnessUp, "BrightnessUp", "BRIGHTNESSUP"),
+    (Key::Media, "Media", "MEDIA"),
+    (Key::SwitchVideoMode, "SwitchVideoMode", "SWITCHVIDEOMODE"),
+    (Key::KbdIllumToggle, "KbdIllumToggle", "KBDILLUMTOGGLE"),
+    (Key::KbdIllumDown, "KbdIllumDown", "KBDILLUMDOWN"),
+    (Key::KbdIllumUp, "KbdIllumUp", "KBDILLUMUP"),
+    (Key::Send, "Send", "SEND"),
+    (Key::Reply, "Reply", "REPLY"),
+    (Key::ForwardMail, "ForwardMail", "FORWARDMAIL"),
+    (Key::Save, "Save", "SAVE"),
+    (Key::Documents, "Documents", "DOCUMENTS"),
+    (Key::Battery, "Battery", "BATTERY"),
+    (Key::Bluetooth, "Bluetooth", "BLUETOOTH"),
+    (Key::Wlan, "Wlan", "WLAN"),
+    (Key::Uwb, "Uwb", "UWB"),
+    (Key::Unknown, "Unknown", "UNKNOWN"),
+    (Key::VideoNext, "VideoNext", "VIDEO_NEXT"),
+    (Key::VideoPrev, "VideoPrev", "VIDEO_PREV"),
+    (Key::BrightnessCycle, "BrightnessCycle", "BRIGHTNESS_CYCLE"),
+    (Key::BrightnessAuto, "BrightnessAuto", "BRIGHTNESS_AUTO"),
+    (Key::DisplayOff, "DisplayOff", "DISPLAY_OFF"),
+    (Key::Wwan, "Wwan", "WWAN"),
+    (Key::Rfkill, "Rfkill", "RFKILL"),
+    (Key::MicMute, "MicMute", "MICMUTE"),
+    (Key::Ok, "Ok", "OK"),
+    (Key::Select, "Select", "SELECT"),
+    (Key::Goto, "Goto", "GOTO"),
+    (Key::Clear, "Clear", "CLEAR"),
+    (Key::Power2, "Power2", "POWER2"),
+    (Key::Option, "Option", "OPTION"),
+    (Key::Info, "Info", "INFO"),
+    (Key::Time, "Time", "TIME"),
+    (Key::Vendor, "Vendor", "VENDOR"),
+    (Key::Archive, "Archive", "ARCHIVE"),
+    (Key::Program, "Program", "PROGRAM"),
+    (Key::Channel, "Channel", "CHANNEL"),
+    (Key::Favorites, "Favorites", "FAVORITES"),
+    (Key::Epg, "Epg", "EPG"),
+    (Key::Pvr, "Pvr", "PVR"),
+    (Key::Mhp, "Mhp", "MHP"),
+    (Key::Language, "Language", "LANGUAGE"),
+    (Key::Title, "Title", "TITLE"),
+    (Key::Subtitle, "Subtitle", "SUBTITLE"),
+    (Key::Angle, "Angle", "ANGLE"),
+    (Key::Zoom, "Zoom", "ZOOM"),
+    (Key::Mode, "Mode", "MODE"),
+    (Key::Keyboard, "Keyboard", "KEYBOARD"),
+    (Key::Screen, "Screen", "SCREEN"),
+    (Key::Pc, "Pc", "PC"),
+    (Key::Tv, "Tv", "TV"),
+    (Key::Tv2, "Tv2", "TV2"),
+    (Key::Vcr, "Vcr", "VCR"),
+    (Key::Vcr2, "Vcr2", "VCR2"),
+    (Key::Sat, "Sat", "SAT"),
+    (Key::Sat2, "Sat2", "SAT2"),
+    (Key::Cd, "Cd", "CD"),
+    (Key::Tape, "Tape", "TAPE"),
+    (Key::Radio, "Radio", "RADIO"),
+    (Key::Tuner, "Tuner", "TUNER"),
+    (Key::Player, "Player", "PLAYER"),
+    (Key::Text, "Text", "TEXT"),
+    (Key::Dvd, "Dvd", "DVD"),
+    (Key::Aux, "Aux", "AUX"),
+    (Key::Mp3, "Mp3", "MP3"),
+    (Key::Audio, "Audio", "AUDIO"),
+    (Key::Video, "Video", "VIDEO"),
+    (Key::Directory, "Directory", "DIRECTORY"),
+    (Key::List, "List", "LIST"),
+    (Key::Memo, "Memo", "MEMO"),
+    (Key::Calendar, "Calendar", "CALENDAR"),
+    (Key::Red, "Red", "RED"),
+    (Key::Green, "Green", "GREEN"),
+    (Key::Yellow, "Yellow", "YELLOW"),
+    (Key::Blue, "Blue", "BLUE"),
+    (Key::ChannelUp, "ChannelUp", "CHANNELUP"),
+    (Key::ChannelDown, "ChannelDown", "CHANNELDOWN"),
+    (Key::First, "First", "FIRST"),
+    (Key::Last, "Last", "LAST"),
+    (Key::Ab, "Ab", "AB"),
+    (Key::Next, "Next", "NEXT"),
+    (Key::Restart, "Restart", "RESTART"),
+    (Key::Slow, "Slow", "SLOW"),
+    (Key::Shuffle, "Shuffle", "SHUFFLE"),
+    (Key::Break, "Break", "BREAK"),
+    (Key::Previous, "Previous", "PREVIOUS"),
+    (Key::Digits, "Digits", "DIGITS"),
+    (Key::Teen, "Teen", "TEEN"),
+    (Key::Twen, "Twen", "TWEN"),
+    (Key::VideoPhone, "VideoPhone", "VIDEOPHONE"),
+    (Key::Games, "Games", "GAMES"),
+    (Key::ZoomIn, "ZoomIn", "ZOOMIN"),
+    (Key::ZoomOut, "ZoomOut", "ZOOMOUT"),
+    (Key::ZoomReset, "ZoomReset", "ZOOMRESET"),
+    (Key::WordProcessor, "WordProcessor", "WORDPROCESSOR"),
+    (Key::Editor, "Editor", "EDITOR"),
+    (Key::Spreadsheet, "Spreadsheet", "SPREADSHEET"),
+    (Key::GraphicsEditor, "GraphicsEditor", "GRAPHICSEDITOR"),
+    (Key::Presentation, "Presentation", "PRESENTATION"),
+    (Key::Database, "Database", "DATABASE"),
+    (Key::News, "News", "NEWS"),
+    (Key::VoiceMail, "VoiceMail", "VOICEMAIL"),
+    (Key::AddressBook, "AddressBook", "ADDRESSBOOK"),
+    (Key::Messenger, "Messenger", "MESSENGER"),
+    (Key::DisplayToggle, "DisplayToggle", "DISPLAYTOGGLE"),
+    (Key::SpellCheck, "SpellCheck", "SPELLCHECK"),
+    (Key::LogOff, "LogOff", "LOGOFF"),
+    (Key::Dollar, "Dollar", "DOLLAR"),
+    (Key::Euro, "Euro", "EURO"),
+    (Key::FrameBack, "FrameBack", "FRAMEBACK"),
+    (Key::FrameForward, "FrameForward", "FRAMEFORWARD"),
+    (Key::ContextMenu, "ContextMenu", "CONTEXT_MENU"),
+    (Key::MediaRepeat, "MediaRepeat", "MEDIA_REPEAT"),
+    (Key::TenChannelsUp, "TenChannelsUp", "10CHANNELSUP"),
+    (Key::TenChannelsDown, "TenChannelsDown", "10CHANNELSDOWN"),
+    (Key::Images, "Images", "IMAGES"),
+    (Key::DelEol, "DelEol", "DEL_EOL"),
+    (Key::DelEos, "DelEos", "DEL_EOS"),
+    (Key::InsLine, "InsLine", "INS_LINE"),
+    (Key::DelLine, "DelLine", "DEL_LINE"),
+    (Key::Fn, "Fn", "FN"),
+    (Key::FnEsc, "FnEsc", "FN_ESC"),
+    (Key::FnF1, "FnF1", "FN_F1"),
+    (Key::FnF2, "FnF2", "FN_F2"),
+    (Key::FnF3, "FnF3", "FN_F3"),
+    (Key::FnF4, "FnF4", "FN_F4"),
+    (Key::FnF5, "FnF5", "FN_F5"),
+    (Key::FnF6, "FnF6", "FN_F6"),
+    (Key::FnF7, "FnF7", "FN_F7"),
+    (Key::FnF8, "FnF8", "FN_F8"),
+    (Key::FnF9, "FnF9", "FN_F9"),
+    (Key::FnF10, "FnF10", "FN_F10"),
+    (Key::FnF11, "FnF11", "FN_F11"),
+    (Key::FnF12, "FnF12", "FN_F12"),
+    (Key::Fn1, "Fn1", "FN_1"),
+    (Key::Fn2, "Fn2", "FN_2"),
+    (Key::FnD, "FnD", "FN_D"),
+    (Key::FnE, "FnE", "FN_E"),
+    (Key::FnF, "FnF", "FN_F"),
+    (Key::FnS, "FnS", "FN_S"),
+    (Key::FnB, "FnB", "FN_B"),
+    (Key::BrlDot1, "BrlDot1", "BRL_DOT1"),
+    (Key::BrlDot2, "BrlDot2", "BRL_DOT2"),
+    (Key::BrlDot3, "BrlDot3", "BRL_DOT3"),
+    (Key::BrlDot4, "BrlDot4", "BRL_DOT4"),
+    (Key::BrlDot5, "BrlDot5", "BRL_DOT5"),
+    (Key::BrlDot6, "BrlDot6", "BRL_DOT6"),
+    (Key::BrlDot7, "BrlDot7", "BRL_DOT7"),
+    (Key::BrlDot8, "BrlDot8", "BRL_DOT8"),
+    (Key::BrlDot9, "BrlDot9", "BRL_DOT9"),
+    (Key::BrlDot10, "BrlDot10", "BRL_DOT10"),
+    (Key::Numeric0, "Numeric0", "NUMERIC_0"),
+    (Key::Numeric1, "Numeric1", "NUMERIC_1"),
+    (Key::Numeric2, "Numeric2", "NUMERIC_2"),
+    (Key::Numeric3, "Numeric3", "NUMERIC_3"),
+    (Key::Numeric4, "Numeric4", "NUMERIC_4"),
+    (Key::Numeric5, "Numeric5", "NUMERIC_5"),
+    (Key::Numeric6, "Numeric6", "NUMERIC_6"),
+    (Key::Numeric7, "Numeric7", "NUMERIC_7"),
+    (Key::Numeric8, "Numeric8", "NUMERIC_8"),
+    (Key::Numeric9, "Numeric9", "NUMERIC_9"),
+    (Key::NumericStar, "NumericStar", "NUMERIC_STAR"),
+    (Key::NumericPound, "NumericPound", "NUMERIC_POUND"),
+    (Key::NumericA, "NumericA", "NUMERIC_A"),
+    (Key::NumericB, "NumericB", "NUMERIC_B"),
+    (Key::NumericC, "NumericC", "NUMERIC_C"),
+    (Key::NumericD, "NumericD", "NUMERIC_D"),
+    (Key::CameraFocus, "CameraFocus", "CAMERA_FOCUS"),
+    (Key::WpsButton, "WpsButton", "WPS_BUTTON"),
+    (Key::TouchpadToggle, "TouchpadToggle", "TOUCHPAD_TOGGLE"),
+    (Key::TouchpadOn, "TouchpadOn", "TOUCHPAD_ON"),
+    (Key::TouchpadOff, "TouchpadOff", "TOUCHPAD_OFF"),
+    (Key::CameraZoomIn, "CameraZoomIn", "CAMERA_ZOOMIN"),
+    (Key::CameraZoomOut, "CameraZoomOut", "CAMERA_ZOOMOUT"),
+    (Key::CameraUp, "CameraUp", "CAMERA_UP"),
+    (Key::CameraDown, "CameraDown", "CAMERA_DOWN"),
+    (Key::CameraLeft, "CameraLeft", "CAMERA_LEFT"),
+    (Key::CameraRight, "CameraRight", "CAMERA_RIGHT"),
+    (Key::AttendantOn, "AttendantOn", "ATTENDANT_ON"),
+    (Key::AttendantOff, "AttendantOff", "ATTENDANT_OFF"),
+    (Key::AttendantToggle, "AttendantToggle", "ATTENDANT_TOGGLE"),
+    (Key::LightsToggle, "LightsToggle", "LIGHTS_TOGGLE"),
+    (Key::AlsToggle, "AlsToggle", "ALS_TOGGLE"),
+    (Key::ButtonConfig, "ButtonConfig", "BUTTONCONFIG"),
+    (Key::TaskManager, "TaskManager", "TASKMANAGER"),
+    (Key::Journal, "Journal", "JOURNAL"),
+    (Key::ControlPanel, "ControlPanel", "CONTROLPANEL"),
+    (Key::AppSelect, "AppSelect", "APPSELECT"),
+    (Key::ScreenSaver, "ScreenSaver", "SCREENSAVER"),
+    (Key::VoiceCommand, "VoiceCommand", "VOICECOMMAND"),
+    (Key::Assistant, "Assistant", "ASSISTANT"),
+    (Key::BrightnessMin, "BrightnessMin", "BRIGHTNESS_MIN"),
+    (Key::BrightnessMax, "BrightnessMax", "BRIGHTNESS_MAX"),
+    (Key::KbdInputAssistPrev, "KbdInputAssistPrev", "KBDINPUTASSIST_PREV"),
+    (Key::KbdInputAssistNext, "KbdInputAssistNext", "KBDINPUTASSIST_NEXT"),
+    (Key::KbdInputAssistPrevGroup, "KbdInputAssistPrevGroup", "KBDINPUTASSIST_PREVGROUP"),
+    (Key::KbdInputAssistNextGroup, "KbdInputAssistNextGroup", "KBDINPUTASSIST_NEXTGROUP"),
+    (Key::KbdInputAssistAccept, "KbdInputAssistAccept", "KBDINPUTASSIST_ACCEPT"),
+    (Key::KbdInputAssistCancel, "KbdInputAssistCancel", "KBDINPUTASSIST_CANCEL"),
+    (Key::RightUp, "RightUp", "RIGHT_UP"),
+    (Key::RightDown, "RightDown", "RIGHT_DOWN"),
+    (Key::LeftUp, "LeftUp", "LEFT_UP"),
+    (Key::LeftDown, "LeftDown", "LEFT_DOWN"),
+    (Key::RootMenu, "RootMenu", "ROOT_MENU"),
+    (Key::MediaTopMenu, "MediaTopMenu", "MEDIA_TOP_MENU"),
+    (Key::Numeric11, "Numeric11", "NUMERIC_11"),
+    (Key::Numeric12, "Numeric12", "NUMERIC_12"),
+    (Key::AudioDesc, "AudioDesc", "AUDIO_DESC"),
+    (Key::Mode3D, "Mode3D", "3D_MODE"),
+    (Key::NextFavorite, "NextFavorite", "NEXT_FAVORITE"),
+    (Key::StopRecord, "StopRecord", "STOP_RECORD"),
+    (Key::PauseRecord, "PauseRecord", "PAUSE_RECORD"),
+    (Key::Vod, "Vod", "VOD"),
+    (Key::Unmute, "Unmute", "UNMUTE"),
+    (Key::FastReverse, "FastReverse", "FASTREVERSE"),
+    (Key::SlowReverse, "SlowReverse", "SLOWREVERSE"),
+    (Key::Data, "Data", "DATA"),
+    (Key::OnscreenKeyboard, "OnscreenKeyboard", "ONSCREEN_KEYBOARD"),
+];
+
+/* Other names keys go by: kernel names shared by two codes, X keysyms and common shorthands */
+const KEY_ALIASES: &[(&str, Key)] = &[
+    ("HANGUEL", Key::Hangeul),
+    ("COFFEE", Key::ScreenLock),
+    ("DIRECTION", Key::RotateDisplay),
+    ("BRIGHTNESS_ZERO", Key::BrightnessAuto),
+    ("WIMAX", Key::Wwan),
+    ("BRIGHTNESS_TOGGLE", Key::DisplayToggle),
+    ("ctrl", Key::LeftControl),
+    ("control", Key::LeftControl),
+    ("Control_L", Key::LeftControl),
+    ("Control_R", Key::RightControl),
+    ("shift", Key::LeftShift),
+    ("Shift_L", Key::LeftShift),
+    ("Shift_R", Key::RightShift),
+    ("alt", Key::LeftAlt),
+    ("Alt_L", Key::LeftAlt),
+    ("Alt_R", Key::RightAlt),
+    ("altgr", Key::RightAlt),
+    ("ISO_Level3_Shift", Key::RightAlt),
+    ("super", Key::LeftMeta),
+    ("meta", Key::LeftMeta),
+    ("win", Key::LeftMeta),
+    ("logo", Key::LeftMeta),
+    ("Super_L", Key::LeftMeta),
+    ("Meta_L", Key::LeftMeta),
+    ("Super_R", Key::RightMeta),
+    ("Meta_R", Key::RightMeta),
+    ("return", Key::Enter),
+    ("del", Key::Delete),
+    ("ins", Key::Insert),
+    ("pgup", Key::PageUp),
+    ("Page_Up", Key::PageUp),
+    ("Prior", Key::PageUp),
+    ("pgdn", Key::PageDown),
+    ("Page_Down", Key::PageDown),
+    ("Caps_Lock", Key::CapsLock),
+    ("Num_Lock", Key::NumLock),
+    ("Scroll_Lock", Key::ScrollLock),
+    ("PrintScreen", Key::SysRq),
+    ("PrtSc", Key::SysRq),
+    ("KP_0", Key::Kp0),
+    ("KP_1", Key::Kp1),
+    ("KP_2", Key::Kp2),
+    ("KP_3", Key::Kp3),
+    ("KP_4", Key::Kp4),
+    ("KP_5", Key::Kp5),
+    ("KP_6", Key::Kp6),
+    ("KP_7", Key::Kp7),
+    ("KP_8", Key::Kp8),
+    ("KP_9", Key::Kp9),
+    ("KP_Add", Key::KpPlus),
+    ("KP_Subtract", Key::KpMinus),
+    ("KP_Multiply", Key::KpAsterisk),
+    ("KP_Divide", Key::KpSlash),
+    ("KP_Enter", Key::KpEnter),
+    ("KP_Decimal", Key::KpDot),
+    ("KP_Equal", Key::KpEqual),
+    ("XF86AudioRaiseVolume", Key::VolumeUp),
+    ("XF86AudioLowerVolume", Key::VolumeDown),
+    ("XF86AudioMute", Key::Mute),
+    ("XF86AudioMicMute", Key::MicMute),
+    ("XF86AudioPlay", Key::PlayPause),
+    ("XF86AudioPause", Key::PauseCd),
+    ("XF86AudioStop", Key::StopCd),
+    ("XF86AudioNext", Key::NextSong),
+    ("XF86AudioPrev", Key::PreviousSong),
+    ("XF86AudioRecord", Key::Record),
+    ("XF86AudioRewind", Key::Rewind),
+    ("XF86AudioForward", Key::FastForward),
+    ("XF86AudioMedia", Key::Media),
+    ("XF86MonBrightnessUp", Key::BrightnessUp),
+    ("XF86MonBrightnessDown", Key::BrightnessDown),
+    ("XF86KbdBrightnessUp", Key::KbdIllumUp),
+    ("XF86KbdBrightnessDown", Key::KbdIllumDown),
+    ("XF86KbdLightOnOff", Key::KbdIllumToggle),
+    ("XF86Calculator", Key::Calc),
+    ("XF86Mail", Key::Mail),
+    ("XF86HomePage", Key::HomePage),
+    ("XF86Search", Key::Search),
+    ("XF86Back", Key::Back),
+    ("XF86Forward", Key::Forward),
+    ("XF86Reload", Key::Refresh),
+    ("XF86Refresh", Key::Refresh),
+    ("XF86Sleep", Key::Sleep),
+    ("XF86WakeUp", Key::WakeUp),
+    ("XF86PowerOff", Key::Power),
+    ("XF86Suspend", Key::Suspend),
+    ("XF86Eject", Key::EjectCd),
+    ("XF86MyComputer", Key::Computer),
+    ("XF86Explorer", Key::File),
+    ("XF86WWW", Key::Www),
+    ("XF86Display", Key::SwitchVideoMode),
+    ("XF86WLAN", Key::Wlan),
+    ("XF86Bluetooth", Key::Bluetooth),
+    ("XF86RFKill", Key::Rfkill),
+    ("XF86Battery", Key::Battery),
+    ("XF86WebCam", Key::Camera),
+    ("XF86TouchpadToggle", Key::TouchpadToggle),
+    ("XF86TouchpadOn", Key::TouchpadOn),
+    ("XF86TouchpadOff", Key::TouchpadOff),
+    ("XF86Favorites", Key::Bookmarks),
+    ("XF86ScreenSaver", Key::ScreenLock),
+    ("XF86Tools", Key::Config),
+    ("XF86Documents", Key::Documents),
+    ("XF86Phone", Key::Phone),
+    ("XF86Shop", Key::Shop),
+    ("XF86Copy", Key::Copy),
+    ("XF86Paste", Key::Paste),
+    ("XF86Cut", Key::Cut),
+    ("XF86Open", Key::Open),
+    ("XF86Save", Key::Save),
+    ("XF86Close", Key::Close),
+    ("XF86New", Key::New),
+];
+
+impl Key {
+    pub fn all() -> impl Iterator<Item = Key> {
+        KEY_NAMES.iter().map(|(key, _, _)| *key)
+    }
+
+    pub fn from_code(code: u32) -> Option<Key> {
+        Key::all().find(|key| *key as u32 == code)
+    }
+
+    pub fn name(self) -> &'static str {
+        KEY_NAMES.iter().find(|(key, _, _)| *key == self).map(|(_, name, _)| *name).unwrap()
+    }
+}
+
+impl fmt::Display for Key {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/* Accepts, ignoring case, the canonical name, the kernel name with or without KEY_ and any alias */
+impl FromStr for Key {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Key, String> {
+        let name = s.trim();
+        let kernel_name = match name.get(..4) {
+            Some(prefix) if name.len() > 4 && prefix.eq_ignore_ascii_case("KEY_") => &name[4..],
+            _ => name,
+        };
+
+        KEY_NAMES.iter().find(|(_, canonical, _)| canonical.eq_ignore_ascii_case(name)).map(|(key, _, _)| *key)
+            .or_else(|| KEY_NAMES.iter().find(|(_, _, kernel)| kernel.eq_ignore_ascii_case(kernel_name)).map(|(key, _, _)| *key))
+            .or_else(|| KEY_ALIASES.iter().find(|(alias, _)| alias.eq_ignore_ascii_case(kernel_name)).map(|(_, key)| *key))
+            .ok_or_else(|| format!("Unknown key \"{}\"", name))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_are_unique_and_round_trip() {
+        for key in Key::all() {
+            assert_eq!(key.to_string().parse::<Key>(), Ok(key));
+            assert_eq!(Key::from_code(key as u32), Some(key));
+        }
+        assert_eq!(Key::from_code(KEY_RESERVED), None);
+    }
+
+    #[test]
+    fn parses_kernel_names_and_aliases() {
+        assert_eq!("VolumeUp".parse::<Key>(), Ok(Key::VolumeUp));
+        assert_eq!("KEY_VOLUMEUP".parse::<Key>(), Ok(Key::VolumeUp));
+        assert_eq!("XF86AudioRaiseVolume".parse::<Key>(), Ok(Key::VolumeUp));
+        assert_eq!("ctrl".parse::<Key>(), Ok(Key::LeftControl));
+        assert_eq!("Super".parse::<Key>(), Ok(Key::LeftMeta));
+        assert_eq!("1".parse::<Key>(), Ok(Key::One));
+        assert_eq!("f24".parse::<Key>(), Ok(Key::F24));
+        assert_eq!("KP_Add".parse::<Key>(), Ok(Key::KpPlus));
+        assert_eq!("coffee".parse::<Key>(), Ok(Key::ScreenLock));
+        assert!("Hyper".parse::<Key>().is_err());
+        assert!("aéé".parse::<Key>().is_err());
+        assert!("KEY_é".parse::<Key>().is_err());
+    }
+}
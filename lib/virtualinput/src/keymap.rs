@@ -0,0 +1,343 @@
+/*
+ * Copyright © 2019 Romeo Calota
+ *
+ * This program is free software; you can redistribute it and/or
+ * modify it under the terms of the GNU Lesser General Public
+ * License as published by the Free Software Foundation; either
+ * version 2 of the licence, or (at your option) any later version.
+ *
+ * This software is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+ * Lesser General Public License for more details.
+ *
+ * You should have received a copy of the GNU Lesser General Public
+ * License along with this program; if not, see <http://www.gnu.org/licenses/>.
+ *
+ * Author: Romeo Calota
+ */
+
+use crate::Key;
+use ::std::os::raw::*;
+use std::collections::HashMap;
+use std::ffi::CString;
+use std::fmt;
+use std::fs;
+use std::ptr;
+use std::str::FromStr;
+
+const XKB_CONTEXT_NO_FLAGS: c_int = 0;
+const XKB_KEYMAP_COMPILE_NO_FLAGS: c_int = 0;
+const XKB_KEYMAP_FORMAT_TEXT_V1: c_int = 1;
+const XKB_MOD_INVALID: u32 = 0xffffffff;
+
+/* xkb keycodes are evdev keycodes shifted by 8, a leftover from X11 */
+const EVDEV_OFFSET: u32 = 8;
+
+const XKB_KEY_RETURN: u32 = 0xff0d;
+const XKB_KEY_TAB: u32 = 0xff09;
+
+/* Modifiers the keymap may ask for and the key that is held for each */
+const MODIFIERS: [(&str, Key); 5] = [
+    ("Shift", Key::LeftShift),
+    ("Control", Key::LeftControl),
+    ("Mod1", Key::LeftAlt),
+    ("Mod4", Key::LeftMeta),
+    ("Mod5", Key::RightAlt),
+];
+
+#[repr(C)]
+struct xkb_rule_names
+{
+    rules: *const c_char,
+    model: *const c_char,
+    layout: *const c_char,
+    variant: *const c_char,
+    options: *const c_char,
+}
+
+#[link(name = "xkbcommon")]
+extern "C"
+{
+    #[no_mangle]
+    fn xkb_context_new(flags: c_int) -> *mut c_void;
+
+    #[no_mangle]
+    fn xkb_context_unref(context: *mut c_void);
+
+    #[no_mangle]
+    fn xkb_keymap_new_from_names(context: *mut c_void, names: *const xkb_rule_names, flags: c_int) -> *mut c_void;
+
+    #[no_mangle]
+    fn xkb_keymap_new_from_string(context: *mut c_void, string: *const c_char, format: c_int, flags: c_int) -> *mut c_void;
+
+    #[no_mangle]
+    fn xkb_keymap_unref(keymap: *mut c_void);
+
+    #[no_mangle]
+    fn xkb_keymap_min_keycode(keymap: *mut c_void) -> u32;
+
+    #[no_mangle]
+    fn xkb_keymap_max_keycode(keymap: *mut c_void) -> u32;
+
+    #[no_mangle]
+    fn xkb_keymap_num_levels_for_key(keymap: *mut c_void, key: u32, layout: u32) -> u32;
+
+    #[no_mangle]
+    fn xkb_keymap_key_get_syms_by_level(keymap: *mut c_void, key: u32, layout: u32, level: u32, syms_out: *mut *const u32) -> c_int;
+
+    #[no_mangle]
+    fn xkb_keymap_key_get_mods_for_level(keymap: *mut c_void, key: u32, layout: u32, level: u32, masks_out: *mut u32, masks_size: usize) -> usize;
+
+    #[no_mangle]
+    fn xkb_keymap_mod_get_index(keymap: *mut c_void, name: *const c_char) -> u32;
+
+    #[no_mangle]
+    fn xkb_utf32_to_keysym(ucs: u32) -> u32;
+}
+
+/* A key and the modifiers held while pressing it */
+#[derive(Debug, PartialEq, Clone)]
+pub struct KeyStroke
+{
+    pub key: Key,
+    pub modifiers: Vec<Key>,
+}
+
+impl KeyStroke {
+    pub fn new(key: Key, modifiers: &[Key]) -> KeyStroke {
+        KeyStroke { key, modifiers: modifiers.to_vec() }
+    }
+}
+
+/* How to type characters the keyboard layout has no key for */
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum UnicodeFallback
+{
+    /* Typing them fails */
+    Disabled,
+    /* Control+Shift+U, the code point in hex and Space; understood by GTK and IBus */
+    CtrlShiftU,
+}
+
+impl fmt::Display for UnicodeFallback {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            UnicodeFallback::Disabled => write!(f, "none"),
+            UnicodeFallback::CtrlShiftU => write!(f, "ctrl-shift-u"),
+        }
+    }
+}
+
+impl FromStr for UnicodeFallback {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<UnicodeFallback, String> {
+        match s.trim() {
+            "none" => Ok(UnicodeFallback::Disabled),
+            "ctrl-shift-u" => Ok(UnicodeFallback::CtrlShiftU),
+            other => Err(format!("Unknown unicode input method \"{}\"", other)),
+        }
+    }
+}
+
+/* Where a keymap is compiled from */
+#[derive(Debug, PartialEq, Clone)]
+pub enum KeymapSource
+{
+    /* A complete keymap in the xkb text format, see Keymap::from_file */
+    File(String),
+    /* A layout and variant, see Keymap::from_layout */
+    Layout(String, String),
+}
+
+impl KeymapSource {
+    pub fn load(&self) -> Result<Keymap, String> {
+        match self {
+            KeymapSource::File(path) => Keymap::from_file(path),
+            KeymapSource::Layout(layout, variant) => Keymap::from_layout(layout, variant),
+        }
+    }
+}
+
+/* The keys that produce each keysym of an xkb keymap, on its first layout */
+#[derive(Clone)]
+pub struct Keymap
+{
+    strokes: HashMap<u32, KeyStroke>,
+}
+
+impl Keymap {
+    /* An RMLVO layout such as "de" with an optional variant such as "nodeadkeys";
+     * empty strings pick the system defaults */
+    pub fn from_layout(layout: &str, variant: &str) -> Result<Keymap, String> {
+        let rules = CString::new("evdev").unwrap();
+        let layout_cstr = CString::new(layout).map_err(|e| e.to_string())?;
+        let variant_cstr = CString::new(variant).map_err(|e| e.to_string())?;
+        let optional = |s: &str, cstr: &CString| if s.is_empty() { ptr::null() } else { cstr.as_ptr() };
+
+        let names = xkb_rule_names {
+            rules: rules.as_ptr(),
+            model: ptr::null(),
+            layout: optional(layout, &layout_cstr),
+            variant: optional(variant, &variant_cstr),
+            options: ptr::null(),
+        };
+
+        Keymap::compile(|context| unsafe { xkb_keymap_new_from_names(context, &names as *const xkb_rule_names, XKB_KEYMAP_COMPILE_NO_FLAGS) })
+            .ok_or_else(|| format!("Failed to compile keyboard layout \"{}\"", if variant.is_empty() { layout.to_string() } else { format!("{}({})", layout, variant) }))
+    }
+
+    /* A complete keymap in the xkb text format, as written by xkbcomp */
+    pub fn from_file(path: &str) -> Result<Keymap, String> {
+        let contents = fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+        let contents = CString::new(contents).map_err(|e| format!("{}: {}", path, e))?;
+
+        Keymap::compile(|context| unsafe { xkb_keymap_new_from_string(context, contents.as_ptr(), XKB_KEYMAP_FORMAT_TEXT_V1, XKB_KEYMAP_COMPILE_NO_FLAGS) })
+            .ok_or_else(|| format!("{}: Failed to compile keymap", path))
+    }
+
+    fn compile<Create: FnOnce(*mut c_void) -> *mut c_void>(create: Create) -> Option<Keymap> {
+        unsafe {
+            let context = xkb_context_new(XKB_CONTEXT_NO_FLAGS);
+            if context.is_null() { return None; }
+
+            let keymap = create(context);
+            let result = if keymap.is_null() { None } else { Some(Keymap { strokes: Keymap::read_strokes(keymap) }) };
+
+            if !keymap.is_null() { xkb_keymap_unref(keymap); }
+            xkb_context_unref(context);
+
+            result
+        }
+    }
+
+    unsafe fn read_strokes(keymap: *mut c_void) -> HashMap<u32, KeyStroke> {
+        let modifiers: Vec<(u32, Key)> = MODIFIERS.iter()
+            .filter_map(|(name, key)| {
+                let name = CString::new(*name).unwrap();
+                match xkb_keymap_mod_get_index(keymap, name.as_ptr()) {
+                    XKB_MOD_INVALID => None,
+                    index => Some((1 << index, *key)),
+                }
+            })
+            .collect();
+        let known_mask = modifiers.iter().fold(0, |mask, (bit, _)| mask | bit);
+
+        let mut strokes: HashMap<u32, KeyStroke> = HashMap::new();
+        for keycode in xkb_keymap_min_keycode(keymap)..=xkb_keymap_max_keycode(keymap) {
+            let key = match keycode.checked_sub(EVDEV_OFFSET).and_then(Key::from_code) {
+                Some(key) => key,
+                None => continue,
+            };
+
+            for level in 0..xkb_keymap_num_levels_for_key(keymap, keycode, 0) {
+                let mut syms = ptr::null();
+                let sym_count = xkb_keymap_key_get_syms_by_level(keymap, keycode, 0, level, &mut syms);
+                if sym_count != 1 { continue; }
+                let keysym = *syms;
+
+                /* Take the first way of reaching the level that only needs modifiers we can hold */
+                let mut masks = [0u32; 8];
+                let mask_count = xkb_keymap_key_get_mods_for_level(keymap, keycode, 0, level, masks.as_mut_ptr(), masks.len());
+                let mask = match masks[..mask_count].iter().find(|mask| *mask & !known_mask == 0) {
+                    Some(mask) => *mask,
+                    None => continue,
+                };
+
+                let stroke = KeyStroke {
+                    key,
+                    modifiers: modifiers.iter().filter(|(bit, _)| mask & bit != 0).map(|(_, key)| *key).collect(),
+                };
+                let better = strokes.get(&keysym).map(|s| stroke.modifiers.len() < s.modifiers.len()).unwrap_or(true);
+                if better { strokes.insert(keysym, stroke); }
+            }
+        }
+
+        strokes
+    }
+
+    pub fn stroke_for(&self, c: char) -> Option<&KeyStroke> {
+        let keysym = match c {
+            '\n' => XKB_KEY_RETURN,
+            '\t' => XKB_KEY_TAB,
+            _ => unsafe { xkb_utf32_to_keysym(c as u32) },
+        };
+
+        self.strokes.get(&keysym)
+    }
+
+    /* The key strokes typing text, using fallback for characters the layout can't reach */
+    pub fn strokes_for_text(&self, text: &str, fallback: UnicodeFallback) -> Result<Vec<KeyStroke>, String> {
+        let mut strokes = Vec::new();
+        for c in text.chars() {
+            if let Some(stroke) = self.stroke_for(c) {
+                strokes.push(stroke.clone());
+                continue;
+            }
+
+            match fallback {
+                UnicodeFallback::Disabled => return Err(format!("The keyboard layout has no key for '{}'", c)),
+                UnicodeFallback::CtrlShiftU => {
+                    strokes.push(KeyStroke::new(Key::U, &[Key::LeftControl, Key::LeftShift]));
+                    for digit in format!("{:x}", c as u32).chars() {
+                        let stroke = self.stroke_for(digit).ok_or_else(|| format!("The keyboard layout has no key for '{}'", digit))?;
+                        strokes.push(stroke.clone());
+                    }
+                    strokes.push(KeyStroke::new(Key::Space, &[]));
+                },
+            }
+        }
+
+        Ok(strokes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    /* The layouts come from xkeyboard-config, which minimal systems and containers may lack */
+    fn has_xkeyboard_config() -> bool {
+        let root = std::env::var("XKB_CONFIG_ROOT").unwrap_or_else(|_| String::from("/usr/share/X11/xkb"));
+        if Path::new(&root).join("rules/evdev").exists() { return true; }
+
+        eprintln!("Skipping, xkeyboard-config is not installed in {}", root);
+        false
+    }
+
+    #[test]
+    fn resolves_characters_through_layout() {
+        if !has_xkeyboard_config() { return; }
+
+        let us = Keymap::from_layout("us", "").unwrap();
+        assert_eq!(us.strokes_for_text("a@\n", UnicodeFallback::Disabled).unwrap(), vec![
+            KeyStroke::new(Key::A, &[]),
+            KeyStroke::new(Key::Two, &[Key::LeftShift]),
+            KeyStroke::new(Key::Enter, &[]),
+        ]);
+
+        let de = Keymap::from_layout("de", "nodeadkeys").unwrap();
+        assert_eq!(de.strokes_for_text("z@", UnicodeFallback::Disabled).unwrap(), vec![
+            KeyStroke::new(Key::Y, &[]),
+            KeyStroke::new(Key::Q, &[Key::RightAlt]),
+        ]);
+
+        assert!(Keymap::from_layout("no-such-layout", "").is_err());
+    }
+
+    #[test]
+    fn falls_back_to_unicode_input() {
+        if !has_xkeyboard_config() { return; }
+
+        let us = Keymap::from_layout("us", "").unwrap();
+        assert!(us.strokes_for_text("é", UnicodeFallback::Disabled).is_err());
+        assert_eq!(us.strokes_for_text("é", UnicodeFallback::CtrlShiftU).unwrap(), vec![
+            KeyStroke::new(Key::U, &[Key::LeftControl, Key::LeftShift]),
+            KeyStroke::new(Key::E, &[]),
+            KeyStroke::new(Key::Nine, &[]),
+            KeyStroke::new(Key::Space, &[]),
+        ]);
+    }
+}
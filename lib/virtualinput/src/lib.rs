@@ -18,85 +18,263 @@
  */
 
 mod errno;
+mod evdev_device;
 mod evdev_uinput;
+mod held_input;
+mod key;
+mod keymap;
+#[cfg(test)]
+mod test_support;
+mod uinput_sink;
+mod virtual_pointer;
+mod virtual_touchpad;
 
 use errno::*;
 use ::std::os::raw::*;
 use evdev_uinput::*;
 use std::borrow::{BorrowMut, Borrow};
+use std::ffi::CStr;
+use std::sync::{Arc, Mutex};
+
+use held_input::{lock, HeldInput, HeldSink};
+
+pub use evdev_device::{monotonic_time, EvdevDevice, InputEvent, UinputClone};
+pub use held_input::release_all_devices;
+pub use key::Key;
+pub use keymap::{KeyStroke, Keymap, KeymapSource, UnicodeFallback};
+pub use uinput_sink::EventSink;
+pub use virtual_pointer::{VirtualPointer, REL_WHEEL_HI_RES, REL_HWHEEL_HI_RES, WHEEL_HI_RES_PER_CLICK};
+pub use virtual_touchpad::VirtualTouchpad;
 
 const LIBEVDEV_UINPUT_OPEN_MANAGED: i32 = -2;
 
+/* Event types and codes, for interpreting what is read from an EvdevDevice */
+pub mod codes {
+    pub use crate::evdev_uinput::{
+        EV_SYN, EV_KEY, EV_REL, EV_ABS, SYN_REPORT, SYN_DROPPED, REL_X, REL_Y,
+        ABS_X, ABS_Y, ABS_MT_SLOT, ABS_MT_TRACKING_ID, ABS_MT_POSITION_X, ABS_MT_POSITION_Y,
+        BTN_LEFT, BTN_RIGHT, BTN_MIDDLE, BTN_TOUCH, BTN_TOOL_FINGER, BTN_TOOL_DOUBLETAP, BTN_TOOL_TRIPLETAP, BTN_TOOL_QUADTAP, BTN_TOOL_QUINTTAP,
+    };
+}
+
 #[link(name = "evdev")]
 extern "C" { }
 
 #[derive(Debug, PartialEq, Clone, Copy)]
-pub enum Key
+pub enum Button
+{
+    Left = BTN_LEFT as isize,
+    Right = BTN_RIGHT as isize,
+    Middle = BTN_MIDDLE as isize,
+    Side = BTN_SIDE as isize,
+    Extra = BTN_EXTRA as isize,
+}
+
+/* "left", "right", "middle", "side" or "extra", in any case */
+impl std::str::FromStr for Button {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Button, String> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "left" => Ok(Button::Left),
+            "right" => Ok(Button::Right),
+            "middle" => Ok(Button::Middle),
+            "side" => Ok(Button::Side),
+            "extra" => Ok(Button::Extra),
+            _ => Err(format!("Unknown mouse button \"{}\"", s.trim())),
+        }
+    }
+}
+
+impl Button {
+    pub fn from_code(code: u32) -> Option<Button> {
+        match code {
+            BTN_LEFT => Some(Button::Left),
+            BTN_RIGHT => Some(Button::Right),
+            BTN_MIDDLE => Some(Button::Middle),
+            BTN_SIDE => Some(Button::Side),
+            BTN_EXTRA => Some(Button::Extra),
+            _ => None,
+        }
+    }
+}
+
+/* "pci", "usb", "bluetooth", "virtual" or "i8042" (PS/2) */
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum BusType
 {
-    A = KEY_A as isize, B = KEY_B as isize, C = KEY_C as isize, D = KEY_D as isize,
-    E = KEY_E as isize, F = KEY_F as isize, G = KEY_G as isize, H = KEY_H as isize,
-    I = KEY_I as isize, J = KEY_J as isize, K = KEY_K as isize, L = KEY_L as isize,
-    M = KEY_M as isize, N = KEY_N as isize, O = KEY_O as isize, P = KEY_P as isize,
-    Q = KEY_Q as isize, R = KEY_R as isize, S = KEY_S as isize, T = KEY_T as isize,
-    U = KEY_U as isize, V = KEY_V as isize, W = KEY_W as isize, X = KEY_X as isize,
-    Y = KEY_Y as isize, Z = KEY_Z as isize,
-    One = KEY_1 as isize,
-    Two = KEY_2 as isize,
-    Three = KEY_3 as isize,
-    Four = KEY_4 as isize,
-    Five = KEY_5 as isize,
-    Six = KEY_6 as isize,
-    Seven = KEY_7 as isize,
-    Eight = KEY_8 as isize,
-    Nine = KEY_9 as isize,
-    Zero = KEY_0 as isize,
-    F1 = KEY_F1 as isize, F2 = KEY_F2 as isize, F3 = KEY_F3 as isize, F4 = KEY_F4 as isize,
-    F5 = KEY_F5 as isize, F6 = KEY_F6 as isize, F7 = KEY_F7 as isize, F8 = KEY_F8 as isize,
-    F9 = KEY_F9 as isize, F10 = KEY_F10 as isize, F11 = KEY_F11 as isize, F12 = KEY_F12 as isize,
-    LeftControl = KEY_LEFTCTRL as isize,
-    LeftShift = KEY_LEFTSHIFT as isize,
-    LeftAlt = KEY_LEFTALT as isize,
-    LeftMeta = KEY_LEFTMETA as isize,
-    RightControl = KEY_RIGHTCTRL as isize,
-    RightShift = KEY_RIGHTSHIFT as isize,
-    RightAlt = KEY_RIGHTALT as isize,
-    RightMeta = KEY_RIGHTMETA as isize,
-    Space = KEY_SPACE as isize,
-    Tab = KEY_TAB as isize,
-    Enter = KEY_ENTER as isize,
-    CapsLock = KEY_CAPSLOCK as isize,
-    PageUp = KEY_PAGEUP as isize,
-    PageDown = KEY_PAGEDOWN as isize,
-    LeftArrow = KEY_LEFT as isize,
-    RightArrow = KEY_RIGHT as isize,
-    UpArrow = KEY_UP as isize,
-    DownArrow = KEY_DOWN as isize,
-    Slash = KEY_SLASH as isize,
-    Backslash = KEY_BACKSLASH as isize,
-    Backspace = KEY_BACKSPACE as isize,
-    Comma = KEY_COMMA as isize,
-    Period = KEY_DOT as isize,
-    Semicolon = KEY_SEMICOLON as isize,
-    Apostrophe = KEY_APOSTROPHE as isize,
-    Minus = KEY_MINUS as isize,
-    Equals = KEY_EQUAL as isize,
-    Backquote = KEY_GRAVE as isize,
-    Escape = KEY_ESC as isize,
+    Pci = BUS_PCI as isize,
+    Usb = BUS_USB as isize,
+    Bluetooth = BUS_BLUETOOTH as isize,
+    Virtual = BUS_VIRTUAL as isize,
+    I8042 = BUS_I8042 as isize,
+}
+
+impl std::str::FromStr for BusType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<BusType, String> {
+        match s.trim() {
+            "pci" => Ok(BusType::Pci),
+            "usb" => Ok(BusType::Usb),
+            "bluetooth" => Ok(BusType::Bluetooth),
+            "virtual" => Ok(BusType::Virtual),
+            "i8042" => Ok(BusType::I8042),
+            _ => Err(format!("Unknown bus type \"{}\"", s.trim())),
+        }
+    }
+}
+
+/*
+ * Identity and capabilities of a VirtualInput. By default it is a full keyboard and a five button mouse
+ * with a wheel on the virtual bus; declaring only what is used keeps it from looking like more than it is.
+ */
+#[derive(Debug, PartialEq, Clone)]
+pub struct VirtualInputBuilder
+{
+    pub name: String,
+    pub vendor_id: u16,
+    pub product_id: u16,
+    pub bus_type: BusType,
+    pub version: u16,
+
+    pub keys: Vec<Key>,
+    pub buttons: Vec<Button>,
+    pub pointer_motion: bool,
+    pub wheel: bool,
+}
+
+impl VirtualInputBuilder {
+    pub fn new(device_name: &str) -> VirtualInputBuilder {
+        VirtualInputBuilder {
+            name: device_name.to_string(),
+            vendor_id: 0,
+            product_id: 0,
+            bus_type: BusType::Virtual,
+            version: 0,
+            keys: Key::all().collect(),
+            buttons: vec![Button::Left, Button::Right, Button::Middle, Button::Side, Button::Extra],
+            pointer_motion: true,
+            wheel: true,
+        }
+    }
+
+    pub fn id(mut self, vendor_id: u16, product_id: u16) -> VirtualInputBuilder {
+        self.vendor_id = vendor_id;
+        self.product_id = product_id;
+        self
+    }
+
+    pub fn bus_type(mut self, bus_type: BusType) -> VirtualInputBuilder {
+        self.bus_type = bus_type;
+        self
+    }
+
+    pub fn version(mut self, version: u16) -> VirtualInputBuilder {
+        self.version = version;
+        self
+    }
+
+    /* Only these keys can be pressed, or typed with */
+    pub fn keys(mut self, keys: &[Key]) -> VirtualInputBuilder {
+        self.keys = keys.to_vec();
+        self
+    }
+
+    pub fn buttons(mut self, buttons: &[Button]) -> VirtualInputBuilder {
+        self.buttons = buttons.to_vec();
+        self
+    }
+
+    pub fn pointer_motion(mut self, enabled: bool) -> VirtualInputBuilder {
+        self.pointer_motion = enabled;
+        self
+    }
+
+    pub fn wheel(mut self, enabled: bool) -> VirtualInputBuilder {
+        self.wheel = enabled;
+        self
+    }
+
+    /* Whether the device would be able to do what is asked of it, with the error it would give otherwise */
+    pub fn check_keys(&self, keys: &[Key]) -> Result<(), String> {
+        match keys.iter().find(|k| !self.keys.contains(k)) {
+            Some(key) => Err(format!("Key {} is not enabled on the virtual device", key)),
+            None => Ok(()),
+        }
+    }
+
+    /* Which keys a text needs depends on the layout, only a device without keys is known not to manage */
+    pub fn check_typing(&self) -> Result<(), String> {
+        if self.keys.is_empty() {
+            return Err(String::from("No keys are enabled on the virtual device to type with"));
+        }
+
+        Ok(())
+    }
+
+    pub fn check_button(&self, button: Button) -> Result<(), String> {
+        if !self.buttons.contains(&button) {
+            return Err(format!("Button {:?} is not enabled on the virtual device", button));
+        }
+
+        Ok(())
+    }
+
+    pub fn check_pointer_motion(&self) -> Result<(), String> {
+        if !self.pointer_motion {
+            return Err(String::from("Pointer motion is not enabled on the virtual device"));
+        }
+
+        Ok(())
+    }
+
+    pub fn check_wheel(&self) -> Result<(), String> {
+        if !self.wheel {
+            return Err(String::from("The wheel is not enabled on the virtual device"));
+        }
+
+        Ok(())
+    }
+
+    pub fn build(self) -> Result<VirtualInput, String> {
+        VirtualInput::from_builder(self)
+    }
 }
 
 pub struct VirtualInput
 {
     evdev_dev: *mut libevdev,
     virtual_device_name: String,
-    uinput_dev: *mut libevdev_uinput
+    uinput_dev: *mut libevdev_uinput,
+    /* What the device was created with, events it doesn't declare are refused */
+    description: VirtualInputBuilder,
+    /* What is pressed, to be released on errors and when the device goes away */
+    held: Arc<Mutex<HeldInput>>,
+    /* Used to type text, the system default layout is loaded on first use if not set */
+    keymap: Option<Keymap>,
+    unicode_fallback: UnicodeFallback,
+    /* Buttons, motion and the wheel, written through held so buttons are released with the keys */
+    pointer: VirtualPointer,
 }
 
 impl VirtualInput {
+    /* A full keyboard and mouse, see VirtualInputBuilder for anything else */
     pub fn new(device_name: &str) -> Result<VirtualInput, String> {
+        VirtualInputBuilder::new(device_name).build()
+    }
+
+    fn from_builder(description: VirtualInputBuilder) -> Result<VirtualInput, String> {
+        let held = Arc::new(Mutex::new(HeldInput::new()));
         let mut instance = VirtualInput {
             evdev_dev: 0 as *mut libevdev,
-            virtual_device_name: device_name.to_string() + "\0",
-            uinput_dev: 0 as *mut libevdev_uinput
+            virtual_device_name: description.name.clone() + "\0",
+            uinput_dev: 0 as *mut libevdev_uinput,
+            description,
+            held: held.clone(),
+            keymap: None,
+            unicode_fallback: UnicodeFallback::Disabled,
+            pointer: VirtualPointer::with_sink(Box::new(HeldSink(held))),
         };
 
         unsafe {
@@ -106,11 +284,33 @@ impl VirtualInput {
                 instance.virtual_device_name.as_ptr() as *const c_char
             );
 
-            libevdev_enable_event_type(instance.evdev_dev, EV_KEY);
+            let description = &instance.description;
+            libevdev_set_id_vendor(instance.evdev_dev, description.vendor_id as c_int);
+            libevdev_set_id_product(instance.evdev_dev, description.product_id as c_int);
+            libevdev_set_id_bustype(instance.evdev_dev, description.bus_type as c_int);
+            libevdev_set_id_version(instance.evdev_dev, description.version as c_int);
 
-            /* Enable all keys for device */
-            for i in 1..249 {
-                libevdev_enable_event_code(instance.evdev_dev, EV_KEY, i as u32, 0 as *const c_void);
+            if !description.keys.is_empty() || !description.buttons.is_empty() {
+                libevdev_enable_event_type(instance.evdev_dev, EV_KEY);
+            }
+            for key in &description.keys {
+                libevdev_enable_event_code(instance.evdev_dev, EV_KEY, *key as u32, 0 as *const c_void);
+            }
+            /* Pointer buttons, motion and the wheel, for actions that click, move and scroll */
+            for button in &description.buttons {
+                libevdev_enable_event_code(instance.evdev_dev, EV_KEY, *button as u32, 0 as *const c_void);
+            }
+
+            let mut relative_codes = Vec::new();
+            if description.pointer_motion { relative_codes.extend_from_slice(&[REL_X, REL_Y]); }
+            if description.wheel {
+                relative_codes.extend_from_slice(&[REL_WHEEL, REL_HWHEEL, REL_WHEEL_HI_RES, REL_HWHEEL_HI_RES]);
+            }
+            if !relative_codes.is_empty() {
+                libevdev_enable_event_type(instance.evdev_dev, EV_REL);
+            }
+            for code in relative_codes {
+                libevdev_enable_event_code(instance.evdev_dev, EV_REL, code, 0 as *const c_void);
             }
 
             let err = libevdev_uinput_create_from_device(
@@ -123,24 +323,28 @@ impl VirtualInput {
             }
         }
 
+        lock(&instance.held).uinput_dev = instance.uinput_dev;
+        held_input::register(&instance.held);
+
         Ok(instance)
     }
 
     fn press_release(&mut self, keys: &[Key], press: bool) -> Result<(), String> {
-        unsafe {
-            for key in keys {
-                let err = libevdev_uinput_write_event(self.uinput_dev, EV_KEY, (*key) as u32, press as i32);
-                if err != 0 {
-                    return Err(strerror(-err));
-                }
-                let err = libevdev_uinput_write_event(self.uinput_dev, EV_SYN, SYN_REPORT, 0);
-                if err != 0 {
-                    return Err(strerror(-err));
-                }
-            }
+        self.description.check_keys(keys)?;
 
-            Ok(())
-        }
+        let result = {
+            let mut held = lock(&self.held);
+            keys.iter().try_for_each(|key| held.write_key(*key as u32, press))
+        };
+        /* Don't leave half a chord pressed */
+        if result.is_err() { self.release_all(); }
+
+        result
+    }
+
+    /* Releases every key and button pressed through this device */
+    pub fn release_all(&mut self) {
+        lock(&self.held).release();
     }
 
     pub fn press(&mut self, keys: &[Key], release: bool) -> Result<(), String> {
@@ -152,15 +356,87 @@ impl VirtualInput {
         Ok(())
     }
 
+    /* Press keys without releasing them */
+    pub fn hold(&mut self, keys: &[Key]) -> Result<(), String> {
+        self.press_release(keys, true)
+    }
+
     pub fn release(&mut self, keys: &[Key]) -> Result<(), String> {
         self.press_release(keys, false)
     }
+
+    /* The layout text is typed with, which should match the one the desktop uses */
+    pub fn set_keymap(&mut self, keymap: Keymap, unicode_fallback: UnicodeFallback) {
+        self.keymap = Some(keymap);
+        self.unicode_fallback = unicode_fallback;
+    }
+
+    pub fn type_text(&mut self, text: &str) -> Result<(), String> {
+        if self.keymap.is_none() {
+            self.keymap = Some(Keymap::from_layout("", "")?);
+        }
+
+        let strokes = self.keymap.as_ref().unwrap().strokes_for_text(text, self.unicode_fallback)?;
+        for stroke in strokes {
+            self.press_release(&stroke.modifiers, true)?;
+            let result = self.press(&[stroke.key], true);
+            /* Never leave modifiers held, even if the key failed */
+            self.press_release(&stroke.modifiers, false)?;
+            result?;
+        }
+
+        Ok(())
+    }
+
+    pub fn button(&mut self, button: Button, pressed: bool) -> Result<(), String> {
+        self.description.check_button(button)?;
+
+        let result = self.pointer.button(button, pressed);
+        if result.is_err() { self.release_all(); }
+
+        result
+    }
+
+    pub fn click(&mut self, button: Button) -> Result<(), String> {
+        self.button(button, true)?;
+        self.button(button, false)
+    }
+
+    pub fn move_by(&mut self, dx: i32, dy: i32) -> Result<(), String> {
+        self.description.check_pointer_motion()?;
+
+        self.pointer.move_by(dx, dy)
+    }
+
+    /* Wheel clicks, positive values scroll up and right */
+    pub fn scroll(&mut self, vertical: i32, horizontal: i32) -> Result<(), String> {
+        self.description.check_wheel()?;
+
+        self.pointer.scroll(vertical, horizontal)
+    }
+
+    /* The /dev/input node of the virtual device, if the kernel created one */
+    pub fn devnode(&self) -> Option<String> {
+        unsafe {
+            let devnode = libevdev_uinput_get_devnode(self.uinput_dev);
+            if devnode.is_null() { return None; }
+
+            Some(CStr::from_ptr(devnode).to_string_lossy().into_owned())
+        }
+    }
 }
 
 impl Drop for VirtualInput {
     fn drop(&mut self) {
+        {
+            let mut held = lock(&self.held);
+            held.release();
+            held.uinput_dev = 0 as *mut libevdev_uinput;
+        }
+
         unsafe {
             libevdev_uinput_destroy(self.uinput_dev);
+            libevdev_free(self.evdev_dev);
         }
     }
 }
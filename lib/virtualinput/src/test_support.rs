@@ -0,0 +1,48 @@
+/*
+ * Copyright © 2019 Romeo Calota
+ *
+ * This program is free software; you can redistribute it and/or
+ * modify it under the terms of the GNU Lesser General Public
+ * License as published by the Free Software Foundation; either
+ * version 2 of the licence, or (at your option) any later version.
+ *
+ * This software is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+ * Lesser General Public License for more details.
+ *
+ * You should have received a copy of the GNU Lesser General Public
+ * License along with this program; if not, see <http://www.gnu.org/licenses/>.
+ *
+ * Author: Romeo Calota
+ */
+
+
+use crate::evdev_device::InputEvent;
+use crate::evdev_uinput::*;
+use crate::uinput_sink::EventSink;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+/* Keeps everything written to it, shared with the test */
+pub(crate) struct RecordingSink
+{
+    events: Rc<RefCell<Vec<InputEvent>>>,
+}
+
+impl EventSink for RecordingSink {
+    fn write(&mut self, events: &[InputEvent]) -> Result<(), String> {
+        self.events.borrow_mut().extend_from_slice(events);
+        Ok(())
+    }
+}
+
+/* A sink for a virtual device under test, and what it received */
+pub(crate) fn recording_sink() -> (Box<RecordingSink>, Rc<RefCell<Vec<InputEvent>>>) {
+    let events = Rc::new(RefCell::new(Vec::new()));
+    (Box::new(RecordingSink { events: events.clone() }), events)
+}
+
+pub(crate) fn syn() -> InputEvent {
+    InputEvent::new(EV_SYN, SYN_REPORT, 0)
+}
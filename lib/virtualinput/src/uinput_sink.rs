@@ -0,0 +1,98 @@
+/*
+ * Copyright © 2019 Romeo Calota
+ *
+ * This program is free software; you can redistribute it and/or
+ * modify it under the terms of the GNU Lesser General Public
+ * License as published by the Free Software Foundation; either
+ * version 2 of the licence, or (at your option) any later version.
+ *
+ * This software is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+ * Lesser General Public License for more details.
+ *
+ * You should have received a copy of the GNU Lesser General Public
+ * License along with this program; if not, see <http://www.gnu.org/licenses/>.
+ *
+ * Author: Romeo Calota
+ */
+
+use crate::errno::*;
+use crate::evdev_device::{InputEvent, UinputClone};
+use crate::evdev_uinput::*;
+use std::ffi::{CStr, CString};
+
+const LIBEVDEV_UINPUT_OPEN_MANAGED: i32 = -2;
+
+/* Where a virtual device writes its events; a uinput device, or anything else for testing */
+pub trait EventSink
+{
+    /* Events are written as given, include a SYN_REPORT to complete a frame */
+    fn write(&mut self, events: &[InputEvent]) -> Result<(), String>;
+}
+
+impl EventSink for UinputClone {
+    fn write(&mut self, events: &[InputEvent]) -> Result<(), String> {
+        UinputClone::write(self, events)
+    }
+}
+
+/* A uinput device described by a libevdev device the caller has set the capabilities of */
+pub(crate) struct UinputSink
+{
+    evdev_dev: *mut libevdev,
+    uinput_dev: *mut libevdev_uinput,
+}
+
+impl UinputSink {
+    /* Calls describe with a fresh, named libevdev device to enable the capabilities on */
+    pub(crate) fn new<Describe: FnOnce(*mut libevdev)>(device_name: &str, describe: Describe) -> Result<UinputSink, String> {
+        let name = CString::new(device_name).map_err(|e| e.to_string())?;
+
+        unsafe {
+            let evdev_dev = libevdev_new();
+            libevdev_set_name(evdev_dev, name.as_ptr());
+            describe(evdev_dev);
+
+            let mut uinput_dev = 0 as *mut libevdev_uinput;
+            let err = libevdev_uinput_create_from_device(evdev_dev, LIBEVDEV_UINPUT_OPEN_MANAGED, &mut uinput_dev as *mut *mut libevdev_uinput);
+            if err != 0 {
+                libevdev_free(evdev_dev);
+                return Err(strerror(-err));
+            }
+
+            Ok(UinputSink { evdev_dev, uinput_dev })
+        }
+    }
+
+    pub(crate) fn devnode(&self) -> Option<String> {
+        unsafe {
+            let devnode = libevdev_uinput_get_devnode(self.uinput_dev);
+            if devnode.is_null() { return None; }
+
+            Some(CStr::from_ptr(devnode).to_string_lossy().into_owned())
+        }
+    }
+}
+
+impl EventSink for UinputSink {
+    fn write(&mut self, events: &[InputEvent]) -> Result<(), String> {
+        for event in events {
+            let err = unsafe { libevdev_uinput_write_event(self.uinput_dev, event.event_type, event.code, event.value) };
+            if err != 0 {
+                return Err(strerror(-err));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Drop for UinputSink {
+    fn drop(&mut self) {
+        unsafe {
+            libevdev_uinput_destroy(self.uinput_dev);
+            libevdev_free(self.evdev_dev);
+        }
+    }
+}
@@ -0,0 +1,164 @@
+/*
+ * Copyright © 2019 Romeo Calota
+ *
+ * This program is free software; you can redistribute it and/or
+ * modify it under the terms of the GNU Lesser General Public
+ * License as published by the Free Software Foundation; either
+ * version 2 of the licence, or (at your option) any later version.
+ *
+ * This software is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+ * Lesser General Public License for more details.
+ *
+ * You should have received a copy of the GNU Lesser General Public
+ * License along with this program; if not, see <http://www.gnu.org/licenses/>.
+ *
+ * Author: Romeo Calota
+ */
+
+use crate::evdev_device::InputEvent;
+use crate::evdev_uinput::*;
+use crate::uinput_sink::{EventSink, UinputSink};
+use crate::Button;
+use ::std::os::raw::*;
+
+/* Not in the generated bindings, the kernel headers they came from predate them */
+pub const REL_WHEEL_HI_RES: u32 = 0x0b;
+pub const REL_HWHEEL_HI_RES: u32 = 0x0c;
+
+/* High resolution wheel units per detent, as defined by the kernel */
+pub const WHEEL_HI_RES_PER_CLICK: i32 = 120;
+
+/* A virtual mouse: relative motion, the five common buttons and smooth scrolling */
+pub struct VirtualPointer
+{
+    sink: Box<dyn EventSink>,
+    /* High resolution wheel movement not yet sent as a whole detent, vertical and horizontal */
+    wheel_remainder: (i32, i32),
+}
+
+impl VirtualPointer {
+    pub fn new(device_name: &str) -> Result<VirtualPointer, String> {
+        let sink = UinputSink::new(device_name, |evdev_dev| unsafe {
+            libevdev_enable_event_type(evdev_dev, EV_KEY);
+            for button in &[Button::Left, Button::Right, Button::Middle, Button::Side, Button::Extra] {
+                libevdev_enable_event_code(evdev_dev, EV_KEY, *button as u32, 0 as *const c_void);
+            }
+
+            libevdev_enable_event_type(evdev_dev, EV_REL);
+            for code in &[REL_X, REL_Y, REL_WHEEL, REL_HWHEEL, REL_WHEEL_HI_RES, REL_HWHEEL_HI_RES] {
+                libevdev_enable_event_code(evdev_dev, EV_REL, *code, 0 as *const c_void);
+            }
+        })?;
+
+        Ok(VirtualPointer::with_sink(Box::new(sink)))
+    }
+
+    pub fn with_sink(sink: Box<dyn EventSink>) -> VirtualPointer {
+        VirtualPointer { sink, wheel_remainder: (0, 0) }
+    }
+
+    fn write_frame(&mut self, mut events: Vec<InputEvent>) -> Result<(), String> {
+        events.push(InputEvent::new(EV_SYN, SYN_REPORT, 0));
+        self.sink.write(&events)
+    }
+
+    pub fn move_by(&mut self, dx: i32, dy: i32) -> Result<(), String> {
+        let mut events = Vec::new();
+        if dx != 0 { events.push(InputEvent::new(EV_REL, REL_X, dx)); }
+        if dy != 0 { events.push(InputEvent::new(EV_REL, REL_Y, dy)); }
+        if events.is_empty() { return Ok(()); }
+
+        self.write_frame(events)
+    }
+
+    pub fn button(&mut self, button: Button, pressed: bool) -> Result<(), String> {
+        self.write_frame(vec![InputEvent::new(EV_KEY, button as u32, pressed as i32)])
+    }
+
+    pub fn click(&mut self, button: Button) -> Result<(), String> {
+        self.button(button, true)?;
+        self.button(button, false)
+    }
+
+    /* Whole wheel detents, positive values scroll up and right */
+    pub fn scroll(&mut self, vertical: i32, horizontal: i32) -> Result<(), String> {
+        self.scroll_hi_res(vertical * WHEEL_HI_RES_PER_CLICK, horizontal * WHEEL_HI_RES_PER_CLICK)
+    }
+
+    /* Fractions of a detent, for smooth scrolling */
+    pub fn scroll_smooth(&mut self, vertical: f64, horizontal: f64) -> Result<(), String> {
+        let per_click = WHEEL_HI_RES_PER_CLICK as f64;
+        self.scroll_hi_res((vertical * per_click).round() as i32, (horizontal * per_click).round() as i32)
+    }
+
+    /* Like a high resolution mouse, the legacy wheel events follow once a whole detent has built up */
+    fn scroll_hi_res(&mut self, vertical: i32, horizontal: i32) -> Result<(), String> {
+        let mut events = Vec::new();
+        let axes = [
+            (vertical, &mut self.wheel_remainder.0, REL_WHEEL_HI_RES, REL_WHEEL),
+            (horizontal, &mut self.wheel_remainder.1, REL_HWHEEL_HI_RES, REL_HWHEEL),
+        ];
+        for (amount, remainder, hi_res_code, code) in axes {
+            if amount == 0 { continue; }
+
+            events.push(InputEvent::new(EV_REL, hi_res_code, amount));
+
+            *remainder += amount;
+            let clicks = *remainder / WHEEL_HI_RES_PER_CLICK;
+            if clicks != 0 {
+                *remainder -= clicks * WHEEL_HI_RES_PER_CLICK;
+                events.push(InputEvent::new(EV_REL, code, clicks));
+            }
+        }
+        if events.is_empty() { return Ok(()); }
+
+        self.write_frame(events)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{recording_sink, syn};
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    fn pointer() -> (VirtualPointer, Rc<RefCell<Vec<InputEvent>>>) {
+        let (sink, events) = recording_sink();
+        (VirtualPointer::with_sink(sink), events)
+    }
+
+    #[test]
+    fn moves_and_clicks() {
+        let (mut pointer, events) = pointer();
+
+        pointer.move_by(5, 0).unwrap();
+        pointer.move_by(0, 0).unwrap();
+        pointer.click(Button::Side).unwrap();
+
+        assert_eq!(*events.borrow(), vec![
+            InputEvent::new(EV_REL, REL_X, 5), syn(),
+            InputEvent::new(EV_KEY, BTN_SIDE, 1), syn(),
+            InputEvent::new(EV_KEY, BTN_SIDE, 0), syn(),
+        ]);
+    }
+
+    #[test]
+    fn scrolls_in_detents_and_fractions() {
+        let (mut pointer, events) = pointer();
+
+        pointer.scroll(-1, 0).unwrap();
+        assert_eq!(*events.borrow(), vec![InputEvent::new(EV_REL, REL_WHEEL_HI_RES, -120), InputEvent::new(EV_REL, REL_WHEEL, -1), syn()]);
+        events.borrow_mut().clear();
+
+        /* Half detents only produce a legacy wheel event every other time */
+        pointer.scroll_smooth(0.0, 0.5).unwrap();
+        pointer.scroll_smooth(0.0, 0.5).unwrap();
+        assert_eq!(*events.borrow(), vec![
+            InputEvent::new(EV_REL, REL_HWHEEL_HI_RES, 60), syn(),
+            InputEvent::new(EV_REL, REL_HWHEEL_HI_RES, 60), InputEvent::new(EV_REL, REL_HWHEEL, 1), syn(),
+        ]);
+    }
+}
@@ -0,0 +1,262 @@
+/*
+ * Copyright © 2019 Romeo Calota
+ *
+ * This program is free software; you can redistribute it and/or
+ * modify it under the terms of the GNU Lesser General Public
+ * License as published by the Free Software Foundation; either
+ * version 2 of the licence, or (at your option) any later version.
+ *
+ * This software is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+ * Lesser General Public License for more details.
+ *
+ * You should have received a copy of the GNU Lesser General Public
+ * License along with this program; if not, see <http://www.gnu.org/licenses/>.
+ *
+ * Author: Romeo Calota
+ */
+
+use crate::evdev_device::InputEvent;
+use crate::evdev_uinput::*;
+use crate::uinput_sink::{EventSink, UinputSink};
+use ::std::os::raw::*;
+use std::f64::consts::PI;
+use std::thread;
+use std::time::Duration;
+
+const SLOT_COUNT: usize = 5;
+/* A 100mm x 60mm pad at 40 units per millimeter */
+const WIDTH: i32 = 4000;
+const HEIGHT: i32 = 2400;
+const RESOLUTION: i32 = 40;
+/* Distance between neighbouring fingers of a swipe */
+const FINGER_SPACING: i32 = 600;
+
+const TOOL_CODES: [u32; SLOT_COUNT] = [BTN_TOOL_FINGER, BTN_TOOL_DOUBLETAP, BTN_TOOL_TRIPLETAP, BTN_TOOL_QUADTAP, BTN_TOOL_QUINTTAP];
+
+/* A scriptable clickpad, for exercising gesture recognition without hardware */
+pub struct VirtualTouchpad
+{
+    sink: Box<dyn EventSink>,
+    devnode: Option<String>,
+    /* Time between the frames of a scripted swipe or pinch; libinput needs to see them spread out */
+    pub frame_interval: Duration,
+
+    /* Tracking ids of the fingers down, by slot */
+    touches: Vec<i32>,
+    next_tracking_id: i32,
+}
+
+impl VirtualTouchpad {
+    pub fn new(device_name: &str) -> Result<VirtualTouchpad, String> {
+        let sink = UinputSink::new(device_name, |evdev_dev| unsafe {
+            libevdev_enable_property(evdev_dev, INPUT_PROP_POINTER);
+            libevdev_enable_property(evdev_dev, INPUT_PROP_BUTTONPAD);
+
+            libevdev_enable_event_type(evdev_dev, EV_KEY);
+            for code in [BTN_LEFT, BTN_TOUCH].iter().chain(TOOL_CODES.iter()) {
+                libevdev_enable_event_code(evdev_dev, EV_KEY, *code, 0 as *const c_void);
+            }
+
+            libevdev_enable_event_type(evdev_dev, EV_ABS);
+            let axes = [
+                (ABS_X, 0, WIDTH, RESOLUTION),
+                (ABS_Y, 0, HEIGHT, RESOLUTION),
+                (ABS_MT_SLOT, 0, SLOT_COUNT as i32 - 1, 0),
+                (ABS_MT_TRACKING_ID, 0, 65535, 0),
+                (ABS_MT_POSITION_X, 0, WIDTH, RESOLUTION),
+                (ABS_MT_POSITION_Y, 0, HEIGHT, RESOLUTION),
+            ];
+            for (code, minimum, maximum, resolution) in axes.iter() {
+                let absinfo = input_absinfo { value: 0, minimum: *minimum, maximum: *maximum, fuzz: 0, flat: 0, resolution: *resolution };
+                libevdev_enable_event_code(evdev_dev, EV_ABS, *code, &absinfo as *const input_absinfo as *const c_void);
+            }
+        })?;
+
+        let devnode = sink.devnode();
+        let mut touchpad = VirtualTouchpad::with_sink(Box::new(sink));
+        touchpad.devnode = devnode;
+        touchpad.frame_interval = Duration::from_millis(10);
+
+        Ok(touchpad)
+    }
+
+    /* Frames are written back to back, without waiting in between */
+    pub fn with_sink(sink: Box<dyn EventSink>) -> VirtualTouchpad {
+        VirtualTouchpad {
+            sink,
+            devnode: None,
+            frame_interval: Duration::from_millis(0),
+            touches: Vec::new(),
+            next_tracking_id: 0,
+        }
+    }
+
+    /* The /dev/input node of the virtual device, for handing to libinput */
+    pub fn devnode(&self) -> Option<String> {
+        self.devnode.clone()
+    }
+
+    /* Size of the pad in device units */
+    pub fn size(&self) -> (i32, i32) {
+        (WIDTH, HEIGHT)
+    }
+
+    /* Puts fingers at positions, in slot order; new fingers touch down and missing ones lift */
+    pub fn touch(&mut self, positions: &[(i32, i32)]) -> Result<(), String> {
+        if positions.len() > SLOT_COUNT {
+            return Err(format!("A touchpad tracks at most {} fingers", SLOT_COUNT));
+        }
+
+        let previous_count = self.touches.len();
+        let mut events = Vec::new();
+        for slot in 0..previous_count.max(positions.len()) {
+            events.push(InputEvent::new(EV_ABS, ABS_MT_SLOT, slot as i32));
+
+            match positions.get(slot) {
+                Some((x, y)) => {
+                    if slot >= previous_count {
+                        self.touches.push(self.next_tracking_id);
+                        events.push(InputEvent::new(EV_ABS, ABS_MT_TRACKING_ID, self.next_tracking_id));
+                        self.next_tracking_id = (self.next_tracking_id + 1) % 65536;
+                    }
+                    events.push(InputEvent::new(EV_ABS, ABS_MT_POSITION_X, *x));
+                    events.push(InputEvent::new(EV_ABS, ABS_MT_POSITION_Y, *y));
+                },
+                None => events.push(InputEvent::new(EV_ABS, ABS_MT_TRACKING_ID, -1)),
+            }
+        }
+        self.touches.truncate(positions.len());
+
+        if let Some((x, y)) = positions.first() {
+            events.push(InputEvent::new(EV_ABS, ABS_X, *x));
+            events.push(InputEvent::new(EV_ABS, ABS_Y, *y));
+        }
+
+        if previous_count != positions.len() {
+            if previous_count == 0 { events.push(InputEvent::new(EV_KEY, BTN_TOUCH, 1)); }
+            if positions.is_empty() { events.push(InputEvent::new(EV_KEY, BTN_TOUCH, 0)); }
+            if previous_count > 0 { events.push(InputEvent::new(EV_KEY, TOOL_CODES[previous_count - 1], 0)); }
+            if !positions.is_empty() { events.push(InputEvent::new(EV_KEY, TOOL_CODES[positions.len() - 1], 1)); }
+        }
+
+        events.push(InputEvent::new(EV_SYN, SYN_REPORT, 0));
+        self.sink.write(&events)?;
+
+        if self.frame_interval > Duration::from_millis(0) {
+            thread::sleep(self.frame_interval);
+        }
+
+        Ok(())
+    }
+
+    /* Lifts every finger */
+    pub fn lift(&mut self) -> Result<(), String> {
+        self.touch(&[])
+    }
+
+    /* Fingers side by side around start move by delta over steps frames, then lift */
+    pub fn swipe(&mut self, finger_count: usize, start: (i32, i32), delta: (i32, i32), steps: u32) -> Result<(), String> {
+        let steps = steps.max(1);
+        for step in 0..=steps {
+            let offset = (delta.0 * step as i32 / steps as i32, delta.1 * step as i32 / steps as i32);
+            let positions: Vec<(i32, i32)> = (0..finger_count)
+                .map(|finger| {
+                    let spread = (2 * finger as i32 - finger_count as i32 + 1) * FINGER_SPACING / 2;
+                    (start.0 + spread + offset.0, start.1 + offset.1)
+                })
+                .collect();
+            self.touch(&positions)?;
+        }
+
+        self.lift()
+    }
+
+    /* Fingers evenly spread on a circle around center, going from one radius to the other, then lift */
+    pub fn pinch(&mut self, finger_count: usize, center: (i32, i32), start_radius: i32, end_radius: i32, steps: u32) -> Result<(), String> {
+        let steps = steps.max(1);
+        for step in 0..=steps {
+            let radius = (start_radius + (end_radius - start_radius) * step as i32 / steps as i32) as f64;
+            let positions: Vec<(i32, i32)> = (0..finger_count)
+                .map(|finger| {
+                    let angle = 2.0 * PI * finger as f64 / finger_count as f64;
+                    (center.0 + (radius * angle.cos()).round() as i32, center.1 + (radius * angle.sin()).round() as i32)
+                })
+                .collect();
+            self.touch(&positions)?;
+        }
+
+        self.lift()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{recording_sink, syn};
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    fn touchpad() -> (VirtualTouchpad, Rc<RefCell<Vec<InputEvent>>>) {
+        let (sink, events) = recording_sink();
+        (VirtualTouchpad::with_sink(sink), events)
+    }
+
+    fn abs(code: u32, value: i32) -> InputEvent {
+        InputEvent::new(EV_ABS, code, value)
+    }
+
+    fn key(code: u32, value: i32) -> InputEvent {
+        InputEvent::new(EV_KEY, code, value)
+    }
+
+    #[test]
+    fn touches_and_lifts_fingers() {
+        let (mut touchpad, events) = touchpad();
+
+        touchpad.touch(&[(100, 200), (300, 200)]).unwrap();
+        assert_eq!(*events.borrow(), vec![
+            abs(ABS_MT_SLOT, 0), abs(ABS_MT_TRACKING_ID, 0), abs(ABS_MT_POSITION_X, 100), abs(ABS_MT_POSITION_Y, 200),
+            abs(ABS_MT_SLOT, 1), abs(ABS_MT_TRACKING_ID, 1), abs(ABS_MT_POSITION_X, 300), abs(ABS_MT_POSITION_Y, 200),
+            abs(ABS_X, 100), abs(ABS_Y, 200),
+            key(BTN_TOUCH, 1), key(BTN_TOOL_DOUBLETAP, 1), syn(),
+        ]);
+        events.borrow_mut().clear();
+
+        touchpad.touch(&[(110, 200)]).unwrap();
+        touchpad.lift().unwrap();
+        assert_eq!(*events.borrow(), vec![
+            abs(ABS_MT_SLOT, 0), abs(ABS_MT_POSITION_X, 110), abs(ABS_MT_POSITION_Y, 200),
+            abs(ABS_MT_SLOT, 1), abs(ABS_MT_TRACKING_ID, -1),
+            abs(ABS_X, 110), abs(ABS_Y, 200),
+            key(BTN_TOOL_DOUBLETAP, 0), key(BTN_TOOL_FINGER, 1), syn(),
+            abs(ABS_MT_SLOT, 0), abs(ABS_MT_TRACKING_ID, -1),
+            key(BTN_TOUCH, 0), key(BTN_TOOL_FINGER, 0), syn(),
+        ]);
+
+        assert!(touchpad.touch(&[(0, 0); 6]).is_err());
+    }
+
+    #[test]
+    fn scripts_swipes_and_pinches() {
+        let (mut touchpad, events) = touchpad();
+
+        touchpad.swipe(3, (2000, 1200), (900, 0), 3).unwrap();
+        let x_positions: Vec<i32> = events.borrow().iter()
+            .filter(|e| e.event_type == EV_ABS && e.code == ABS_X)
+            .map(|e| e.value)
+            .collect();
+        assert_eq!(x_positions, vec![1400, 1700, 2000, 2300]);
+        assert_eq!(events.borrow().iter().filter(|e| **e == key(BTN_TOOL_TRIPLETAP, 1)).count(), 1);
+        assert_eq!(events.borrow()[events.borrow().len() - 2], key(BTN_TOOL_TRIPLETAP, 0));
+        events.borrow_mut().clear();
+
+        touchpad.pinch(4, (2000, 1200), 200, 600, 2).unwrap();
+        let first_finger_x: Vec<i32> = events.borrow().iter()
+            .filter(|e| e.event_type == EV_ABS && e.code == ABS_X)
+            .map(|e| e.value)
+            .collect();
+        assert_eq!(first_finger_x, vec![2200, 2400, 2600]);
+    }
+}
@@ -9,6 +9,9 @@ use gtk::*;
 
 use kinesix;
 
+use std::cell::RefCell;
+use std::rc::Rc;
+
 fn swipe(dir: kinesix::SwipeDirection, finger_count: i32) {
     println!("SWIPE: {:?}, {} fingers", dir, finger_count)
 }
@@ -18,12 +21,44 @@ fn pinch(t: kinesix::PinchType, finger_count: i32) {
 }
 
 fn main() {
-    let mut b = kinesix::KinesixBackend::new(swipe, pinch);
+    /* Shared with the GTK callbacks, it must not move once polling started */
+    let backend = Rc::new(RefCell::new(kinesix::KinesixBackend::new(swipe, pinch)));
+    let mut b = backend.borrow_mut();
+    if let Some(config_path) = kinesix::config::Config::default_path() {
+        if config_path.exists() {
+            match kinesix::config::Config::load(&config_path) {
+                Ok(config) => b.apply_config(&config),
+                Err(e) => println!("{}", e),
+            }
+        }
+    }
     let devices = b.get_valid_device_list();
     println!("{:?}", devices);
-    b.set_active_device(&devices[0]);
+    /* Tablet pads are opened next to the touchpad anyway */
+    match devices.iter().find(|d| d.kind == kinesix::device::DeviceKind::Touchpad).or_else(|| devices.first()) {
+        Some(device) => b.set_active_device(device),
+        None => println!("No touchpad or tablet pad found"),
+    }
     b.start_polling();
 
+    let service = kinesix::dbus::DBusConnection::session().and_then(|connection| b.export_dbus_service(connection));
+    if let Err(e) = service {
+        println!("{}", e);
+    }
+    match kinesix::ipc::default_socket_path() {
+        Some(path) => {
+            if let Err(e) = b.listen_ipc(&path) {
+                println!("{}", e);
+            }
+        },
+        None => println!("XDG_RUNTIME_DIR is not set, kinesixctl can't reach this instance"),
+    }
+    match kinesix::focused_window::X11WindowContext::open(None) {
+        Ok(context) => b.set_window_context(Some(Box::new(context))),
+        Err(e) => println!("{}, [app] bindings are ignored", e),
+    }
+    drop(b);
+
     let application = Application::new(
         Some("com.github.kicsyromy.kinesix"),
         ApplicationFlags::empty()
@@ -40,6 +75,30 @@ fn main() {
     header.set_show_close_button(true);
     header.pack_end(&device_chooser);
 
+    /* Draw a shape with the stroke button held, or on the touchscreen, to store it under the given name */
+    let shape_name = Entry::new();
+    shape_name.set_placeholder_text(Some("Shape name"));
+    let record_shape = Button::new_with_label("Record shape");
+    header.pack_start(&shape_name);
+    header.pack_start(&record_shape);
+
+    let recording_backend = backend.clone();
+    record_shape.connect_clicked(move |_| {
+        let name = shape_name.get_text().map(|t| t.to_string()).unwrap_or_default();
+        if name.trim().is_empty() { return; }
+
+        recording_backend.borrow_mut().record_template(name.trim(), |template| {
+            match kinesix::config::Config::default_path() {
+                Some(config_path) => {
+                    if let Err(e) = kinesix::config::Config::save_template(&config_path, &template) {
+                        println!("{}", e);
+                    }
+                },
+                None => println!("Nowhere to store shape \"{}\"", template.name),
+            }
+        });
+    });
+
     main_window.set_titlebar(Some(&header));
 
     let window_ptr = &main_window as *const Window as *const ::std::os::raw::c_void as usize;